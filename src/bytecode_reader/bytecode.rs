@@ -4,20 +4,66 @@ use std::cmp;
 use std::collections::HashMap;
 use std::fmt;
 
+use crate::error::PellucidError;
 use crate::tools::utils::remove_0x;
 
+use super::hardfork::Hardfork;
+use super::metadata::get_metadata_start;
 use super::opcode::Opcode;
 use super::vopcode::Vopcode;
 
+pub type LibraryHash = [u8; 17];
+
+const LIBRARY_PLACEHOLDER_PREFIX: &str = "__$";
+const LIBRARY_PLACEHOLDER_SUFFIX: &str = "$__";
+const LIBRARY_PLACEHOLDER_LENGTH: usize = 40; // hex chars, the size of an address
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DataKind {
+    Metadata,    // solc CBOR trailer
+    Unreachable, // bytes no jump can land on (constructor arguments, appended code, ...)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DataSection {
+    pub offset: usize,
+    pub kind: DataKind,
+    pub bytes: Vec<u8>,
+}
+
+impl DataSection {
+    pub fn to_string(&self) -> String {
+        let mut res: String = String::from("");
+        let name: &str = match self.kind {
+            DataKind::Metadata => "METADATA",
+            DataKind::Unreachable => "DATA",
+        };
+        for (i, chunk) in self.bytes.chunks(32).enumerate() {
+            res.push_str(&format!(
+                "{:04x} {} 0x{}\n",
+                self.offset + i * 32,
+                name,
+                hex::encode(chunk)
+            ));
+        }
+        return res;
+    }
+}
+
 #[derive(Default, Debug, PartialEq)]
 pub struct Bytecode {
-    vopcodes: Vec<Vopcode>,
+    vopcodes: Vec<Vopcode>,             // executable code only
     pc_to_index: HashMap<usize, usize>, // line => index of corresponding VOpcode in `vopcodes`
+    data_sections: Vec<DataSection>,
+    hardfork: Hardfork,
 }
 
 impl fmt::Display for Bytecode {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        let res: String = stringify_vopcodes(&self.vopcodes);
+        let mut res: String = stringify_vopcodes(&self.vopcodes);
+        for data_section in &self.data_sections {
+            res.push_str(&data_section.to_string());
+        }
         formatter.write_str(&res)?;
         Ok(())
     }
@@ -28,22 +74,35 @@ impl Bytecode {
         return Bytecode {
             vopcodes: Vec::new(),
             pc_to_index: HashMap::new(),
+            data_sections: Vec::new(),
+            hardfork: Hardfork::default(),
         };
     }
 
-    pub fn from(raw_bytecode: &str) -> Option<Self> {
-        let vec_bytecode: Vec<u8> = match hex::decode(remove_0x(&raw_bytecode)) {
+    pub fn from(raw_bytecode: &str, hardfork: Hardfork) -> Result<Self, PellucidError> {
+        let (hex_bytecode, library_placeholders) =
+            strip_library_placeholders(remove_0x(&raw_bytecode));
+        let vec_bytecode: Vec<u8> = match hex::decode(hex_bytecode) {
             Ok(res) => res,
-            Err(_) => return None,
+            Err(error) => return Err(PellucidError::InvalidHex(error.to_string())),
         };
+        let mut bytecode: Bytecode = Bytecode::from_bytes(&vec_bytecode, hardfork);
+        bytecode.link_library_placeholders(&library_placeholders, 0);
+        return Ok(bytecode);
+    }
 
+    pub fn from_bytes(vec_bytecode: &[u8], hardfork: Hardfork) -> Self {
         let mut bytecode: Bytecode = Bytecode::new();
+        bytecode.hardfork = hardfork;
 
-        let bytecode_length = vec_bytecode.len();
+        // the metadata trailer is never executed, don't even decode it
+        let metadata_start: Option<usize> = get_metadata_start(vec_bytecode);
+        let bytecode_length = metadata_start.unwrap_or(vec_bytecode.len());
+        let mut vopcodes: Vec<Vopcode> = Vec::new();
         let mut pc: usize = 0;
         while pc < bytecode_length {
             let origin_line = pc;
-            let opcode: Opcode = Opcode::from(vec_bytecode[pc]);
+            let opcode: Opcode = Opcode::from_with_hardfork(vec_bytecode[pc], hardfork);
             pc += 1;
 
             let mut item: Option<U256> = None;
@@ -54,10 +113,62 @@ impl Bytecode {
                 pc = item_end;
             }
 
-            bytecode.insert_vopcode(Vopcode::new(opcode, item, origin_line));
+            vopcodes.push(Vopcode::new(opcode, item, origin_line));
+        }
+
+        let code_end: usize = find_code_end(&vopcodes, bytecode_length);
+        for vopcode in vopcodes {
+            if vopcode.pc >= code_end {
+                break;
+            }
+            bytecode.insert_vopcode(vopcode);
+        }
+        if code_end < bytecode_length {
+            bytecode.data_sections.push(DataSection {
+                offset: code_end,
+                kind: DataKind::Unreachable,
+                bytes: vec_bytecode[code_end..bytecode_length].to_vec(),
+            });
+        }
+        if let Some(metadata_start) = metadata_start {
+            bytecode.data_sections.push(DataSection {
+                offset: metadata_start,
+                kind: DataKind::Metadata,
+                bytes: vec_bytecode[metadata_start..].to_vec(),
+            });
+        }
+
+        return bytecode;
+    }
+
+    pub fn from_vopcodes(vopcodes: Vec<Vopcode>, hardfork: Hardfork) -> Self {
+        let mut bytecode: Bytecode = Bytecode::new();
+        bytecode.hardfork = hardfork;
+        for vopcode in vopcodes {
+            bytecode.insert_vopcode(vopcode);
         }
+        return bytecode;
+    }
 
-        return Some(bytecode);
+    // Turns the PUSH20 of each placeholder into a symbolic library address. `code_start` is the
+    // offset of this bytecode in the one the placeholders were found in.
+    pub fn link_library_placeholders(
+        &mut self,
+        library_placeholders: &[(usize, LibraryHash)],
+        code_start: usize,
+    ) {
+        for (offset, hash) in library_placeholders {
+            if *offset <= code_start {
+                continue;
+            }
+            if let Some(index) = self.pc_to_index.get(&(offset - code_start - 1)) {
+                let vopcode: &mut Vopcode = &mut self.vopcodes[*index];
+                if vopcode.opcode == (Opcode::PUSH { item_size: 20 }) {
+                    vopcode.opcode = Opcode::LIBRARY { hash: *hash };
+                    vopcode.value = None;
+                }
+            }
+        }
     }
 
     pub fn insert_vopcode(&mut self, vopcode: Vopcode) {
@@ -69,10 +180,22 @@ impl Bytecode {
         return &self.vopcodes[self.pc_to_index[&pc]];
     }
 
+    pub fn get_hardfork(&self) -> Hardfork {
+        return self.hardfork;
+    }
+
     pub fn get_vopcodes(&self) -> &[Vopcode] {
         return &self.vopcodes;
     }
 
+    pub fn code_section(&self) -> &[Vopcode] {
+        return &self.vopcodes;
+    }
+
+    pub fn data_sections(&self) -> &[DataSection] {
+        return &self.data_sections;
+    }
+
     pub fn get_last_pc(&self) -> usize {
         return self.vopcodes[self.vopcodes.len() - 1].pc;
     }
@@ -93,6 +216,55 @@ impl Bytecode {
     }
 }
 
+// Unlinked bytecode contains `__$<34 hex chars>$__` instead of library addresses. They are
+// replaced by zeros, and their byte offsets are returned with the hash they contain.
+pub fn strip_library_placeholders(hex_bytecode: &str) -> (String, Vec<(usize, LibraryHash)>) {
+    let mut stripped_bytecode: String = String::from(hex_bytecode);
+    let mut library_placeholders: Vec<(usize, LibraryHash)> = Vec::new();
+    let mut search_start: usize = 0;
+    while let Some(position) = stripped_bytecode[search_start..].find(LIBRARY_PLACEHOLDER_PREFIX) {
+        let start: usize = search_start + position;
+        let end: usize = start + LIBRARY_PLACEHOLDER_LENGTH;
+        search_start = start + LIBRARY_PLACEHOLDER_PREFIX.len();
+        // compared as bytes: any other character may be found in the input, the end of a
+        // placeholder could fall inside one (the hex decoding rejects it later)
+        if end > stripped_bytecode.len()
+            || start % 2 != 0
+            || !stripped_bytecode.as_bytes()[..end].ends_with(LIBRARY_PLACEHOLDER_SUFFIX.as_bytes())
+        {
+            continue;
+        }
+        let hash_hex: &str = &stripped_bytecode
+            [start + LIBRARY_PLACEHOLDER_PREFIX.len()..end - LIBRARY_PLACEHOLDER_SUFFIX.len()];
+        let mut hash: LibraryHash = [0; 17];
+        if hex::decode_to_slice(hash_hex, &mut hash).is_err() {
+            continue;
+        }
+        library_placeholders.push((start / 2, hash));
+        stripped_bytecode.replace_range(start..end, &"0".repeat(LIBRARY_PLACEHOLDER_LENGTH));
+        search_start = end;
+    }
+    return (stripped_bytecode, library_placeholders);
+}
+
+// Nothing can jump past the last JUMPDEST, so the code ends at the first terminating opcode after it.
+fn find_code_end(vopcodes: &[Vopcode], bytecode_length: usize) -> usize {
+    let last_jumpdest: usize = vopcodes
+        .iter()
+        .rposition(|vopcode| vopcode.opcode == Opcode::JUMPDEST)
+        .unwrap_or(0);
+    for vopcode in &vopcodes[last_jumpdest..] {
+        let is_terminating: bool = match vopcode.opcode {
+            Opcode::JUMP | Opcode::INVALID { code: _ } => true,
+            opcode => opcode.is_exiting(),
+        };
+        if is_terminating {
+            return vopcode.get_next_pc();
+        }
+    }
+    return bytecode_length;
+}
+
 pub fn stringify_vopcodes(vopcodes: &[Vopcode]) -> String {
     let mut res: String = String::from("");
     for vopcode in vopcodes {
@@ -105,6 +277,7 @@ pub fn stringify_vopcodes(vopcodes: &[Vopcode]) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tools::utils::read_file;
     use rand;
     use std::fs;
 
@@ -125,7 +298,8 @@ mod tests {
         let bytecode_string: String =
             fs::read_to_string("./contracts/simple/contract_0/bytecode.txt")
                 .expect("Unable to read file.");
-        let bytecode_test: Bytecode = Bytecode::from(&bytecode_string).unwrap();
+        let bytecode_test: Bytecode =
+            Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
         assert_eq!(bytecode_ref, bytecode_test, "Bytecode mismatch");
     }
 
@@ -134,8 +308,80 @@ mod tests {
         let bytecode_ref: Bytecode = read_opcodes_file("./contracts/complex/opcodes.txt");
         let bytecode_string: String =
             fs::read_to_string("./contracts/complex/bytecode.txt").expect("Unable to read file.");
-        let bytecode_test: Bytecode = Bytecode::from(&bytecode_string).unwrap();
-        assert_eq!(bytecode_ref, bytecode_test, "Bytecode mismatch");
+        let bytecode_test: Bytecode =
+            Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
+        // the reference disassembles the whole string, data included
+        let code_length: usize = bytecode_test.code_section().len();
+        assert_eq!(
+            &bytecode_ref.get_vopcodes()[..code_length],
+            bytecode_test.code_section(),
+            "Bytecode mismatch"
+        );
+        assert_eq!(
+            bytecode_test.data_sections()[0].offset,
+            bytecode_ref.get_vopcodes()[code_length].pc
+        );
+    }
+
+    #[test]
+    fn test_data_sections() {
+        let raw_bytecode: String = read_file("./contracts/simple/contract_0/bytecode.txt");
+        let bytecode: Bytecode = Bytecode::from(&raw_bytecode, Hardfork::default()).unwrap();
+        let data_sections: &[DataSection] = bytecode.data_sections();
+        let metadata: &DataSection = data_sections.last().unwrap();
+        assert_eq!(metadata.kind, DataKind::Metadata);
+        assert_eq!(
+            metadata.offset + metadata.bytes.len(),
+            raw_bytecode.trim().len() / 2
+        );
+        let data_start: usize = data_sections[0].offset;
+        assert!(bytecode
+            .code_section()
+            .iter()
+            .all(|vopcode| vopcode.pc < data_start));
+        assert!(bytecode.to_string().contains(" METADATA 0x"));
+
+        // no JUMPDEST after the REVERT: the remaining bytes are data
+        let bytecode: Bytecode =
+            Bytecode::from("0x60006000fd5b00fe1234", Hardfork::default()).unwrap();
+        assert_eq!(bytecode.get_last_pc(), 6);
+        assert_eq!(
+            bytecode.data_sections(),
+            &[DataSection {
+                offset: 7,
+                kind: DataKind::Unreachable,
+                bytes: vec![0xfe, 0x12, 0x34],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_library_placeholders() {
+        // memory[0x00] = <library address>, return[0x00::0x20]
+        let bytecode: Bytecode = Bytecode::from(
+            "0x73__$1234567890abcdef1234567890abcdef12$__60005260206000f3",
+            Hardfork::default(),
+        )
+        .unwrap();
+        let mut hash: LibraryHash = [0; 17];
+        hex::decode_to_slice("1234567890abcdef1234567890abcdef12", &mut hash).unwrap();
+        assert_eq!(bytecode.get_vopcode_at(0).opcode, Opcode::LIBRARY { hash });
+        assert_eq!(
+            bytecode.get_vopcode_at(21).opcode,
+            Opcode::PUSH { item_size: 1 }
+        );
+        assert!(bytecode
+            .to_string()
+            .starts_with("0000 PUSH20 __$1234567890abcdef1234567890abcdef12$__\n"));
+    }
+
+    #[test]
+    fn test_library_placeholder_with_non_ascii_characters() {
+        let raw_bytecode: String = format!("6000__${}", "é".repeat(20));
+        assert!(matches!(
+            Bytecode::from(&raw_bytecode, Hardfork::default()),
+            Err(PellucidError::InvalidHex(_))
+        ));
     }
 
     #[test]
@@ -147,26 +393,51 @@ mod tests {
         let bytecode1 = hex::encode(random_vec);
         let mut bytecode2 = "0x".to_owned();
         bytecode2.push_str(&bytecode1);
-        assert_eq!(Bytecode::from(&bytecode1), Bytecode::from(&bytecode2));
+        assert_eq!(
+            Bytecode::from(&bytecode1, Hardfork::default()),
+            Bytecode::from(&bytecode2, Hardfork::default())
+        );
     }
 
     #[test]
     fn test_invalid_bytecode() {
-        assert!(
-            Bytecode::from("abc").is_none(),
+        assert_eq!(
+            Bytecode::from("abc", Hardfork::default()),
+            Err(PellucidError::InvalidHex(String::from(
+                "Odd number of digits"
+            ))),
             "Odd size bytecode without 0x did not panic"
         );
         assert!(
-            Bytecode::from("0xabc").is_none(),
+            Bytecode::from("0xabc", Hardfork::default()).is_err(),
             "Odd size bytecode with 0x did not panic"
         );
         assert!(
-            Bytecode::from("abcg").is_none(),
+            Bytecode::from("abcg", Hardfork::default()).is_err(),
             "Bytecode with invalid character without 0x did not panic"
         );
         assert!(
-            Bytecode::from("0xabcg").is_none(),
+            Bytecode::from("0xabcg", Hardfork::default()).is_err(),
             "Bytecode with invalid character with 0x did not panic"
         );
     }
+
+    #[test]
+    fn test_shanghai_cancun_opcodes() {
+        let bytecode: Bytecode = Bytecode::from("0x5f5c5d5e494a", Hardfork::default()).unwrap();
+        let opcodes: Vec<Opcode> = bytecode.get_vopcodes().iter().map(|v| v.opcode).collect();
+        assert_eq!(
+            opcodes,
+            vec![
+                Opcode::PUSH0,
+                Opcode::TLOAD,
+                Opcode::TSTORE,
+                Opcode::MCOPY,
+                Opcode::BLOBHASH,
+                Opcode::BLOBBASEFEE
+            ]
+        );
+        assert_eq!(bytecode.get_vopcode_at(1).pc, 1);
+        assert!(opcodes.iter().all(|opcode| !opcode.is_invalid()));
+    }
 }
@@ -0,0 +1,151 @@
+use primitive_types::U256;
+
+use crate::error::PellucidError;
+
+use super::bytecode::{Bytecode, LibraryHash};
+use super::hardfork::Hardfork;
+use super::opcode::Opcode;
+
+// Creation (init) bytecode, as found in a deployment transaction:
+// [constructor][runtime bytecode][ABI encoded constructor arguments]
+#[derive(Debug, PartialEq)]
+pub struct CreationBytecode {
+    pub constructor: Bytecode,
+    pub runtime: Bytecode,
+    pub runtime_offset: usize,
+    pub constructor_arguments: Vec<u8>,
+}
+
+impl CreationBytecode {
+    pub fn from_bytes(raw_bytecode: &[u8], hardfork: Hardfork) -> Result<Self, PellucidError> {
+        let full_bytecode: Bytecode = Bytecode::from_bytes(raw_bytecode, hardfork);
+        let (runtime_start, runtime_end) =
+            match find_deployed_code(&full_bytecode, raw_bytecode.len()) {
+                Some(location) => location,
+                None => return Err(PellucidError::NotCreationBytecode),
+            };
+        return Ok(CreationBytecode {
+            constructor: Bytecode::from_bytes(&raw_bytecode[..runtime_start], hardfork),
+            runtime: Bytecode::from_bytes(&raw_bytecode[runtime_start..runtime_end], hardfork),
+            runtime_offset: runtime_start,
+            constructor_arguments: raw_bytecode[runtime_end..].to_vec(),
+        });
+    }
+
+    // Placeholder offsets are relative to the whole creation bytecode.
+    pub fn link_library_placeholders(&mut self, library_placeholders: &[(usize, LibraryHash)]) {
+        self.constructor
+            .link_library_placeholders(library_placeholders, 0);
+        self.runtime
+            .link_library_placeholders(library_placeholders, self.runtime_offset);
+    }
+
+    // Static arguments are encoded as one word each, dynamic ones as an offset to their content.
+    pub fn get_constructor_argument_words(&self) -> Vec<U256> {
+        return self
+            .constructor_arguments
+            .chunks(32)
+            .map(U256::from_big_endian)
+            .collect();
+    }
+}
+
+// Looks for the deployer pattern `CODECOPY(dest, offset, size) ... RETURN(_, size)` with constant
+// offset and size, and returns the (start, end) of the copied code.
+fn find_deployed_code(bytecode: &Bytecode, bytecode_length: usize) -> Option<(usize, usize)> {
+    // only constants are tracked, the stack is forgotten at each block start
+    let mut stack: Vec<Option<U256>> = Vec::new();
+    let mut copied_code: Option<(U256, U256)> = None;
+    for vopcode in bytecode.get_vopcodes() {
+        match vopcode.opcode {
+            Opcode::JUMPDEST => stack.clear(),
+            Opcode::PUSH { item_size: _ } => stack.push(vopcode.value),
+            Opcode::PUSH0 => stack.push(Some(U256::zero())),
+            Opcode::DUP { depth } => {
+                let value: Option<U256> = if depth <= stack.len() {
+                    stack[stack.len() - depth]
+                } else {
+                    None
+                };
+                stack.push(value);
+            }
+            Opcode::SWAP { depth } => {
+                if depth < stack.len() {
+                    let top: usize = stack.len() - 1;
+                    stack.swap(top, top - depth);
+                } else {
+                    stack.clear();
+                }
+            }
+            Opcode::CODECOPY => {
+                let args: Vec<Option<U256>> = pop_args(&mut stack, 3);
+                if let (Some(offset), Some(size)) = (args[1], args[2]) {
+                    copied_code = Some((offset, size));
+                }
+            }
+            Opcode::RETURN => {
+                let args: Vec<Option<U256>> = pop_args(&mut stack, 2);
+                if let (Some((offset, size)), Some(returned_size)) = (copied_code, args[1]) {
+                    let end: U256 = offset.saturating_add(size);
+                    if size == returned_size && end <= U256::from(bytecode_length) {
+                        return Some((offset.as_usize(), end.as_usize()));
+                    }
+                }
+            }
+            opcode => {
+                pop_args(&mut stack, opcode.stack_input());
+                for _ in 0..opcode.stack_output() {
+                    stack.push(None);
+                }
+                if opcode.is_jump() || opcode.is_exiting() {
+                    stack.clear();
+                    copied_code = None;
+                }
+            }
+        }
+    }
+    return None;
+}
+
+// Top of the stack first, unknown when below what is tracked.
+fn pop_args(stack: &mut Vec<Option<U256>>, n: usize) -> Vec<Option<U256>> {
+    let mut args: Vec<Option<U256>> = Vec::new();
+    for _ in 0..n {
+        args.push(stack.pop().flatten());
+    }
+    return args;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // constructor: memory[0x40] = 0x80, codecopy(0, 0x11, 9), return(0, 9)
+    // runtime: storage[0] = 1 + 2
+    // constructor argument: 0x2a
+    const CREATION_BYTECODE: &str =
+        "608060405260098060116000396000f3fe600160020160005500000000000000000000000000000000000000000000000000000000000000002a";
+
+    #[test]
+    fn test_split_creation_bytecode() {
+        let raw_bytecode: Vec<u8> = hex::decode(CREATION_BYTECODE).unwrap();
+        let creation: CreationBytecode =
+            CreationBytecode::from_bytes(&raw_bytecode, Hardfork::default()).unwrap();
+        assert_eq!(creation.constructor.get_last_pc(), 0x0f);
+        assert_eq!(creation.runtime_offset, 0x11);
+        assert_eq!(
+            creation.runtime,
+            Bytecode::from("600160020160005500", Hardfork::default()).unwrap()
+        );
+        assert_eq!(
+            creation.get_constructor_argument_words(),
+            vec![U256::from(0x2a)]
+        );
+
+        // runtime bytecode does not deploy anything
+        assert_eq!(
+            CreationBytecode::from_bytes(&raw_bytecode[0x11..], Hardfork::default()),
+            Err(PellucidError::NotCreationBytecode)
+        );
+    }
+}
@@ -0,0 +1,488 @@
+use primitive_types::U256;
+use std::{cmp, fmt};
+
+use super::bytecode::Bytecode;
+use super::hardfork::Hardfork;
+use super::opcode::Opcode;
+use super::vopcode::Vopcode;
+use crate::error::PellucidError;
+use crate::tools::utils::remove_0x;
+
+pub const EOF_MAGIC: [u8; 2] = [0xef, 0x00];
+pub const NON_RETURNING_FUNCTION: u8 = 0x80;
+
+const KIND_TYPES: u8 = 0x01;
+const KIND_CODE: u8 = 0x02;
+const KIND_CONTAINER: u8 = 0x03;
+const KIND_DATA: u8 = 0xff;
+const TERMINATOR: u8 = 0x00;
+
+// Legacy opcodes that are invalid in EOF code sections (jumps, code and gas introspection, legacy
+// calls and creations)
+const REJECTED_OPCODES: [u8; 16] = [
+    0x38, 0x39, 0x3b, 0x3c, 0x3f, 0x56, 0x57, 0x58, 0x5a, 0xf0, 0xf1, 0xf2, 0xf4, 0xf5, 0xfa, 0xff,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeSection {
+    pub inputs: u8,
+    pub outputs: u8,
+    pub max_stack_increase: u16,
+}
+
+impl TypeSection {
+    pub fn is_returning(&self) -> bool {
+        return self.outputs != NON_RETURNING_FUNCTION;
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EofContainer {
+    pub version: u8,
+    pub types: Vec<TypeSection>,
+    pub code_sections: Vec<Bytecode>, // pcs are relative to the start of each section
+    pub container_sections: Vec<Vec<u8>>,
+    pub data: Vec<u8>,
+}
+
+pub fn is_eof(bytes: &[u8]) -> bool {
+    return bytes.starts_with(&EOF_MAGIC);
+}
+
+fn malformed(reason: &str) -> PellucidError {
+    return PellucidError::EofMalformed(reason.to_owned());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, size: usize) -> Result<&'a [u8], PellucidError> {
+        if self.position + size > self.bytes.len() {
+            return Err(malformed("unexpected end of container"));
+        }
+        self.position += size;
+        return Ok(&self.bytes[self.position - size..self.position]);
+    }
+
+    fn read_u8(&mut self) -> Result<u8, PellucidError> {
+        return Ok(self.read_bytes(1)?[0]);
+    }
+
+    fn read_u16(&mut self) -> Result<u16, PellucidError> {
+        let bytes: &[u8] = self.read_bytes(2)?;
+        return Ok(u16::from_be_bytes([bytes[0], bytes[1]]));
+    }
+
+    fn read_u32(&mut self) -> Result<u32, PellucidError> {
+        let bytes: &[u8] = self.read_bytes(4)?;
+        return Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+    }
+
+    fn expect_kind(&mut self, kind: u8) -> Result<(), PellucidError> {
+        let read_kind: u8 = self.read_u8()?;
+        if read_kind != kind {
+            return Err(PellucidError::EofMalformed(format!(
+                "expected section kind 0x{:02x}, got 0x{:02x}",
+                kind, read_kind
+            )));
+        }
+        return Ok(());
+    }
+}
+
+impl EofContainer {
+    pub fn from(raw_container: &str, hardfork: Hardfork) -> Result<Self, PellucidError> {
+        let bytes: Vec<u8> = match hex::decode(remove_0x(raw_container)) {
+            Ok(res) => res,
+            Err(error) => return Err(PellucidError::InvalidHex(error.to_string())),
+        };
+        return EofContainer::from_bytes(&bytes, hardfork);
+    }
+
+    pub fn from_bytes(bytes: &[u8], hardfork: Hardfork) -> Result<Self, PellucidError> {
+        if !is_eof(bytes) {
+            return Err(malformed("missing magic 0xef00"));
+        }
+        let mut reader: Reader = Reader { bytes, position: 2 };
+
+        // header
+        let version: u8 = reader.read_u8()?;
+        if version != 1 {
+            return Err(PellucidError::EofMalformed(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        reader.expect_kind(KIND_TYPES)?;
+        let types_size: usize = reader.read_u16()? as usize;
+        reader.expect_kind(KIND_CODE)?;
+        let n_code_sections: usize = reader.read_u16()? as usize;
+        let mut code_sizes: Vec<usize> = Vec::new();
+        for _ in 0..n_code_sections {
+            code_sizes.push(reader.read_u16()? as usize);
+        }
+        if n_code_sections == 0 || types_size != 4 * n_code_sections {
+            return Err(malformed("type and code sections do not match"));
+        }
+        let mut container_sizes: Vec<usize> = Vec::new();
+        if bytes.get(reader.position) == Some(&KIND_CONTAINER) {
+            reader.expect_kind(KIND_CONTAINER)?;
+            let n_container_sections: usize = reader.read_u16()? as usize;
+            for _ in 0..n_container_sections {
+                container_sizes.push(reader.read_u32()? as usize);
+            }
+        }
+        reader.expect_kind(KIND_DATA)?;
+        let data_size: usize = reader.read_u16()? as usize;
+        reader.expect_kind(TERMINATOR)?;
+
+        // body
+        let mut types: Vec<TypeSection> = Vec::new();
+        for _ in 0..n_code_sections {
+            types.push(TypeSection {
+                inputs: reader.read_u8()?,
+                outputs: reader.read_u8()?,
+                max_stack_increase: reader.read_u16()?,
+            });
+        }
+        if types[0].inputs != 0 || types[0].is_returning() {
+            return Err(malformed(
+                "the first code section must be non-returning without inputs",
+            ));
+        }
+
+        let mut code_sections: Vec<Bytecode> = Vec::new();
+        for code_size in code_sizes {
+            let code: &[u8] = reader.read_bytes(code_size)?;
+            code_sections.push(decode_code_section(code, &types, hardfork)?);
+        }
+
+        let mut container_sections: Vec<Vec<u8>> = Vec::new();
+        for container_size in container_sizes {
+            container_sections.push(reader.read_bytes(container_size)?.to_vec());
+        }
+
+        // the data section of a container that is not deployed yet may be truncated
+        let data_end: usize = cmp::min(reader.position + data_size, bytes.len());
+        let data: Vec<u8> = bytes[reader.position..data_end].to_vec();
+
+        return Ok(EofContainer {
+            version,
+            types,
+            code_sections,
+            container_sections,
+            data,
+        });
+    }
+}
+
+fn read_relative_destination(
+    code: &[u8],
+    offset_position: usize,
+    next_pc: usize,
+) -> Result<usize, PellucidError> {
+    if offset_position + 2 > code.len() {
+        return Err(malformed("truncated relative jump"));
+    }
+    let offset: i16 = i16::from_be_bytes([code[offset_position], code[offset_position + 1]]);
+    let destination: isize = next_pc as isize + offset as isize;
+    if destination < 0 || destination as usize >= code.len() {
+        return Err(PellucidError::EofMalformed(format!(
+            "the relative jump offset at {:#x} points outside of the code section",
+            offset_position
+        )));
+    }
+    return Ok(destination as usize);
+}
+
+fn read_u8(code: &[u8], position: usize) -> Result<u8, PellucidError> {
+    return code
+        .get(position)
+        .copied()
+        .ok_or_else(|| malformed("truncated immediate"));
+}
+
+fn read_u16(code: &[u8], position: usize) -> Result<u16, PellucidError> {
+    return Ok(u16::from_be_bytes([
+        read_u8(code, position)?,
+        read_u8(code, position + 1)?,
+    ]));
+}
+
+fn read_section_index(
+    code: &[u8],
+    position: usize,
+    types: &[TypeSection],
+) -> Result<(u16, TypeSection), PellucidError> {
+    if position + 2 > code.len() {
+        return Err(malformed("truncated code section index"));
+    }
+    let section: u16 = u16::from_be_bytes([code[position], code[position + 1]]);
+    match types.get(section as usize) {
+        Some(type_section) => return Ok((section, *type_section)),
+        None => {
+            return Err(PellucidError::EofMalformed(format!(
+                "call to the unknown code section {}",
+                section
+            )))
+        }
+    }
+}
+
+fn decode_code_section(
+    code: &[u8],
+    types: &[TypeSection],
+    hardfork: Hardfork,
+) -> Result<Bytecode, PellucidError> {
+    let mut vopcodes: Vec<Vopcode> = Vec::new();
+    let mut pc: usize = 0;
+    while pc < code.len() {
+        let mut value: Option<U256> = None;
+        let mut relative_destinations: Vec<usize> = Vec::new();
+        let opcode: Opcode = match code[pc] {
+            0xe0 | 0xe1 => {
+                relative_destinations.push(read_relative_destination(code, pc + 1, pc + 3)?);
+                if code[pc] == 0xe0 {
+                    Opcode::RJUMP
+                } else {
+                    Opcode::RJUMPI
+                }
+            }
+            0xe2 => {
+                let max_index: u8 = *code
+                    .get(pc + 1)
+                    .ok_or_else(|| malformed("truncated jump table"))?;
+                let n_destinations: usize = max_index as usize + 1;
+                let next_pc: usize = pc + 2 + 2 * n_destinations;
+                for index in 0..n_destinations {
+                    relative_destinations.push(read_relative_destination(
+                        code,
+                        pc + 2 + 2 * index,
+                        next_pc,
+                    )?);
+                }
+                Opcode::RJUMPV { max_index }
+            }
+            0xe3 => {
+                let (section, type_section): (u16, TypeSection) =
+                    read_section_index(code, pc + 1, types)?;
+                Opcode::CALLF {
+                    section,
+                    inputs: type_section.inputs,
+                    outputs: if type_section.is_returning() {
+                        type_section.outputs
+                    } else {
+                        0
+                    },
+                }
+            }
+            0xe4 => Opcode::RETF,
+            0xe5 => {
+                let (section, type_section): (u16, TypeSection) =
+                    read_section_index(code, pc + 1, types)?;
+                Opcode::JUMPF {
+                    section,
+                    inputs: type_section.inputs,
+                }
+            }
+            0xd0 => Opcode::DATALOAD,
+            0xd1 => Opcode::DATALOADN {
+                offset: read_u16(code, pc + 1)?,
+            },
+            0xd2 => Opcode::DATASIZE,
+            0xd3 => Opcode::DATACOPY,
+            0xe6 => Opcode::DUPN {
+                depth: read_u8(code, pc + 1)? as usize + 1,
+            },
+            0xe7 => Opcode::SWAPN {
+                depth: read_u8(code, pc + 1)? as usize + 1,
+            },
+            0xe8 => {
+                let immediate: u8 = read_u8(code, pc + 1)?;
+                Opcode::EXCHANGE {
+                    depth: (immediate >> 4) as usize + 1,
+                    distance: (immediate & 0x0f) as usize + 1,
+                }
+            }
+            0xec => Opcode::EOFCREATE {
+                container: read_u8(code, pc + 1)?,
+            },
+            0xee => Opcode::RETURNCONTRACT {
+                container: read_u8(code, pc + 1)?,
+            },
+            0xf7 => Opcode::RETURNDATALOAD,
+            0xf8 => Opcode::EXTCALL,
+            0xf9 => Opcode::EXTDELEGATECALL,
+            0xfb => Opcode::EXTSTATICCALL,
+            byte if REJECTED_OPCODES.contains(&byte) => {
+                return Err(PellucidError::EofMalformed(format!(
+                    "legacy opcode 0x{:02x} at pc {:#x} is not allowed in EOF",
+                    byte, pc
+                )));
+            }
+            byte => {
+                let opcode: Opcode = Opcode::from_with_hardfork(byte, hardfork);
+                if let Opcode::PUSH { item_size } = opcode {
+                    let item_end: usize = cmp::min(pc + 1 + item_size, code.len());
+                    value = Some(U256::from_big_endian(&code[pc + 1..item_end]));
+                }
+                opcode
+            }
+        };
+        let vopcode: Vopcode = if opcode.is_relative_jump() {
+            Vopcode::new_relative_jump(opcode, relative_destinations, pc)
+        } else {
+            Vopcode::new(opcode, value, pc)
+        };
+        pc = vopcode.get_next_pc();
+        vopcodes.push(vopcode);
+    }
+    return Ok(Bytecode::from_vopcodes(vopcodes, hardfork));
+}
+
+impl fmt::Display for EofContainer {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(formatter, "EOF version {}", self.version)?;
+        for (index, code_section) in self.code_sections.iter().enumerate() {
+            let type_section: &TypeSection = &self.types[index];
+            let outputs: String = if type_section.is_returning() {
+                type_section.outputs.to_string()
+            } else {
+                String::from("non-returning")
+            };
+            writeln!(
+                formatter,
+                "\ncode section {} (inputs: {}, outputs: {})",
+                index, type_section.inputs, outputs
+            )?;
+            write!(formatter, "{}", code_section)?;
+        }
+        for (index, container_section) in self.container_sections.iter().enumerate() {
+            writeln!(
+                formatter,
+                "\ncontainer section {}: 0x{}",
+                index,
+                hex::encode(container_section)
+            )?;
+        }
+        write!(formatter, "\ndata section: 0x{}", hex::encode(&self.data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // section 0: PUSH1 1, RJUMPI 0x0a, PUSH0, CALLF 1, STOP, (0x0a) PUSH0, PUSH0, RETURN
+    // section 1: PUSH1 1, ADD, RETF
+    const CONTAINER: &str =
+        "ef0001010008020002000d0004ff00020000800002010100016001e100055fe30001005f5ff3600101e4beef";
+
+    #[test]
+    fn test_parse_eof_container() {
+        let container: EofContainer = EofContainer::from(CONTAINER, Hardfork::default()).unwrap();
+        assert_eq!(container.version, 1);
+        assert_eq!(container.code_sections.len(), 2);
+        assert_eq!(
+            container.types[1],
+            TypeSection {
+                inputs: 1,
+                outputs: 1,
+                max_stack_increase: 1
+            }
+        );
+        assert_eq!(container.data, vec![0xbe, 0xef]);
+
+        let section_0: &Bytecode = &container.code_sections[0];
+        let rjumpi: &Vopcode = section_0.get_vopcode_at(2);
+        assert_eq!(rjumpi.opcode, Opcode::RJUMPI);
+        assert_eq!(rjumpi.get_relative_destinations(), vec![0x0a]);
+        assert_eq!(
+            section_0.get_vopcode_at(6).opcode,
+            Opcode::CALLF {
+                section: 1,
+                inputs: 1,
+                outputs: 1
+            }
+        );
+        assert_eq!(
+            container.code_sections[1].get_vopcode_at(3).opcode,
+            Opcode::RETF
+        );
+    }
+
+    #[test]
+    fn test_parse_large_jump_table() {
+        // PUSH0, CALLDATALOAD, RJUMPV with 20 entries jumping to the STOP right after it
+        let container_hex: String = format!(
+            "ef000101000402000100{:02x}ff00000000800002{}{}00",
+            4 + 2 * 20 + 1,
+            "5f35e213",
+            "0000".repeat(20)
+        );
+        let container: EofContainer =
+            EofContainer::from(&container_hex, Hardfork::default()).unwrap();
+        let rjumpv: &Vopcode = container.code_sections[0].get_vopcode_at(2);
+        assert_eq!(rjumpv.opcode, Opcode::RJUMPV { max_index: 19 });
+        assert_eq!(rjumpv.get_relative_destinations(), vec![0x2c; 20]);
+    }
+
+    #[test]
+    fn test_parse_stack_and_data_opcodes() {
+        // 6 PUSH0, DUPN 3, SWAPN 1, EXCHANGE 2 5, DATALOADN 0, STOP
+        let container: EofContainer = EofContainer::from(
+            "ef00010100040200010010ff00000000800007\
+             5f5f5f5f5f5fe602e700e812d1000000",
+            Hardfork::default(),
+        )
+        .unwrap();
+        let section_0: &Bytecode = &container.code_sections[0];
+        assert_eq!(
+            section_0.get_vopcode_at(6).opcode,
+            Opcode::DUPN { depth: 3 }
+        );
+        assert_eq!(
+            section_0.get_vopcode_at(8).opcode,
+            Opcode::SWAPN { depth: 1 }
+        );
+        assert_eq!(
+            section_0.get_vopcode_at(10).opcode,
+            Opcode::EXCHANGE {
+                depth: 2,
+                distance: 3
+            }
+        );
+        assert_eq!(
+            section_0.get_vopcode_at(12).opcode,
+            Opcode::DATALOADN { offset: 0 }
+        );
+        assert_eq!(section_0.get_vopcode_at(15).opcode, Opcode::STOP);
+    }
+
+    #[test]
+    fn test_reject_legacy_jumps() {
+        // PUSH0, JUMP
+        assert!(matches!(
+            EofContainer::from(
+                "ef00010100040200010002ff000000008000015f56",
+                Hardfork::default()
+            ),
+            Err(PellucidError::EofMalformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_malformed_eof_container() {
+        assert!(matches!(
+            EofContainer::from("ef0001", Hardfork::default()),
+            Err(PellucidError::EofMalformed(_))
+        ));
+        assert!(matches!(
+            EofContainer::from("6080", Hardfork::default()),
+            Err(PellucidError::EofMalformed(_))
+        ));
+    }
+}
@@ -0,0 +1,103 @@
+use super::metadata::{get_metadata, Metadata};
+
+// Only the forks that changed the opcode table are listed (e.g. Petersburg is Constantinople).
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum Hardfork {
+    Frontier,
+    Homestead,
+    Byzantium,
+    Constantinople,
+    Istanbul,
+    Berlin,
+    London,
+    Paris,
+    Shanghai,
+    Cancun,
+    #[default]
+    Prague,
+}
+
+impl Hardfork {
+    // Default EVM version targeted by each solc release.
+    pub fn from_solc_version(major: u8, minor: u8, patch: u8) -> Hardfork {
+        let version: (u8, u8, u8) = (major, minor, patch);
+        return match version {
+            v if v >= (0, 8, 30) => Hardfork::Prague,
+            v if v >= (0, 8, 25) => Hardfork::Cancun,
+            v if v >= (0, 8, 20) => Hardfork::Shanghai,
+            v if v >= (0, 8, 18) => Hardfork::Paris,
+            v if v >= (0, 8, 7) => Hardfork::London,
+            v if v >= (0, 8, 5) => Hardfork::Berlin,
+            v if v >= (0, 5, 14) => Hardfork::Istanbul,
+            v if v >= (0, 5, 5) => Hardfork::Constantinople,
+            v if v >= (0, 4, 21) => Hardfork::Byzantium,
+            _ => Hardfork::Homestead,
+        };
+    }
+
+    pub fn from_metadata(metadata: &[Metadata]) -> Option<Hardfork> {
+        for m in metadata {
+            if let Metadata::SolcVersion(major, minor, patch) = m {
+                return Some(Hardfork::from_solc_version(*major, *minor, *patch));
+            }
+        }
+        return None;
+    }
+
+    // Falls back to the latest fork when the bytecode does not embed a solc version.
+    pub fn detect(raw_bytecode: &str) -> Hardfork {
+        return match get_metadata(raw_bytecode) {
+            Ok(metadata) => Hardfork::from_metadata(&metadata).unwrap_or_default(),
+            Err(_) => Hardfork::default(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode_reader::opcode::Opcode;
+    use crate::tools::utils::read_file;
+
+    #[test]
+    fn test_hardfork_from_solc_version() {
+        assert_eq!(Hardfork::from_solc_version(0, 4, 11), Hardfork::Homestead);
+        assert_eq!(Hardfork::from_solc_version(0, 5, 14), Hardfork::Istanbul);
+        assert_eq!(Hardfork::from_solc_version(0, 8, 19), Hardfork::Paris);
+        assert_eq!(Hardfork::from_solc_version(0, 8, 24), Hardfork::Shanghai);
+        assert_eq!(Hardfork::from_solc_version(0, 8, 28), Hardfork::Cancun);
+        assert!(
+            Hardfork::detect(&read_file("./contracts/simple/contract_0/bytecode.txt"))
+                < Hardfork::Shanghai
+        );
+        assert_eq!(Hardfork::detect(""), Hardfork::default());
+    }
+
+    #[test]
+    fn test_opcodes_by_hardfork() {
+        assert_eq!(
+            Opcode::from_with_hardfork(0x5f, Hardfork::London),
+            Opcode::INVALID { code: 0x5f }
+        );
+        assert_eq!(
+            Opcode::from_with_hardfork(0x5f, Hardfork::Shanghai),
+            Opcode::PUSH0
+        );
+        assert_eq!(
+            Opcode::from_with_hardfork(0x48, Hardfork::Berlin),
+            Opcode::INVALID { code: 0x48 }
+        );
+        assert_eq!(
+            Opcode::from_with_hardfork(0x1b, Hardfork::Byzantium),
+            Opcode::INVALID { code: 0x1b }
+        );
+        assert_eq!(
+            Opcode::from_with_hardfork(0x44, Hardfork::London),
+            Opcode::DIFFICULTY
+        );
+        assert_eq!(
+            Opcode::from_with_hardfork(0x44, Hardfork::Paris),
+            Opcode::PREVRANDAO
+        );
+    }
+}
@@ -1,4 +1,7 @@
 #![allow(dead_code)]
+use crate::bytecode_reader::bytecode::strip_library_placeholders;
+use crate::error::PellucidError;
+use crate::tools::utils::remove_0x;
 use cbor::Decoder as CBOR_Decoder;
 use hex;
 use multibase::Base;
@@ -31,19 +34,28 @@ impl fmt::Display for Metadata {
     }
 }
 
-pub fn get_metadata(source_code: &str) -> Vec<Metadata> {
-    let decoded_source_code = hex::decode(source_code).unwrap();
+pub fn get_metadata(source_code: &str) -> Result<Vec<Metadata>, PellucidError> {
+    let (hex_source_code, _) = strip_library_placeholders(remove_0x(source_code));
+    let decoded_source_code: Vec<u8> = match hex::decode(hex_source_code) {
+        Ok(res) => res,
+        Err(error) => return Err(PellucidError::InvalidHex(error.to_string())),
+    };
     let metadata_bytes = get_metadata_bytes(&decoded_source_code);
     if metadata_bytes.len() > 0 {
         return decode_metadata(metadata_bytes);
     } else {
-        return Vec::new();
+        return Ok(Vec::new());
     }
 }
 
 fn get_metadata_bytes(source_code: &[u8]) -> &[u8] {
     let length = source_code.len();
-    let metadata_size = (source_code[length - 1] as usize) + (source_code[length - 2] as usize);
+    if length < 2 {
+        return &[];
+    }
+    // the length of the CBOR payload is stored big-endian in the last two bytes
+    let metadata_size =
+        ((source_code[length - 2] as usize) << 8) | (source_code[length - 1] as usize);
     let metadata_end = length - 2;
     if metadata_size > metadata_end {
         return &[];
@@ -52,7 +64,21 @@ fn get_metadata_bytes(source_code: &[u8]) -> &[u8] {
     return &source_code[metadata_start..metadata_end];
 }
 
-fn decode_metadata(encoded_metadata: &[u8]) -> Vec<Metadata> {
+// Offset of the metadata trailer (including its two length bytes), if it decodes as solc metadata.
+pub fn get_metadata_start(source_code: &[u8]) -> Option<usize> {
+    let metadata_bytes: &[u8] = get_metadata_bytes(source_code);
+    if metadata_bytes.is_empty() {
+        return None;
+    }
+    match decode_metadata(metadata_bytes) {
+        Ok(metadata) if !metadata.is_empty() => {
+            return Some(source_code.len() - 2 - metadata_bytes.len())
+        }
+        _ => return None,
+    }
+}
+
+fn decode_metadata(encoded_metadata: &[u8]) -> Result<Vec<Metadata>, PellucidError> {
     let mut decoder = CBOR_Decoder::from_bytes(encoded_metadata);
     let decoded = decoder.decode::<HashMap<String, Vec<u8>>>().next();
     match decoded {
@@ -60,16 +86,16 @@ fn decode_metadata(encoded_metadata: &[u8]) -> Vec<Metadata> {
             let mut metadata: Vec<Metadata> = Vec::new();
             for (key, value) in decoded_metadata.into_iter() {
                 metadata.push(match key.as_str() {
-                    "solc" => decode_solc_version(&value),
+                    "solc" => decode_solc_version(&value)?,
                     "ipfs" => decode_ipfs(&value),
                     "bzzr0" => decode_bzzr(0, &value),
                     "bzzr1" => decode_bzzr(1, &value),
                     _ => Metadata::Unknown(key, value),
                 })
             }
-            return metadata;
+            return Ok(metadata);
         }
-        _ => return Vec::new(),
+        _ => return Ok(Vec::new()),
     }
 }
 
@@ -78,10 +104,41 @@ fn decode_ipfs(ipfs_bytes: &[u8]) -> Metadata {
     return Metadata::IPFS(decoded[1..].to_string());
 }
 
-fn decode_solc_version(solc_bytes: &[u8]) -> Metadata {
-    return Metadata::SolcVersion(solc_bytes[0], solc_bytes[1], solc_bytes[2]);
+fn decode_solc_version(solc_bytes: &[u8]) -> Result<Metadata, PellucidError> {
+    if solc_bytes.len() != 3 {
+        return Err(PellucidError::MetadataMalformed(format!(
+            "solc version should be 3 bytes long, got 0x{}",
+            hex::encode(solc_bytes)
+        )));
+    }
+    return Ok(Metadata::SolcVersion(
+        solc_bytes[0],
+        solc_bytes[1],
+        solc_bytes[2],
+    ));
 }
 
 fn decode_bzzr(version: u8, bzzr_bytes: &[u8]) -> Metadata {
     return Metadata::BZZR(version, hex::encode(bzzr_bytes));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::utils::read_file;
+
+    #[test]
+    fn test_get_metadata() {
+        let metadata: Vec<Metadata> =
+            get_metadata(&read_file("./contracts/simple/contract_0/bytecode.txt")).unwrap();
+        assert!(metadata
+            .iter()
+            .any(|m| matches!(m, Metadata::SolcVersion(0, _, _))));
+        assert!(get_metadata("").unwrap().is_empty());
+        assert!(get_metadata("0x00").unwrap().is_empty());
+        assert!(matches!(
+            get_metadata("0xabc"),
+            Err(PellucidError::InvalidHex(_))
+        ));
+    }
+}
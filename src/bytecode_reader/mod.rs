@@ -1,4 +1,7 @@
 pub mod bytecode;
+pub mod creation;
+pub mod eof;
+pub mod hardfork;
 pub mod metadata;
 pub mod opcode;
 pub mod opcode_functions;
@@ -1,12 +1,14 @@
+use super::hardfork::Hardfork;
 use super::opcode_functions::{
     eval_add, eval_addmod, eval_and, eval_div, eval_eq, eval_exp, eval_gt, eval_iszero, eval_lt,
-    eval_mod, eval_mul, eval_mulmod, eval_not, eval_or, eval_sar, eval_sdiv, eval_sgt, eval_shl,
-    eval_shr, eval_signextend, eval_slt, eval_smod, eval_sub, eval_xor,
+    eval_mod, eval_mul, eval_mulmod, eval_not, eval_or, eval_push0, eval_sar, eval_sdiv, eval_sgt,
+    eval_shl, eval_shr, eval_signextend, eval_slt, eval_smod, eval_sub, eval_xor,
 };
 use primitive_types::U256;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+#[allow(non_camel_case_types)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Deserialize, Serialize)]
 pub enum Opcode {
     // 0x0 range - arithmetic ops.
@@ -22,6 +24,11 @@ pub enum Opcode {
     MULMOD,
     EXP,
     SIGNEXTEND,
+    // ADD / SUB / MUL / DIV preceded by the overflow or division by zero check of solidity >= 0.8
+    CHECKED_ADD,
+    CHECKED_SUB,
+    CHECKED_MUL,
+    CHECKED_DIV,
 
     // 0x10 range - comparison ops.
     LT,
@@ -66,10 +73,13 @@ pub enum Opcode {
     TIMESTAMP,
     NUMBER,
     DIFFICULTY,
+    PREVRANDAO,
     GASLIMIT,
     CHAINID,
     SELFBALANCE,
     BASEFEE,
+    BLOBHASH,
+    BLOBBASEFEE,
 
     // 0x50 range - 'storage' and execution.
     POP,
@@ -80,22 +90,84 @@ pub enum Opcode {
     SSTORE,
     JUMP,
     JUMPI,
+    // JUMP / JUMPI whose destination is unknown when building the graph
+    DYNAMIC_JUMP,
+    DYNAMIC_JUMPI,
     PC,
     MSIZE,
     GAS,
     JUMPDEST,
+    TLOAD,
+    TSTORE,
+    MCOPY,
+    PUSH0,
 
     // 0x60 range - pushes.
-    PUSH { item_size: usize },
+    PUSH {
+        item_size: usize,
+    },
+    // PUSH20 of an unlinked library address (`__$<hash>$__` placeholder)
+    LIBRARY {
+        hash: [u8; 17],
+    },
 
     // 0x80 range - dups.
-    DUP { depth: usize },
+    DUP {
+        depth: usize,
+    },
 
     // 0x90 range - swaps.
-    SWAP { depth: usize },
+    SWAP {
+        depth: usize,
+    },
 
     // 0xa0 range - logging ops.
-    LOG { topic_count: usize },
+    LOG {
+        topic_count: usize,
+    },
+
+    // 0xd0 range - EOF data section (only decoded inside EOF code sections).
+    DATALOAD,
+    DATALOADN {
+        offset: u16,
+    },
+    DATASIZE,
+    DATACOPY,
+
+    // 0xe0 range - EOF relative jumps, functions, stack and containers (only decoded inside EOF
+    // code sections).
+    RJUMP,
+    RJUMPI,
+    RJUMPV {
+        max_index: u8,
+    },
+    CALLF {
+        section: u16,
+        inputs: u8,
+        outputs: u8,
+    },
+    RETF,
+    JUMPF {
+        section: u16,
+        inputs: u8,
+    },
+    DUPN {
+        depth: usize,
+    },
+    SWAPN {
+        depth: usize,
+    },
+    // exchanges the items at `depth` and `depth + distance` (the top of the stack is at depth 0)
+    EXCHANGE {
+        depth: usize,
+        distance: usize,
+    },
+    EOFCREATE {
+        container: u8,
+    },
+    RETURNCONTRACT {
+        container: u8,
+    },
 
     // 0xf0 range - closures.
     CREATE,
@@ -107,8 +179,15 @@ pub enum Opcode {
     STATICCALL,
     REVERT,
     SELFDESTRUCT,
+    // EOF calls (only decoded inside EOF code sections).
+    RETURNDATALOAD,
+    EXTCALL,
+    EXTDELEGATECALL,
+    EXTSTATICCALL,
 
-    INVALID { code: u8 },
+    INVALID {
+        code: u8,
+    },
 }
 
 struct OpcodeInfo {
@@ -165,6 +244,38 @@ impl Opcode {
                 external_effect: false,
                 function: Some(Box::new(eval_div)),
             },
+            Opcode::CHECKED_ADD => OpcodeInfo {
+                code: 0x01,
+                name: "ADD".to_owned(),
+                stack_input: 2,
+                stack_output: 1,
+                external_effect: false,
+                function: Some(Box::new(eval_add)),
+            },
+            Opcode::CHECKED_SUB => OpcodeInfo {
+                code: 0x03,
+                name: "SUB".to_owned(),
+                stack_input: 2,
+                stack_output: 1,
+                external_effect: false,
+                function: Some(Box::new(eval_sub)),
+            },
+            Opcode::CHECKED_MUL => OpcodeInfo {
+                code: 0x02,
+                name: "MUL".to_owned(),
+                stack_input: 2,
+                stack_output: 1,
+                external_effect: false,
+                function: Some(Box::new(eval_mul)),
+            },
+            Opcode::CHECKED_DIV => OpcodeInfo {
+                code: 0x04,
+                name: "DIV".to_owned(),
+                stack_input: 2,
+                stack_output: 1,
+                external_effect: false,
+                function: Some(Box::new(eval_div)),
+            },
             Opcode::SDIV => OpcodeInfo {
                 code: 0x05,
                 name: "SDIV".to_owned(),
@@ -518,6 +629,14 @@ impl Opcode {
                 external_effect: false,
                 function: None,
             },
+            Opcode::PREVRANDAO => OpcodeInfo {
+                code: 0x44,
+                name: "PREVRANDAO".to_owned(),
+                stack_input: 0,
+                stack_output: 1,
+                external_effect: false,
+                function: None,
+            },
             Opcode::GASLIMIT => OpcodeInfo {
                 code: 0x45,
                 name: "GASLIMIT".to_owned(),
@@ -550,6 +669,22 @@ impl Opcode {
                 external_effect: false,
                 function: None,
             },
+            Opcode::BLOBHASH => OpcodeInfo {
+                code: 0x49,
+                name: "BLOBHASH".to_owned(),
+                stack_input: 1,
+                stack_output: 1,
+                external_effect: false,
+                function: None,
+            },
+            Opcode::BLOBBASEFEE => OpcodeInfo {
+                code: 0x4a,
+                name: "BLOBBASEFEE".to_owned(),
+                stack_input: 0,
+                stack_output: 1,
+                external_effect: false,
+                function: None,
+            },
 
             // 0x50 range - 'storage' and execution.
             Opcode::POP => OpcodeInfo {
@@ -616,6 +751,22 @@ impl Opcode {
                 external_effect: true,
                 function: None,
             },
+            Opcode::DYNAMIC_JUMP => OpcodeInfo {
+                code: 0x56,
+                name: "JUMP".to_owned(),
+                stack_input: 1,
+                stack_output: 0,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::DYNAMIC_JUMPI => OpcodeInfo {
+                code: 0x57,
+                name: "JUMPI".to_owned(),
+                stack_input: 2,
+                stack_output: 0,
+                external_effect: true,
+                function: None,
+            },
             Opcode::PC => OpcodeInfo {
                 code: 0x58,
                 name: "PC".to_owned(),
@@ -648,6 +799,38 @@ impl Opcode {
                 external_effect: false,
                 function: None,
             },
+            Opcode::TLOAD => OpcodeInfo {
+                code: 0x5c,
+                name: "TLOAD".to_owned(),
+                stack_input: 1,
+                stack_output: 1,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::TSTORE => OpcodeInfo {
+                code: 0x5d,
+                name: "TSTORE".to_owned(),
+                stack_input: 2,
+                stack_output: 0,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::MCOPY => OpcodeInfo {
+                code: 0x5e,
+                name: "MCOPY".to_owned(),
+                stack_input: 3,
+                stack_output: 0,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::PUSH0 => OpcodeInfo {
+                code: 0x5f,
+                name: "PUSH0".to_owned(),
+                stack_input: 0,
+                stack_output: 1,
+                external_effect: false,
+                function: Some(Box::new(eval_push0)),
+            },
 
             // 0x60 range - pushes.
             Opcode::PUSH { item_size } => {
@@ -662,6 +845,14 @@ impl Opcode {
                     function: None,
                 }
             }
+            Opcode::LIBRARY { hash: _ } => OpcodeInfo {
+                code: 0x73,
+                name: "PUSH20".to_owned(),
+                stack_input: 0,
+                stack_output: 1,
+                external_effect: false,
+                function: None,
+            },
 
             // 0x80 range - dups.
             Opcode::DUP { depth } => {
@@ -771,6 +962,162 @@ impl Opcode {
                 external_effect: true,
                 function: None,
             },
+            Opcode::DATALOAD => OpcodeInfo {
+                code: 0xd0,
+                name: "DATALOAD".to_owned(),
+                stack_input: 1,
+                stack_output: 1,
+                external_effect: false,
+                function: None,
+            },
+            Opcode::DATALOADN { offset: _ } => OpcodeInfo {
+                code: 0xd1,
+                name: "DATALOADN".to_owned(),
+                stack_input: 0,
+                stack_output: 1,
+                external_effect: false,
+                function: None,
+            },
+            Opcode::DATASIZE => OpcodeInfo {
+                code: 0xd2,
+                name: "DATASIZE".to_owned(),
+                stack_input: 0,
+                stack_output: 1,
+                external_effect: false,
+                function: None,
+            },
+            Opcode::DATACOPY => OpcodeInfo {
+                code: 0xd3,
+                name: "DATACOPY".to_owned(),
+                stack_input: 3,
+                stack_output: 0,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::RJUMP => OpcodeInfo {
+                code: 0xe0,
+                name: "RJUMP".to_owned(),
+                stack_input: 0,
+                stack_output: 0,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::RJUMPI => OpcodeInfo {
+                code: 0xe1,
+                name: "RJUMPI".to_owned(),
+                stack_input: 1,
+                stack_output: 0,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::RJUMPV { max_index: _ } => OpcodeInfo {
+                code: 0xe2,
+                name: "RJUMPV".to_owned(),
+                stack_input: 1,
+                stack_output: 0,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::CALLF {
+                section: _,
+                inputs,
+                outputs,
+            } => OpcodeInfo {
+                code: 0xe3,
+                name: "CALLF".to_owned(),
+                stack_input: *inputs as usize,
+                stack_output: *outputs as usize,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::RETF => OpcodeInfo {
+                code: 0xe4,
+                name: "RETF".to_owned(),
+                stack_input: 0,
+                stack_output: 0,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::JUMPF { section: _, inputs } => OpcodeInfo {
+                code: 0xe5,
+                name: "JUMPF".to_owned(),
+                stack_input: *inputs as usize,
+                stack_output: 0,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::DUPN { depth } => OpcodeInfo {
+                code: 0xe6,
+                name: "DUPN".to_owned(),
+                stack_input: *depth,
+                stack_output: depth + 1,
+                external_effect: false,
+                function: None,
+            },
+            Opcode::SWAPN { depth } => OpcodeInfo {
+                code: 0xe7,
+                name: "SWAPN".to_owned(),
+                stack_input: depth + 1,
+                stack_output: depth + 1,
+                external_effect: false,
+                function: None,
+            },
+            Opcode::EXCHANGE { depth, distance } => OpcodeInfo {
+                code: 0xe8,
+                name: "EXCHANGE".to_owned(),
+                stack_input: depth + distance + 1,
+                stack_output: depth + distance + 1,
+                external_effect: false,
+                function: None,
+            },
+            Opcode::EOFCREATE { container: _ } => OpcodeInfo {
+                code: 0xec,
+                name: "EOFCREATE".to_owned(),
+                stack_input: 4,
+                stack_output: 1,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::RETURNCONTRACT { container: _ } => OpcodeInfo {
+                code: 0xee,
+                name: "RETURNCONTRACT".to_owned(),
+                stack_input: 2,
+                stack_output: 0,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::RETURNDATALOAD => OpcodeInfo {
+                code: 0xf7,
+                name: "RETURNDATALOAD".to_owned(),
+                stack_input: 1,
+                stack_output: 1,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::EXTCALL => OpcodeInfo {
+                code: 0xf8,
+                name: "EXTCALL".to_owned(),
+                stack_input: 4,
+                stack_output: 1,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::EXTDELEGATECALL => OpcodeInfo {
+                code: 0xf9,
+                name: "EXTDELEGATECALL".to_owned(),
+                stack_input: 3,
+                stack_output: 1,
+                external_effect: true,
+                function: None,
+            },
+            Opcode::EXTSTATICCALL => OpcodeInfo {
+                code: 0xfb,
+                name: "EXTSTATICCALL".to_owned(),
+                stack_input: 3,
+                stack_output: 1,
+                external_effect: true,
+                function: None,
+            },
             Opcode::SELFDESTRUCT => OpcodeInfo {
                 code: 0xff,
                 name: "SELFDESTRUCT".to_owned(),
@@ -858,23 +1205,90 @@ impl Opcode {
     pub fn is_exiting(&self) -> bool {
         match self {
             Self::STOP | Self::RETURN | Self::REVERT | Self::SELFDESTRUCT => true,
+            Self::RETF
+            | Self::JUMPF {
+                section: _,
+                inputs: _,
+            } => true, // exit the current EOF code section
+            Self::RETURNCONTRACT { container: _ } => true,
             _ => false,
         }
     }
 
     pub fn is_jump(&self) -> bool {
         match self {
-            Self::JUMP | Self::JUMPI => true,
+            Self::JUMP | Self::JUMPI | Self::DYNAMIC_JUMP | Self::DYNAMIC_JUMPI => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_relative_jump(&self) -> bool {
+        match self {
+            Self::RJUMP | Self::RJUMPI | Self::RJUMPV { max_index: _ } => true,
             _ => false,
         }
     }
 
+    // Number of bytes following the opcode in the bytecode.
+    pub fn immediate_size(&self) -> usize {
+        match self {
+            Self::PUSH { item_size } => *item_size,
+            Self::LIBRARY { hash: _ } => 20,
+            Self::RJUMP | Self::RJUMPI => 2,
+            Self::RJUMPV { max_index } => 1 + 2 * (*max_index as usize + 1),
+            Self::CALLF {
+                section: _,
+                inputs: _,
+                outputs: _,
+            }
+            | Self::JUMPF {
+                section: _,
+                inputs: _,
+            }
+            | Self::DATALOADN { offset: _ } => 2,
+            Self::DUPN { depth: _ }
+            | Self::SWAPN { depth: _ }
+            | Self::EXCHANGE {
+                depth: _,
+                distance: _,
+            }
+            | Self::EOFCREATE { container: _ }
+            | Self::RETURNCONTRACT { container: _ } => 1,
+            _ => 0,
+        }
+    }
+
     pub fn delta(&self) -> isize {
         return self.stack_output() as isize - self.stack_input() as isize;
     }
 
+    // Fork in which the opcode became available.
+    pub fn introduced_in(&self) -> Hardfork {
+        match self {
+            Self::DELEGATECALL => Hardfork::Homestead,
+            Self::RETURNDATASIZE | Self::RETURNDATACOPY | Self::STATICCALL | Self::REVERT => {
+                Hardfork::Byzantium
+            }
+            Self::SHL | Self::SHR | Self::SAR | Self::EXTCODEHASH | Self::CREATE2 => {
+                Hardfork::Constantinople
+            }
+            Self::CHAINID | Self::SELFBALANCE => Hardfork::Istanbul,
+            Self::BASEFEE => Hardfork::London,
+            Self::PREVRANDAO => Hardfork::Paris,
+            Self::PUSH0 => Hardfork::Shanghai,
+            Self::TLOAD | Self::TSTORE | Self::MCOPY | Self::BLOBHASH | Self::BLOBBASEFEE => {
+                Hardfork::Cancun
+            }
+            _ => Hardfork::Frontier,
+        }
+    }
+
     pub fn from(code: u8) -> Opcode {
-        return match code {
+        return Opcode::from_with_hardfork(code, Hardfork::default());
+    }
+
+    pub fn from_with_hardfork(code: u8, hardfork: Hardfork) -> Opcode {
+        let mut opcode: Opcode = match code {
             // 0x0 range - arithmetic ops.
             0x00 => Opcode::STOP,
             0x01 => Opcode::ADD,
@@ -936,6 +1350,8 @@ impl Opcode {
             0x46 => Opcode::CHAINID,
             0x47 => Opcode::SELFBALANCE,
             0x48 => Opcode::BASEFEE,
+            0x49 => Opcode::BLOBHASH,
+            0x4a => Opcode::BLOBBASEFEE,
 
             // 0x50 range - 'storage' and execution.
             0x50 => Opcode::POP,
@@ -950,6 +1366,10 @@ impl Opcode {
             0x59 => Opcode::MSIZE,
             0x5A => Opcode::GAS,
             0x5B => Opcode::JUMPDEST,
+            0x5C => Opcode::TLOAD,
+            0x5D => Opcode::TSTORE,
+            0x5E => Opcode::MCOPY,
+            0x5F => Opcode::PUSH0,
 
             // 0x60 range - pushes.
             0x60 => Opcode::PUSH { item_size: 1 },
@@ -1042,6 +1462,14 @@ impl Opcode {
             0xfF => Opcode::SELFDESTRUCT,
             other => Opcode::INVALID { code: other },
         };
+        // DIFFICULTY was repurposed at the Merge
+        if opcode == Opcode::DIFFICULTY && hardfork >= Hardfork::Paris {
+            opcode = Opcode::PREVRANDAO;
+        }
+        if opcode.introduced_in() > hardfork {
+            return Opcode::INVALID { code };
+        }
+        return opcode;
     }
 }
 
@@ -1062,6 +1490,18 @@ pub fn calculation_to_str(opcode: Opcode, get_args: impl Fn(usize, bool) -> Stri
         Opcode::MUL => format!("{} * {}", get_args(0, true), get_args(1, true)),
         Opcode::SUB => format!("{} - {}", get_args(0, true), get_args(1, true)),
         Opcode::DIV => format!("{} / {}", get_args(0, true), get_args(1, true)),
+        Opcode::CHECKED_ADD => {
+            format!("{} + {} /* checked */", get_args(0, true), get_args(1, true))
+        }
+        Opcode::CHECKED_SUB => {
+            format!("{} - {} /* checked */", get_args(0, true), get_args(1, true))
+        }
+        Opcode::CHECKED_MUL => {
+            format!("{} * {} /* checked */", get_args(0, true), get_args(1, true))
+        }
+        Opcode::CHECKED_DIV => {
+            format!("{} / {} /* checked */", get_args(0, true), get_args(1, true))
+        }
         Opcode::SDIV => format!("{} / {} (signed)", get_args(0, true), get_args(1, true)),
         Opcode::MOD => format!("{} % {}", get_args(0, true), get_args(1, true)),
         Opcode::SMOD => format!("{} % {} (signed)", get_args(0, true), get_args(1, true)),
@@ -1087,6 +1527,7 @@ pub fn calculation_to_str(opcode: Opcode, get_args: impl Fn(usize, bool) -> Stri
         Opcode::ADDRESS => format!("address(this)"),
         Opcode::BALANCE => format!("address({}).balance", get_args(0, false)),
         Opcode::ORIGIN => format!("tx.origin"),
+        Opcode::LIBRARY { hash } => format!("library_{}", hex::encode(hash)),
         Opcode::CALLER => format!("msg.sender"),
         Opcode::CALLVALUE => format!("msg.value"),
         Opcode::CALLDATALOAD => format!("calldata[{}]", get_args(0, false)),
@@ -1117,10 +1558,13 @@ pub fn calculation_to_str(opcode: Opcode, get_args: impl Fn(usize, bool) -> Stri
         Opcode::TIMESTAMP => format!("block.timestamp"),
         Opcode::NUMBER => format!("block.number"),
         Opcode::DIFFICULTY => format!("block.difficulty"),
+        Opcode::PREVRANDAO => format!("block.prevrandao"),
         Opcode::GASLIMIT => format!("gasLeft()"),
         Opcode::CHAINID => format!("block.chainid"),
         Opcode::SELFBALANCE => format!("address(this).balance"),
         Opcode::BASEFEE => format!("block.basefee"),
+        Opcode::BLOBHASH => format!("blobhash({})", get_args(0, false)),
+        Opcode::BLOBBASEFEE => format!("block.blobbasefee"),
         Opcode::POP => panic!("POP should not be displayed at a high level."),
         Opcode::MLOAD => format!("memory[{}]", get_args(0, false)),
         Opcode::MSTORE => format!("memory[{}] = {}", get_args(0, false), get_args(1, false)), // general case: we do do show that the size is 32 bytes
@@ -1129,20 +1573,33 @@ pub fn calculation_to_str(opcode: Opcode, get_args: impl Fn(usize, bool) -> Stri
         Opcode::SSTORE => format!("storage[{}] = {}", get_args(0, false), get_args(1, false)),
         Opcode::JUMP => panic!("JUMP should not be displayed at a high level."),
         Opcode::JUMPI => panic!("JUMPI should not be displayed at a high level."),
+        Opcode::DYNAMIC_JUMP | Opcode::DYNAMIC_JUMPI => {
+            panic!("Dynamic jumps should not be displayed at a high level.")
+        }
         Opcode::PC => format!("PC"), // TODO fill it with the integer value when building the graph
         Opcode::MSIZE => format!("msize"),
         Opcode::GAS => format!("gasleft()"),
         Opcode::JUMPDEST => panic!("JUMPDEST should not be displayed at a high level."),
+        Opcode::TLOAD => format!("transient[{}]", get_args(0, false)),
+        Opcode::TSTORE => format!("transient[{}] = {}", get_args(0, false), get_args(1, false)),
+        Opcode::MCOPY => format!(
+            "memory[{}..] = memory[{}{OFFSET_SIZE_SEP}{}]",
+            get_args(0, false), get_args(1, true), get_args(2, true)
+        ),
+        Opcode::PUSH0 => panic!("PUSH0 should not be displayed at a high level."),
         Opcode::PUSH { item_size: _ } => panic!("PUSH should not be displayed at a high level."),
         Opcode::DUP { depth: _ } => panic!("DUP should not be displayed at a high level."),
         Opcode::SWAP { depth: _ } => panic!("SWAP should not be displayed at a high level."),
         Opcode::LOG { topic_count } => {
-            let mut res = format!("log[{}{OFFSET_SIZE_SEP}{}]", get_args(0, true), get_args(1, true));
-            for topic_index in 0..topic_count {
-                res += &format!(", {}", get_args(2 + topic_index, true));
-            }
-            res += ")";
-            return res;
+            let topics: Vec<String> = (0..topic_count)
+                .map(|topic_index| get_args(2 + topic_index, false))
+                .collect();
+            return format!(
+                "log[{}{OFFSET_SIZE_SEP}{}]({})",
+                get_args(0, true),
+                get_args(1, true),
+                topics.join(", ")
+            );
         }
         Opcode::CREATE => format!(
             "create(value: {}, code: [{}{OFFSET_SIZE_SEP}{}])",
@@ -1171,6 +1628,53 @@ pub fn calculation_to_str(opcode: Opcode, get_args: impl Fn(usize, bool) -> Stri
         ),
         Opcode::REVERT => format!("revert[{}{OFFSET_SIZE_SEP}{}]", get_args(0, true), get_args(1, true)),
         Opcode::SELFDESTRUCT => format!("seldestruct({})", get_args(0, false)),
+        Opcode::RJUMP
+        | Opcode::RJUMPI
+        | Opcode::RJUMPV { max_index: _ }
+        | Opcode::CALLF {
+            section: _,
+            inputs: _,
+            outputs: _,
+        }
+        | Opcode::RETF
+        | Opcode::JUMPF {
+            section: _,
+            inputs: _,
+        } => panic!("EOF control flow opcodes should not be displayed at a high level."),
+        Opcode::DATALOAD => format!("data[{}]", get_args(0, false)),
+        Opcode::DATALOADN { offset } => format!("data[{:#x}]", offset),
+        Opcode::DATASIZE => format!("datasize"),
+        Opcode::DATACOPY => format!(
+            "memory[{}..] = data[{}{OFFSET_SIZE_SEP}{}]",
+            get_args(0, false), get_args(1, true), get_args(2, true)
+        ),
+        Opcode::DUPN { depth: _ } => panic!("DUPN should not be displayed at a high level."),
+        Opcode::SWAPN { depth: _ } => panic!("SWAPN should not be displayed at a high level."),
+        Opcode::EXCHANGE {
+            depth: _,
+            distance: _,
+        } => panic!("EXCHANGE should not be displayed at a high level."),
+        Opcode::EOFCREATE { container } => format!(
+            "eofcreate(container: {}, value: {}, salt: {}, args: [{}{OFFSET_SIZE_SEP}{}])",
+            container, get_args(0, false), get_args(1, false), get_args(2, true), get_args(3, true)
+        ),
+        Opcode::RETURNCONTRACT { container } => format!(
+            "returncontract(container: {}, aux data: [{}{OFFSET_SIZE_SEP}{}])",
+            container, get_args(0, true), get_args(1, true)
+        ),
+        Opcode::RETURNDATALOAD => format!("returndata[{}]", get_args(0, false)),
+        Opcode::EXTCALL => format!(
+            "extcall(address: {}, value: {}, args: [{}{OFFSET_SIZE_SEP}{}])",
+            get_args(0, false), get_args(3, false), get_args(1, true), get_args(2, true)
+        ),
+        Opcode::EXTDELEGATECALL => format!(
+            "extdelegatecall(address: {}, args: [{}{OFFSET_SIZE_SEP}{}])",
+            get_args(0, false), get_args(1, true), get_args(2, true)
+        ),
+        Opcode::EXTSTATICCALL => format!(
+            "extstaticcall(address: {}, args: [{}{OFFSET_SIZE_SEP}{}])",
+            get_args(0, false), get_args(1, true), get_args(2, true)
+        ),
         Opcode::INVALID { code: _ } => panic!("INVALID should not be displayed at a high level."),
     }
 }
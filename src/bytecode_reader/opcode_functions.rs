@@ -139,6 +139,11 @@ pub fn byte(a: U256, b: U256) -> U256 {
     ret
 }
 
+pub fn eval_push0(input: Vec<U256>) -> U256 {
+    assert!(input.len() == 0);
+    return U256::zero();
+}
+
 pub fn eval_add(input: Vec<U256>) -> U256 {
     assert!(input.len() == 2);
     return input[0].overflowing_add(input[1]).0;
@@ -256,7 +261,7 @@ pub fn eval_signextend(input: Vec<U256>) -> U256 {
 
 pub fn eval_lt(input: Vec<U256>) -> U256 {
     assert!(input.len() == 2);
-    if input[0] < input[0] {
+    if input[0] < input[1] {
         return U256::from(1);
     } else {
         return U256::zero();
@@ -265,7 +270,7 @@ pub fn eval_lt(input: Vec<U256>) -> U256 {
 
 pub fn eval_gt(input: Vec<U256>) -> U256 {
     assert!(input.len() == 2);
-    if input[0] > input[0] {
+    if input[0] > input[1] {
         return U256::from(1);
     } else {
         return U256::zero();
@@ -369,3 +374,19 @@ pub fn eval_sar(input: Vec<U256>) -> U256 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comparisons() {
+        let (one, two): (U256, U256) = (U256::from(1), U256::from(2));
+        assert_eq!(eval_lt(vec![one, two]), U256::one());
+        assert_eq!(eval_lt(vec![two, one]), U256::zero());
+        assert_eq!(eval_lt(vec![one, one]), U256::zero());
+        assert_eq!(eval_gt(vec![two, one]), U256::one());
+        assert_eq!(eval_gt(vec![one, two]), U256::zero());
+        assert_eq!(eval_gt(vec![two, two]), U256::zero());
+    }
+}
@@ -3,12 +3,13 @@ use primitive_types::U256;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::ops::Deref;
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub struct Vopcode {
     // an opcode with a value, used when it's a PUSH
     pub opcode: Opcode,
     pub value: Option<U256>,
     pub pc: usize,
+    pub relative_destinations: Vec<usize>, // resolved destinations of a relative jump (RJUMP, RJUMPI, RJUMPV)
 }
 
 impl Deref for Vopcode {
@@ -22,16 +23,34 @@ impl Deref for Vopcode {
 impl Vopcode {
     pub fn new(opcode: Opcode, value: Option<U256>, pc: usize) -> Self {
         Vopcode::sanity_check(opcode, value);
-        return Self { opcode, value, pc };
+        assert!(
+            !opcode.is_relative_jump(),
+            "A relative jump should be created with its destinations"
+        );
+        return Self {
+            opcode,
+            value,
+            pc,
+            relative_destinations: Vec::new(),
+        };
+    }
+
+    pub fn new_relative_jump(opcode: Opcode, relative_destinations: Vec<usize>, pc: usize) -> Self {
+        assert!(opcode.is_relative_jump());
+        return Self {
+            opcode,
+            value: None,
+            pc,
+            relative_destinations,
+        };
     }
 
     pub fn get_next_pc(&self) -> usize {
-        return self.pc
-            + 1
-            + match self.opcode {
-                Opcode::PUSH { item_size } => item_size,
-                _ => 0,
-            };
+        return self.pc + 1 + self.opcode.immediate_size();
+    }
+
+    pub fn get_relative_destinations(&self) -> Vec<usize> {
+        return self.relative_destinations.clone();
     }
 
     fn sanity_check(opcode: Opcode, value: Option<U256>) {
@@ -68,6 +87,24 @@ impl Vopcode {
             } else {
                 res.push_str("invalid");
             }
+        } else if self.opcode.is_relative_jump() {
+            for dest in self.get_relative_destinations() {
+                res.push_str(&format!(" 0x{:04x}", dest));
+            }
+        } else if let Opcode::LIBRARY { hash } = self.opcode {
+            res.push_str(&format!(" __${}$__", hex::encode(hash)));
+        } else if let Opcode::CALLF { section, .. } | Opcode::JUMPF { section, .. } = self.opcode {
+            res.push_str(&format!(" {}", section));
+        } else if let Opcode::DATALOADN { offset } = self.opcode {
+            res.push_str(&format!(" 0x{:04x}", offset));
+        } else if let Opcode::DUPN { depth } | Opcode::SWAPN { depth } = self.opcode {
+            res.push_str(&format!(" {}", depth));
+        } else if let Opcode::EXCHANGE { depth, distance } = self.opcode {
+            res.push_str(&format!(" {} {}", depth, depth + distance));
+        } else if let Opcode::EOFCREATE { container } | Opcode::RETURNCONTRACT { container } =
+            self.opcode
+        {
+            res.push_str(&format!(" {}", container));
         }
 
         return res;
@@ -142,11 +179,13 @@ mod test {
                 opcode: Opcode::PUSH { item_size: 2 },
                 value: Some(U256::from("0x11aa")),
                 pc: 5,
+                relative_destinations: Vec::new(),
             },
             Vopcode {
                 opcode: Opcode::ADD,
                 value: None,
                 pc: 10,
+                relative_destinations: Vec::new(),
             },
         ] {
             let json: &String = &serde_json::to_string(&vopcode).unwrap();
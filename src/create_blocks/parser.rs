@@ -1,47 +1,52 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::{
-    bytecode_reader::{bytecode::Bytecode, opcode::Opcode},
+    bytecode_reader::{bytecode::Bytecode, opcode::Opcode, vopcode::Vopcode},
     create_graph::block::Block,
 };
 
+fn find_relative_jump_destinations(bytecode: &Bytecode) -> HashSet<usize> {
+    // in EOF code sections, blocks start at the destinations of relative jumps instead of JUMPDEST
+    return bytecode
+        .iter(0, bytecode.get_last_pc())
+        .flat_map(|vopcode: &Vopcode| vopcode.get_relative_destinations())
+        .collect();
+}
+
 fn find_block_locations(bytecode: &Bytecode) -> Vec<(usize, usize)> {
     let mut block_locations: Vec<(usize, usize)> = Vec::new(); // (pc_start, pc_end)
     let mut pc_start: Option<usize> = Some(0);
+    let relative_jump_destinations: HashSet<usize> = find_relative_jump_destinations(bytecode);
 
     for vopcode in bytecode.iter(0, bytecode.get_last_pc()) {
         let opcode: Opcode = vopcode.opcode;
         let pc: usize = vopcode.pc;
         let is_last_vopcode: bool = pc == bytecode.get_last_pc();
 
-        match pc_start {
-            Some(pc_start_) => {
-                // we already are in a block, we search for the end
-                if is_last_vopcode || opcode.is_exiting() || opcode == Opcode::JUMP {
-                    block_locations.push((pc_start_, pc));
-                    pc_start = None;
-                } else if opcode == Opcode::JUMPI {
-                    block_locations.push((pc_start_, pc));
-                    pc_start = Some(pc + 1);
-                } else if opcode == Opcode::JUMPDEST {
-                    block_locations.push((
-                        pc_start_,
-                        if let Some(prev_pc) = bytecode.get_previous_pc(pc) {
-                            prev_pc
-                        } else {
-                            pc_start_ // rare case of a JUMPDEST at pc 0
-                        },
-                    ));
-                    pc_start = Some(pc);
+        if opcode == Opcode::JUMPDEST || relative_jump_destinations.contains(&pc) {
+            // a new block starts here, the current one (if any) ends just before
+            if let Some(pc_start_) = pc_start {
+                if pc_start_ != pc {
+                    block_locations.push((pc_start_, bytecode.get_previous_pc(pc).unwrap()));
                 }
             }
-            None => {
-                // we are not in a block, we search for a new block
-                if opcode == Opcode::JUMPDEST {
-                    pc_start = Some(pc);
-                }
+            pc_start = Some(pc);
+        }
+
+        if let Some(pc_start_) = pc_start {
+            // we already are in a block, we search for the end
+            if is_last_vopcode
+                || opcode.is_exiting()
+                || opcode == Opcode::JUMP
+                || opcode == Opcode::RJUMP
+            {
+                block_locations.push((pc_start_, pc));
+                pc_start = None;
+            } else if opcode == Opcode::JUMPI || opcode.is_relative_jump() {
+                block_locations.push((pc_start_, pc));
+                pc_start = Some(vopcode.get_next_pc());
             }
-        };
+        }
     }
     return block_locations;
 }
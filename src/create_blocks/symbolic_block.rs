@@ -1,3 +1,4 @@
+use primitive_types::U256;
 use serde::{Deserialize, Serialize};
 
 use crate::bytecode_reader::{opcode::Opcode, vopcode::Vopcode};
@@ -37,7 +38,9 @@ impl SymbolicBlock {
         let length = self.effects.len();
 
         if length > 0 {
-            if self.effects[length - 1].opcode.is_jump() {
+            if self.effects[length - 1].opcode.is_jump()
+                || self.effects[length - 1].opcode.is_relative_jump()
+            {
                 return Some(Rc::clone(&self.effects[length - 1]));
             } else if self.effects[length - 1].opcode.is_exiting() {
                 return Some(Rc::clone(&self.effects[length - 1]));
@@ -62,8 +65,15 @@ impl SymbolicBlock {
         self.fill_stack_with_place_holders(vopcode.opcode.stack_input());
 
         match vopcode.opcode {
-            Opcode::DUP { depth } => self.symbolic_expressions.dup(depth),
-            Opcode::SWAP { depth } => self.symbolic_expressions.swap(depth),
+            Opcode::DUP { depth } | Opcode::DUPN { depth } => self.symbolic_expressions.dup(depth),
+            Opcode::SWAP { depth } | Opcode::SWAPN { depth } => {
+                self.symbolic_expressions.swap(depth)
+            }
+            Opcode::EXCHANGE { depth, distance } => {
+                self.symbolic_expressions.swap(depth);
+                self.symbolic_expressions.swap(depth + distance);
+                self.symbolic_expressions.swap(depth);
+            }
             Opcode::POP => {
                 self.symbolic_expressions.pop();
             }
@@ -71,6 +81,10 @@ impl SymbolicBlock {
                 self.symbolic_expressions
                     .push(SymbolicExpression::new_bytes(vopcode.value.unwrap(), None));
             }
+            Opcode::PUSH0 => {
+                self.symbolic_expressions
+                    .push(SymbolicExpression::new_bytes(U256::zero(), None));
+            }
 
             opcode => {
                 let consumed_symbolic_expressions: Vec<SymbolicExpression> =
@@ -89,12 +103,13 @@ impl SymbolicBlock {
                     effect = None
                 };
 
-                if opcode.stack_output() > 0 {
+                for _ in 0..opcode.stack_output() {
+                    // only CALLF may output more than one value
                     self.symbolic_expressions
                         .push(SymbolicExpression::new_compose(
                             opcode,
-                            consumed_symbolic_expressions,
-                            effect,
+                            consumed_symbolic_expressions.clone(),
+                            effect.clone(),
                         ))
                 }
             }
@@ -107,10 +122,11 @@ mod tests {
 
     use super::*;
     use crate::bytecode_reader::bytecode::Bytecode;
+    use crate::bytecode_reader::hardfork::Hardfork;
 
     #[test]
     pub fn test_apply_first_block() {
-        let bytecode = Bytecode::from("608060405234801561001057").unwrap();
+        let bytecode = Bytecode::from("608060405234801561001057", Hardfork::default()).unwrap();
         let mut block = SymbolicBlock::from(&[]);
         block.apply_vopcode(&bytecode.get_vopcode_at(0));
         block.apply_vopcode(&bytecode.get_vopcode_at(2));
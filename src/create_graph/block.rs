@@ -6,8 +6,10 @@ use crate::{
         symbolic_expression::{Effect, StackExpression},
     },
     create_graph::simple_evm::{SimpleStackExpression, State},
+    error::PellucidError,
     tools::utils::{calculate_hash, usize_to_hex},
 };
+use primitive_types::U256;
 use std::{cell::RefCell, collections::HashSet, fmt, fmt::Debug, rc::Rc};
 
 pub struct InnerBlock<'a> {
@@ -172,14 +174,21 @@ impl<'a> Block<'a> {
         return self.get_child_blocks().is_empty();
     }
 
-    pub fn apply_on_simple_context(&self, initial_context: &SimpleContext) -> SimpleContext {
+    pub fn apply_on_simple_context(
+        &self,
+        initial_context: &SimpleContext,
+    ) -> Result<SimpleContext, PellucidError> {
         // return the resulting stack + the list of the next pc destinations
-        assert!(initial_context.state == State::RUNNING); // I want to delete this
+        if initial_context.state != State::RUNNING {
+            return Err(PellucidError::StoppedContext {
+                pc_start: self.get_pc_start(),
+            });
+        }
         let mut final_context: SimpleContext = initial_context.clone();
 
         if self.get_n_args() > initial_context.stack.len() {
             final_context.state = State::STOP;
-            return final_context;
+            return Ok(final_context);
         }
 
         let mut args: Vec<SimpleStackExpression> = vec![];
@@ -193,15 +202,18 @@ impl<'a> Block<'a> {
                     .stack
                     .push(SimpleStackExpression::BYTES(value)),
                 StackExpression::ARG(index) => final_context.stack.push(args[index - 1].clone()),
-                StackExpression::COMPOSE(_, _) => {
-                    final_context.stack.push(SimpleStackExpression::OTHER)
-                }
+                StackExpression::COMPOSE(_, _) => final_context.stack.push(
+                    match evaluate(&symbolic_expr.stack_expression, &args) {
+                        Some(value) => SimpleStackExpression::COMPUTED_BYTES(value),
+                        None => SimpleStackExpression::OTHER,
+                    },
+                ),
             }
         }
 
         final_context.state = self.compute_final_state(self.final_effect(), args);
 
-        return final_context;
+        return Ok(final_context);
     }
 
     pub fn compute_final_state(
@@ -214,38 +226,29 @@ impl<'a> Block<'a> {
                 return State::RUNNING;
             }
             Some(final_effect) => {
-                if final_effect.opcode.is_jump() {
+                if final_effect.opcode.is_relative_jump() {
+                    // destinations are immediates, no need to look at the stack
+                    let mut destinations: Vec<usize> = Vec::new();
+                    if final_effect.opcode != Opcode::RJUMP {
+                        destinations.push(self.get_next_pc_start())
+                    }
+                    destinations
+                        .extend(self.get_code().last().unwrap().get_relative_destinations());
+                    return State::JUMP(destinations);
+                } else if final_effect.opcode.is_jump() {
                     let mut destinations: Vec<usize> = Vec::new();
                     if final_effect.opcode == Opcode::JUMPI {
                         destinations.push(self.get_next_pc_start())
                     }
                     let final_expression: &StackExpression =
                         &final_effect.symbolic_expressions[0].stack_expression;
-                    match final_expression {
-                        StackExpression::COMPOSE(_, _) => {
-                            /*
-                            In case the computation of the jumpdests involves bytes from previous blocks, we could also add another type in SimpleStackExpression:
-                            COMPUTED_BYTES(U256) where we put the result of all the arithmetical operations wa manage to perform (when all the inputs are BYTES or
-                            COMPUTED_BYTES). We could continue to compare stacks based on BYTES (and not COMPUTED_BYTES), but we could a COMPUTED_BYTES when it's a
-                            jump destination. Issue: In the latter case, how to handle the previous comparisons that didn't take into account this COMPUTED_BYTES
-                            while it should have been the case, because it is in fact a jump destination. We should probably go back in time, at the moment where
-                            the involved COMPUTED_BYTES is beeing computed... Hard to implement
-                            */
-                            destinations.push(
-                                final_expression
-                                    .compute_value()
-                                    .expect("cannot compute jump dest")
-                                    .as_usize(),
-                            );
-                            log::info!("Jumpdest required a computation");
+                    // jump tables and computed jumps are resolved when their inputs are known
+                    match evaluate(final_expression, &args) {
+                        Some(dest) if dest <= U256::from(usize::MAX) => {
+                            destinations.push(dest.as_usize())
                         }
-                        StackExpression::BYTES(dest) => destinations.push(dest.as_usize()),
-                        StackExpression::ARG(value) => match args[value - 1] {
-                            SimpleStackExpression::BYTES(dest) => {
-                                destinations.push(dest.as_usize())
-                            }
-                            _ => panic!("JUMP destination is not a constant"),
-                        },
+                        // destination loaded from calldata, storage, code...
+                        _ => return State::UNRESOLVED_JUMP(destinations),
                     }
                     return State::JUMP(destinations);
                 } else if final_effect.opcode.is_exiting() {
@@ -257,8 +260,17 @@ impl<'a> Block<'a> {
         }
     }
 
+    // The destination of the final JUMP / JUMPI is unknown in at least one of the contexts the
+    // block is reached with.
+    pub fn has_unresolved_jump(&self) -> bool {
+        return self
+            .get_nodes()
+            .iter()
+            .any(|node| matches!(node.clone_final_context().state, State::UNRESOLVED_JUMP(_)));
+    }
+
     pub fn has_deterministic_child_blocks(&self) -> bool {
-        let final_vopcode: Vopcode = self.get_code()[self.get_code().len() - 1];
+        let final_vopcode: &Vopcode = &self.get_code()[self.get_code().len() - 1];
         if !final_vopcode.opcode.is_jump() {
             return true;
         }
@@ -322,6 +334,22 @@ impl<'a> Block<'a> {
     }
 }
 
+// Value of the expression given the values of the block arguments, if they are all known.
+fn evaluate(expression: &StackExpression, args: &[SimpleStackExpression]) -> Option<U256> {
+    match expression {
+        StackExpression::BYTES(value) => return Some(*value),
+        StackExpression::ARG(index) => return args[index - 1].get_value(),
+        StackExpression::COMPOSE(opcode, symbolic_expressions) => {
+            let eval_function = opcode.get_function()?;
+            let mut params: Vec<U256> = Vec::new();
+            for symbolic_expression in symbolic_expressions {
+                params.push(evaluate(&symbolic_expression.stack_expression, args)?);
+            }
+            return Some(eval_function(params));
+        }
+    }
+}
+
 impl<'a> Debug for Block<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Block")
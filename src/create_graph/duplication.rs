@@ -94,10 +94,8 @@ pub fn duplicate_block_connection<'a, 'b>(
                 &mut duplicated_nodes
             };
             if !dupplication_table.contains_key(node) {
-                dupplication_table.insert(
-                    node.clone(),
-                    Node::create_and_attach(duplicated_block.clone(), node.clone_initial_context()),
-                );
+                dupplication_table
+                    .insert(node.clone(), node.duplicate_on(duplicated_block.clone()));
             }
             *duplicated_node = Some(dupplication_table[node].clone());
         }
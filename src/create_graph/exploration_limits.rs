@@ -0,0 +1,44 @@
+// Bounds of the graph exploration. A node is created for every context a block is reached with,
+// so some contracts would otherwise produce too many of them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ExplorationLimits {
+    pub max_nodes: usize, // no node is created beyond it, the exploration stops
+    pub max_nodes_per_block: usize, // beyond it, the contexts reaching the block are merged
+    pub max_stack_depth: usize, // deeper stacks are not explored
+}
+
+impl Default for ExplorationLimits {
+    fn default() -> Self {
+        return ExplorationLimits {
+            max_nodes: 100_000,
+            max_nodes_per_block: 1_000,
+            max_stack_depth: 1024, // the EVM halts on a stack overflow
+        };
+    }
+}
+
+// A limit hit during the exploration: the graph is still built, but it is less precise.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, PartialOrd, Ord)]
+pub enum ReachedLimit {
+    MaxNodes,
+    MaxNodesPerBlock { pc_start: usize },
+    MaxStackDepth { pc_start: usize },
+}
+
+impl ToString for ReachedLimit {
+    fn to_string(&self) -> String {
+        return match self {
+            ReachedLimit::MaxNodes => {
+                String::from("too many nodes, the rest of the graph is not explored")
+            }
+            ReachedLimit::MaxNodesPerBlock { pc_start } => format!(
+                "too many contexts reaching the block at {:#x}, they are merged",
+                pc_start
+            ),
+            ReachedLimit::MaxStackDepth { pc_start } => format!(
+                "stack too deep when reaching the block at {:#x}, it is not explored",
+                pc_start
+            ),
+        };
+    }
+}
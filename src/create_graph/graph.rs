@@ -1,61 +1,210 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use itertools::Itertools;
+use primitive_types::U256;
 
 use super::block::Block;
+use super::exploration_limits::{ExplorationLimits, ReachedLimit};
+use super::jump_liveness::{compute_live_slots, LiveSlots};
 use super::node::Node;
 use super::post_processing::remove_looping_blocks;
+use super::recursion::{explore_recursive_calls, find_recursion_entry, RecursiveCall};
 use super::simple_evm::SimpleContext;
 use crate::bytecode_reader::bytecode::Bytecode;
+use crate::bytecode_reader::opcode::Opcode;
 use crate::create_blocks::parser;
 use crate::create_graph::simple_evm::State;
+use crate::error::PellucidError;
 #[derive(Debug)]
 pub struct Graph<'a> {
     pub origin_blocks: HashMap<usize, Block<'a>>,
     pub all_blocks: HashSet<Block<'a>>, // icnlude duplications
+    pub recursive_calls: HashMap<usize, usize>, // pc_start of a block calling the function it belongs to -> pc_start of the function
+    pub recursive_functions: HashMap<usize, HashSet<usize>>, // pc_start of a recursive function -> pc_starts of its returning blocks
+    pub pending_recursive_calls: Vec<RecursiveCall<'a>>, // explored once the called function is known to return
+    pub dynamic_jump_targets: HashMap<usize, Node<'a>>, // pc_start of a JUMPDEST -> node explored as the destination of unresolved jumps
+    pub live_slots: HashMap<usize, LiveSlots>, // pc_start of a block -> stack slots that may become a jump destination
+    pub exploration_limits: ExplorationLimits,
+    pub reached_limits: BTreeSet<ReachedLimit>,
+    edges_to_explore: Vec<(Node<'a>, Block<'a>, SimpleContext)>, // origin, destination, initial context of the destination
+    dynamic_jumps_to_explore: Vec<(Block<'a>, SimpleContext)>, // possible destination of an unresolved jump, initial context
+    n_nodes: usize,
 }
 pub static mut CURRENT_DUPLICATION_INDEX: usize = 0;
 
 impl<'a> Graph<'a> {
-    pub fn from(bytecode: &'a Bytecode) -> Self {
+    pub fn from(bytecode: &'a Bytecode) -> Result<Self, PellucidError> {
+        return Graph::from_with_limits(bytecode, ExplorationLimits::default());
+    }
+
+    pub fn from_with_limits(
+        bytecode: &'a Bytecode,
+        exploration_limits: ExplorationLimits,
+    ) -> Result<Self, PellucidError> {
         let origin_blocks: HashMap<usize, Block<'a>> = parser::find_blocks(&bytecode);
         let all_blocks: HashSet<Block> = origin_blocks.values().cloned().collect();
+        let live_slots: HashMap<usize, LiveSlots> =
+            compute_live_slots(&origin_blocks, exploration_limits.max_stack_depth);
         let mut graph: Graph = Graph {
             origin_blocks,
             all_blocks,
+            recursive_calls: HashMap::new(),
+            recursive_functions: HashMap::new(),
+            pending_recursive_calls: Vec::new(),
+            dynamic_jump_targets: HashMap::new(),
+            live_slots,
+            exploration_limits,
+            reached_limits: BTreeSet::new(),
+            edges_to_explore: Vec::new(),
+            dynamic_jumps_to_explore: Vec::new(),
+            n_nodes: 0,
         };
         let first_block: Block = graph.get_block(0);
-        let initial_node: Node = Node::create_and_attach(first_block, SimpleContext::new());
+        let initial_node: Node = Node::create_and_attach(first_block, SimpleContext::new())?;
+        graph.n_nodes += 1;
         graph.explore_from(initial_node);
+        graph.explore()?;
+        explore_recursive_calls(&mut graph)?;
         remove_looping_blocks(&mut graph);
-        return graph;
+        return Ok(graph);
+    }
+
+    // Depth first, with a stack of edges rather than recursive calls: the native stack would
+    // overflow on big contracts. The JUMPDESTs an unresolved jump may land on are only explored
+    // once the known edges are, and only if nothing reached them: the code of the others is
+    // already known.
+    pub fn explore(&mut self) -> Result<(), PellucidError> {
+        loop {
+            while let Some((node_origin, block_dest, initial_context)) = self.edges_to_explore.pop()
+            {
+                if let Some(entry_node) =
+                    find_recursion_entry(self, &node_origin, &block_dest, &initial_context)
+                {
+                    // otherwise the stack would grow forever
+                    self.pending_recursive_calls.push(RecursiveCall {
+                        calling_node: node_origin,
+                        entry_node,
+                        context: initial_context,
+                    });
+                    continue;
+                }
+                self.explore_child(&node_origin, &block_dest, initial_context)?;
+            }
+            let Some((block_dest, initial_context)) = self.dynamic_jumps_to_explore.pop() else {
+                return Ok(());
+            };
+            if block_dest.nodes_count() > 0 {
+                continue;
+            }
+            if let Some(node_dest) = self.get_or_explore_node(&block_dest, initial_context)? {
+                self.dynamic_jump_targets
+                    .insert(block_dest.get_pc_start(), node_dest);
+            }
+        }
     }
 
-    pub fn explore_from(&self, node_origin: Node<'a>) {
+    pub fn explore_from(&mut self, node_origin: Node<'a>) {
         let block_origin: Block = node_origin.get_block();
         let current_final_context: SimpleContext = node_origin.clone_final_context();
+        let is_unresolved: bool = matches!(current_final_context.state, State::UNRESOLVED_JUMP(_));
         let next_dests: Vec<usize> = match &current_final_context.state {
             State::RUNNING => vec![block_origin.get_next_pc_start()],
             State::STOP => vec![],
-            State::JUMP(next_dests) => next_dests.clone(),
+            State::JUMP(next_dests) | State::UNRESOLVED_JUMP(next_dests) => next_dests.clone(),
         };
 
         let mut next_initial_context: SimpleContext = current_final_context;
         next_initial_context.state = State::RUNNING;
+        next_initial_context.forget_computed_bytes(|value| self.is_jump_destination(value));
+
+        // the first destination is explored first
+        for dest in next_dests.into_iter().rev() {
+            if let Some(block_dest) = self.origin_blocks.get(&dest).cloned() {
+                // the contexts that only differ by values never used as destinations share a node
+                let mut initial_context: SimpleContext = next_initial_context.clone();
+                initial_context.forget_dead_values(&self.live_slots[&dest]);
+                self.edges_to_explore
+                    .push((node_origin.clone(), block_dest, initial_context));
+            }
+        }
+
+        if is_unresolved {
+            // the jump may land on any JUMPDEST, whose code is explored without an edge: the
+            // destination is still unknown
+            for block_dest in self.get_jumpdest_blocks().into_iter().rev() {
+                if block_dest.nodes_count() > 0 {
+                    continue;
+                }
+                let mut initial_context: SimpleContext = next_initial_context.clone();
+                initial_context.forget_dead_values(&self.live_slots[&block_dest.get_pc_start()]);
+                self.dynamic_jumps_to_explore
+                    .push((block_dest, initial_context));
+            }
+        }
+    }
+
+    pub fn explore_child(
+        &mut self,
+        node_origin: &Node<'a>,
+        block_dest: &Block<'a>,
+        initial_context: SimpleContext,
+    ) -> Result<(), PellucidError> {
+        if let Some(node_dest) = self.get_or_explore_node(block_dest, initial_context)? {
+            node_origin.add_child(node_dest);
+        }
+        return Ok(());
+    }
 
-        for dest in next_dests {
-            if let Some(block_dest) = self.origin_blocks.get(&dest) {
-                if let Some(node_dest) = block_dest.get_node_starting_with(&next_initial_context) {
-                    node_origin.add_child(Node::clone(&node_dest));
-                } else {
-                    let node_dest: Node = Node::create_and_attach(
-                        Block::clone(block_dest),
-                        next_initial_context.clone(),
-                    );
-                    node_origin.add_child(Node::clone(&node_dest));
-                    self.explore_from(node_dest);
+    // The node of the block starting with the given context, created if needed. None when an
+    // exploration limit is reached.
+    fn get_or_explore_node(
+        &mut self,
+        block_dest: &Block<'a>,
+        initial_context: SimpleContext,
+    ) -> Result<Option<Node<'a>>, PellucidError> {
+        if let Some(node_dest) = block_dest.get_node_starting_with(&initial_context) {
+            return Ok(Some(node_dest));
+        }
+        if initial_context.stack.len() > self.exploration_limits.max_stack_depth {
+            self.reach_limit(ReachedLimit::MaxStackDepth {
+                pc_start: block_dest.get_pc_start(),
+            });
+            return Ok(None);
+        }
+
+        let mut initial_context: SimpleContext = initial_context;
+        if block_dest.nodes_count() >= self.exploration_limits.max_nodes_per_block {
+            self.reach_limit(ReachedLimit::MaxNodesPerBlock {
+                pc_start: block_dest.get_pc_start(),
+            });
+            // the merged context only gets more general, so few nodes are added to the block: one
+            // per set of jump destinations
+            for node in block_dest.get_nodes() {
+                if let Some(merged_context) = node
+                    .clone_initial_context()
+                    .merge(&initial_context, |value| self.is_jump_destination(value))
+                {
+                    initial_context = merged_context;
                 }
             }
+            if let Some(node_dest) = block_dest.get_node_starting_with(&initial_context) {
+                return Ok(Some(node_dest));
+            }
+        }
+        if self.n_nodes >= self.exploration_limits.max_nodes {
+            self.reach_limit(ReachedLimit::MaxNodes);
+            return Ok(None);
+        }
+
+        let node_dest: Node = Node::create_and_attach(Block::clone(block_dest), initial_context)?;
+        self.n_nodes += 1;
+        self.explore_from(Node::clone(&node_dest));
+        return Ok(Some(node_dest));
+    }
+
+    fn reach_limit(&mut self, reached_limit: ReachedLimit) {
+        if self.reached_limits.insert(reached_limit.clone()) {
+            log::warn!("Exploration limit reached: {}", reached_limit.to_string());
         }
     }
 
@@ -93,6 +242,27 @@ impl<'a> Graph<'a> {
         return edges;
     }
 
+    // True if a JUMP to `value` lands on a JUMPDEST.
+    pub fn is_jump_destination(&self, value: U256) -> bool {
+        if value > U256::from(usize::MAX) {
+            return false;
+        }
+        return match self.origin_blocks.get(&value.as_usize()) {
+            Some(block) => block.get_code()[0].opcode == Opcode::JUMPDEST,
+            None => false,
+        };
+    }
+
+    pub fn get_jumpdest_blocks(&self) -> Vec<Block<'a>> {
+        return self
+            .origin_blocks
+            .values()
+            .filter(|block| block.get_code()[0].opcode == Opcode::JUMPDEST)
+            .sorted_by_key(|block| block.get_pc_start())
+            .cloned()
+            .collect();
+    }
+
     pub fn get_pc_end_of_block(&self, block_pc_start: usize) -> usize {
         return self.origin_blocks[&block_pc_start].get_pc_end();
     }
@@ -136,6 +306,33 @@ impl<'a> Graph<'a> {
         return self.origin_blocks[&0].get_nodes()[0].clone();
     }
 
+    // The initial node, then the nodes only reached by jumps whose destination is unknown.
+    pub fn get_root_nodes(&self) -> Vec<Node<'a>> {
+        let mut root_nodes: Vec<Node<'a>> = vec![self.get_initial_node()];
+        for block in self.all_blocks.iter().sorted_by_key(|block| {
+            (
+                block.get_pc_start(),
+                block.get_duplication_info().map(|(index, _)| index),
+            )
+        }) {
+            root_nodes.extend(
+                block
+                    .get_nodes()
+                    .into_iter()
+                    .filter(|node| self.is_dynamic_jump_target(node)),
+            );
+        }
+        return root_nodes;
+    }
+
+    pub fn is_dynamic_jump_target(&self, node: &Node<'a>) -> bool {
+        return node.is_orphan()
+            && self
+                .dynamic_jump_targets
+                .get(&node.get_block().get_pc_start())
+                == Some(node);
+    }
+
     // pub fn deep_copy(&self) -> Self {
     //     let mut new_blocks: HashMap<usize, Block<'a>> = HashMap::new();
     //     for (pc_start, current_block) in &self.blocks {
@@ -165,12 +362,13 @@ impl<'a> Graph<'a> {
 
 #[cfg(test)]
 pub mod tests {
-    use serde::{Deserialize, Serialize};
-    use std::collections::HashSet;
     use super::*;
     use crate::bytecode_reader::bytecode::Bytecode;
+    use crate::bytecode_reader::hardfork::Hardfork;
     use crate::tools::utils::read_file;
     use itertools::Itertools;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashSet;
     use std::fs;
     use std::iter::FromIterator;
 
@@ -222,8 +420,8 @@ pub mod tests {
         let bytecode_string: String =
             fs::read_to_string("./contracts/simple/contract_0/bytecode.txt")
                 .expect("Unable to read file.");
-        let bytecode: Bytecode = Bytecode::from(&bytecode_string).unwrap();
-        let graph: Graph = Graph::from(&bytecode);
+        let bytecode: Bytecode = Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
+        let graph: Graph = Graph::from(&bytecode).unwrap();
 
         let target_serializable_graph: SerializableGraph =
             serde_json::from_str(&read_file("./contracts/simple/contract_0/graph.json")).unwrap();
@@ -237,4 +435,155 @@ pub mod tests {
         //     &serde_json::to_string(&serializable_graph).unwrap(),
         // );
     }
+
+    #[test]
+    pub fn test_computed_jump() {
+        // 0x0a + 0x04 is computed in the first block and used as a destination by the block at 0x09
+        let bytecode: Bytecode =
+            Bytecode::from("600a600401600956005b560000005b00", Hardfork::default()).unwrap();
+        let graph: Graph = Graph::from(&bytecode).unwrap();
+        assert_eq!(
+            graph.get_block(0x09).get_child_pc_starts(),
+            HashSet::from([0x0e])
+        );
+    }
+
+    #[test]
+    pub fn test_exploration_limits() {
+        let bytecode_string: String =
+            fs::read_to_string("./contracts/simple/contract_0/bytecode.txt")
+                .expect("Unable to read file.");
+        let bytecode: Bytecode = Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
+        let unbounded_graph: Graph = Graph::from(&bytecode).unwrap();
+        assert!(unbounded_graph.reached_limits.is_empty());
+
+        let graph: Graph = Graph::from_with_limits(
+            &bytecode,
+            ExplorationLimits {
+                max_nodes_per_block: 2,
+                ..ExplorationLimits::default()
+            },
+        )
+        .unwrap();
+        assert!(graph
+            .reached_limits
+            .iter()
+            .all(|limit| matches!(limit, ReachedLimit::MaxNodesPerBlock { pc_start: _ })));
+        assert!(!graph.reached_limits.is_empty());
+        assert!(graph.get_all_nodes().len() < unbounded_graph.get_all_nodes().len());
+
+        let graph: Graph = Graph::from_with_limits(
+            &bytecode,
+            ExplorationLimits {
+                max_nodes: 10,
+                ..ExplorationLimits::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            graph.reached_limits,
+            BTreeSet::from([ReachedLimit::MaxNodes])
+        );
+        assert_eq!(graph.get_all_nodes().len(), 10);
+
+        let graph: Graph = Graph::from_with_limits(
+            &bytecode,
+            ExplorationLimits {
+                max_stack_depth: 4,
+                ..ExplorationLimits::default()
+            },
+        )
+        .unwrap();
+        assert!(graph
+            .get_all_nodes()
+            .iter()
+            .all(|node| node.clone_initial_context().stack.len() <= 4));
+        assert!(graph
+            .reached_limits
+            .iter()
+            .all(|limit| matches!(limit, ReachedLimit::MaxStackDepth { pc_start: _ })));
+        assert!(!graph.reached_limits.is_empty());
+    }
+
+    #[test]
+    pub fn test_dead_values() {
+        // the constant set by the condition is only stored by the block at 0x0e
+        let bytecode: Bytecode = Bytecode::from(
+            "600035600b576001600e565b60025b60005500",
+            Hardfork::default(),
+        )
+        .unwrap();
+        let graph: Graph = Graph::from(&bytecode).unwrap();
+        assert!(!graph.live_slots[&0x0e].contains(0));
+        assert_eq!(graph.get_block(0x0e).nodes_count(), 1);
+
+        // the constant set by the condition is the destination of the jump at 0x0e
+        let bytecode: Bytecode = Bytecode::from(
+            "600035600b576010600e565b60125b565b005b00",
+            Hardfork::default(),
+        )
+        .unwrap();
+        let graph: Graph = Graph::from(&bytecode).unwrap();
+        assert!(graph.live_slots[&0x0e].contains(0));
+        assert_eq!(graph.get_block(0x0e).nodes_count(), 2);
+    }
+
+    #[test]
+    pub fn test_jump_destinations() {
+        // blocks start at 0x00, 0x07 (after the JUMPI), 0x0b and 0x0e (JUMPDESTs)
+        let bytecode: Bytecode = Bytecode::from(
+            "600035600b576001600e565b60025b60005500",
+            Hardfork::default(),
+        )
+        .unwrap();
+        let graph: Graph = Graph::from(&bytecode).unwrap();
+        assert!(graph.is_jump_destination(U256::from(0x0b)));
+        assert!(graph.is_jump_destination(U256::from(0x0e)));
+        assert!(!graph.is_jump_destination(U256::from(0x07)));
+        assert!(!graph.is_jump_destination(U256::from(0x03)));
+        assert!(!graph.is_jump_destination(U256::MAX));
+    }
+
+    #[test]
+    pub fn test_recursive_call() {
+        // the function at 0x0d calls itself from the block at 0x13, and returns at 0x1c and 0x20
+        let bytecode: Bytecode = Bytecode::from(
+            "6008600035600d565b600055005b8015602057601c60018203600d565b0190565b5060009056",
+            Hardfork::default(),
+        )
+        .unwrap();
+        let graph: Graph = Graph::from(&bytecode).unwrap();
+        assert_eq!(graph.recursive_calls, HashMap::from([(0x13, 0x0d)]));
+        assert_eq!(
+            graph.recursive_functions,
+            HashMap::from([(0x0d, HashSet::from([0x1c, 0x20]))])
+        );
+        // the execution goes on at the return address of the nested call
+        assert_eq!(
+            graph.get_block(0x13).get_child_pc_starts(),
+            HashSet::from([0x1c])
+        );
+    }
+
+    #[test]
+    pub fn test_unresolved_jump() {
+        // the first block jumps to calldata[0] when calldata[0x20] is non-zero
+        let bytecode: Bytecode =
+            Bytecode::from("602035600035576001600c565b005b600255", Hardfork::default()).unwrap();
+        let graph: Graph = Graph::from(&bytecode).unwrap();
+        assert!(graph.get_block(0x00).has_unresolved_jump());
+        assert_eq!(
+            graph.get_block(0x00).get_child_pc_starts(),
+            HashSet::from([0x07])
+        );
+        // the JUMPDESTs not reached otherwise are explored as possible destinations, without an edge
+        assert_eq!(
+            graph
+                .get_root_nodes()
+                .iter()
+                .map(|node| node.get_block().get_pc_start())
+                .collect::<Vec<usize>>(),
+            vec![0x00, 0x0e]
+        );
+    }
 }
@@ -0,0 +1,211 @@
+use super::block::Block;
+use crate::bytecode_reader::opcode::Opcode;
+use crate::create_blocks::symbolic_block::SymbolicBlock;
+use crate::create_blocks::symbolic_expression::StackExpression;
+use primitive_types::U256;
+use std::collections::{HashMap, HashSet};
+
+// How the stack leaving a block is made of the stack entering it. Slots are counted from the top.
+struct StackTransfer {
+    n_args: usize,
+    outputs: Vec<HashSet<usize>>, // for each output, from the top, the args its value depends on
+    jump_args: HashSet<usize>,    // the args the destination of the final jump depends on
+}
+
+impl StackTransfer {
+    fn from(symbolic_block: &SymbolicBlock) -> Self {
+        let outputs: Vec<HashSet<usize>> = symbolic_block
+            .symbolic_expressions
+            ._get_data()
+            .iter()
+            .rev()
+            .map(|symbolic_expression| get_args(&symbolic_expression.stack_expression))
+            .collect();
+        let jump_args: HashSet<usize> = match symbolic_block.final_effect() {
+            Some(final_effect) if final_effect.opcode.is_jump() => {
+                get_args(&final_effect.symbolic_expressions[0].stack_expression)
+            }
+            _ => HashSet::new(),
+        };
+        return StackTransfer {
+            n_args: symbolic_block.n_args,
+            outputs,
+            jump_args,
+        };
+    }
+
+    fn get_entry_slots(&self, exit_slot: usize) -> HashSet<usize> {
+        if exit_slot < self.outputs.len() {
+            return self.outputs[exit_slot].clone();
+        }
+        // untouched by the block
+        return HashSet::from([exit_slot - self.outputs.len() + self.n_args]);
+    }
+}
+
+// Args whose value is needed to evaluate the expression on a simple context. Values computed by
+// an opcode that cannot be evaluated (MLOAD, CALLDATALOAD...) are unknown whatever their inputs.
+fn get_args(expression: &StackExpression) -> HashSet<usize> {
+    match expression {
+        StackExpression::BYTES(_) => return HashSet::new(),
+        StackExpression::ARG(index) => return HashSet::from([index - 1]),
+        StackExpression::COMPOSE(opcode, symbolic_expressions) => {
+            if opcode.get_function().is_none() {
+                return HashSet::new();
+            }
+            return symbolic_expressions
+                .iter()
+                .flat_map(|symbolic_expression| get_args(&symbolic_expression.stack_expression))
+                .collect();
+        }
+    }
+}
+
+// Slots counted from the top of the stack when entering a block.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LiveSlots {
+    slots: HashSet<usize>,
+    deep_slots_from: Option<usize>, // every slot from this one on is live
+}
+
+impl LiveSlots {
+    pub fn contains(&self, slot: usize) -> bool {
+        return self.slots.contains(&slot)
+            || self
+                .deep_slots_from
+                .is_some_and(|deep_slots_from| slot >= deep_slots_from);
+    }
+
+    fn all() -> Self {
+        return LiveSlots {
+            slots: HashSet::new(),
+            deep_slots_from: Some(0),
+        };
+    }
+
+    fn extend(&mut self, other: &LiveSlots) {
+        self.slots.extend(&other.slots);
+        self.deep_slots_from = match (self.deep_slots_from, other.deep_slots_from) {
+            (Some(slot), Some(other_slot)) => Some(slot.min(other_slot)),
+            (slot, other_slot) => slot.or(other_slot),
+        };
+        if let Some(deep_slots_from) = self.deep_slots_from {
+            self.slots.retain(|slot| *slot < deep_slots_from);
+        }
+    }
+
+    // The slots entering the block whose value is needed by the live slots leaving it.
+    fn before(&self, stack_transfer: &StackTransfer, max_stack_depth: usize) -> LiveSlots {
+        let n_outputs: usize = stack_transfer.outputs.len();
+        let mut live_slots: LiveSlots = LiveSlots {
+            slots: stack_transfer.jump_args.clone(),
+            deep_slots_from: None,
+        };
+        for exit_slot in &self.slots {
+            live_slots
+                .slots
+                .extend(stack_transfer.get_entry_slots(*exit_slot));
+        }
+        if let Some(deep_slots_from) = self.deep_slots_from {
+            for exit_slot in deep_slots_from..n_outputs {
+                live_slots
+                    .slots
+                    .extend(stack_transfer.get_entry_slots(exit_slot));
+            }
+            live_slots.extend(&LiveSlots {
+                slots: HashSet::new(),
+                deep_slots_from: Some(
+                    deep_slots_from.max(n_outputs) - n_outputs + stack_transfer.n_args,
+                ),
+            });
+        }
+        // loops popping values could otherwise push the slots down forever
+        live_slots.slots.retain(|slot| *slot < max_stack_depth);
+        return live_slots;
+    }
+}
+
+// The destinations known without a context. The destination of a jump taken from the stack is
+// usually a return address: where the function returns is not known, so everything left on the
+// stack is considered live.
+fn get_successors(block: &Block) -> (Vec<usize>, bool) {
+    let Some(final_effect) = block.final_effect() else {
+        return (vec![block.get_next_pc_start()], false);
+    };
+    let mut successors: Vec<usize> = Vec::new();
+    let mut has_unknown_successor: bool = false;
+    if final_effect.opcode.is_relative_jump() {
+        if final_effect.opcode != Opcode::RJUMP {
+            successors.push(block.get_next_pc_start());
+        }
+        successors.extend(block.get_code().last().unwrap().get_relative_destinations());
+    } else if final_effect.opcode.is_jump() {
+        if final_effect.opcode == Opcode::JUMPI {
+            successors.push(block.get_next_pc_start());
+        }
+        match final_effect.symbolic_expressions[0]
+            .stack_expression
+            .compute_value()
+        {
+            Some(dest) if dest <= U256::from(usize::MAX) => successors.push(dest.as_usize()),
+            _ => has_unknown_successor = true,
+        }
+    } else if !final_effect.opcode.is_exiting() {
+        successors.push(block.get_next_pc_start());
+    }
+    return (successors, has_unknown_successor);
+}
+
+// For each block, the stack slots whose value may end up in the destination of a jump. The other
+// values cannot change the graph, so contexts that only differ by them can share the same node.
+// Typically, locals set to different constants in the branches of a condition are popped before
+// the function returns: they do not duplicate the rest of the function.
+pub fn compute_live_slots(
+    origin_blocks: &HashMap<usize, Block>,
+    max_stack_depth: usize,
+) -> HashMap<usize, LiveSlots> {
+    let mut stack_transfers: HashMap<usize, StackTransfer> = HashMap::new();
+    let mut successors: HashMap<usize, (Vec<usize>, bool)> = HashMap::new();
+    let mut predecessors: HashMap<usize, HashSet<usize>> = HashMap::new();
+    let mut live_slots: HashMap<usize, LiveSlots> = HashMap::new();
+    for (pc_start, block) in origin_blocks {
+        let (mut block_successors, has_unknown_successor) = get_successors(block);
+        block_successors.retain(|dest| origin_blocks.contains_key(dest));
+        for successor in &block_successors {
+            predecessors
+                .entry(*successor)
+                .or_default()
+                .insert(*pc_start);
+        }
+        stack_transfers.insert(
+            *pc_start,
+            StackTransfer::from(&block.clone_symbolic_block()),
+        );
+        successors.insert(*pc_start, (block_successors, has_unknown_successor));
+        live_slots.insert(*pc_start, LiveSlots::default());
+    }
+
+    // backward propagation, the sets only grow until they are stable
+    let mut blocks_to_update: Vec<usize> = origin_blocks.keys().cloned().collect();
+    while let Some(pc_start) = blocks_to_update.pop() {
+        let (block_successors, has_unknown_successor) = &successors[&pc_start];
+        let mut exit_live_slots: LiveSlots = if *has_unknown_successor {
+            LiveSlots::all()
+        } else {
+            LiveSlots::default()
+        };
+        for successor in block_successors {
+            exit_live_slots.extend(&live_slots[successor]);
+        }
+        let mut new_live_slots: LiveSlots = live_slots[&pc_start].clone();
+        new_live_slots
+            .extend(&exit_live_slots.before(&stack_transfers[&pc_start], max_stack_depth));
+        if new_live_slots != live_slots[&pc_start] {
+            live_slots.insert(pc_start, new_live_slots);
+            if let Some(block_predecessors) = predecessors.get(&pc_start) {
+                blocks_to_update.extend(block_predecessors);
+            }
+        }
+    }
+    return live_slots;
+}
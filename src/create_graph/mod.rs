@@ -1,6 +1,9 @@
 pub mod block;
 pub mod duplication;
+pub mod exploration_limits;
 pub mod graph;
+pub mod jump_liveness;
 pub mod node;
 mod post_processing;
+pub mod recursion;
 pub mod simple_evm;
@@ -1,4 +1,5 @@
 use super::{block::Block, simple_evm::SimpleContext};
+use crate::error::PellucidError;
 use crate::tools::utils::calculate_hash;
 use std::collections::HashSet;
 use std::fmt::{self, Debug};
@@ -36,8 +37,32 @@ impl<'a> PartialEq for Node<'a> {
 impl<'a> Eq for Node<'a> {}
 
 impl<'a> Node<'a> {
-    pub fn create_and_attach(block: Block<'a>, initial_context: SimpleContext) -> Self {
-        let final_context: SimpleContext = block.apply_on_simple_context(&initial_context);
+    pub fn create_and_attach(
+        block: Block<'a>,
+        initial_context: SimpleContext,
+    ) -> Result<Self, PellucidError> {
+        let final_context: SimpleContext = block.apply_on_simple_context(&initial_context)?;
+        return Ok(Self::attach_with_contexts(
+            block,
+            initial_context,
+            final_context,
+        ));
+    }
+
+    pub fn duplicate_on(&self, block: Block<'a>) -> Self {
+        // the duplicated block has the same code, so the final context does not need to be computed again
+        return Self::attach_with_contexts(
+            block,
+            self.clone_initial_context(),
+            self.clone_final_context(),
+        );
+    }
+
+    fn attach_with_contexts(
+        block: Block<'a>,
+        initial_context: SimpleContext,
+        final_context: SimpleContext,
+    ) -> Self {
         let node = Node {
             inner: Rc::new(RefCell::new(InnerNode {
                 initial_context: initial_context,
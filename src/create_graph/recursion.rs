@@ -0,0 +1,291 @@
+use super::{
+    block::Block,
+    graph::Graph,
+    node::Node,
+    simple_evm::{SimpleContext, SimpleStackExpression},
+};
+use crate::{
+    bytecode_reader::opcode::Opcode,
+    error::PellucidError,
+    tools::{graph_tools::node_dfs, utils::calculate_hash},
+};
+use std::collections::HashSet;
+
+// A function called again before it returns. The new call is not explored like the others:
+// its stack starts with the stack of the first call, so it would grow forever.
+#[derive(Debug)]
+pub struct RecursiveCall<'a> {
+    pub calling_node: Node<'a>,
+    pub entry_node: Node<'a>, // first call of the function, whose frame is still on the stack
+    pub context: SimpleContext,
+}
+
+// Returns the first call of the function starting at `block_dest` if jumping there from
+// `calling_node` calls it again.
+pub fn find_recursion_entry<'a>(
+    graph: &Graph<'a>,
+    calling_node: &Node<'a>,
+    block_dest: &Block<'a>,
+    context: &SimpleContext,
+) -> Option<Node<'a>> {
+    // functions are entered by a jump
+    if block_dest.get_code()[0].opcode != Opcode::JUMPDEST {
+        return None;
+    }
+    return block_dest.get_nodes().into_iter().find(|entry_node| {
+        let entry_context: SimpleContext = entry_node.clone_initial_context();
+        let Some(frame_size) = get_frame_size(graph, &entry_context, context) else {
+            return false;
+        };
+        let base: usize = entry_context.stack.len() - frame_size;
+        let return_address_index: usize =
+            get_return_address_index(graph, &entry_context.stack._get_data()[base..]).unwrap();
+        return leads_to(entry_node, calling_node, base + return_address_index);
+    });
+}
+
+// The stack of a nested call is the stack of the first call, where the return address is kept,
+// with a frame of the same size (a return address and the arguments) pushed on top of it.
+fn get_frame_size(
+    graph: &Graph,
+    entry_context: &SimpleContext,
+    context: &SimpleContext,
+) -> Option<usize> {
+    let entry_stack: &Vec<SimpleStackExpression> = entry_context.stack._get_data();
+    let stack: &Vec<SimpleStackExpression> = context.stack._get_data();
+    if stack.len() <= entry_stack.len() || 2 * entry_stack.len() < stack.len() {
+        return None;
+    }
+    let frame_size: usize = stack.len() - entry_stack.len();
+    let base: usize = entry_stack.len() - frame_size;
+    if stack[..base] != entry_stack[..base] {
+        return None;
+    }
+    let return_address_index: usize = get_return_address_index(graph, &entry_stack[base..])?;
+    if stack[base + return_address_index] != entry_stack[base + return_address_index]
+        || get_jump_destination(graph, &stack[entry_stack.len() + return_address_index]).is_none()
+    {
+        return None;
+    }
+    return Some(frame_size);
+}
+
+fn get_return_address_index(graph: &Graph, frame: &[SimpleStackExpression]) -> Option<usize> {
+    return frame
+        .iter()
+        .position(|expression| get_jump_destination(graph, expression).is_some());
+}
+
+fn get_jump_destination<'a>(
+    graph: &Graph<'a>,
+    expression: &SimpleStackExpression,
+) -> Option<Block<'a>> {
+    let SimpleStackExpression::BYTES(value) = expression else {
+        return None;
+    };
+    if !graph.is_jump_destination(*value) {
+        return None;
+    }
+    return graph.origin_blocks.get(&value.as_usize()).cloned();
+}
+
+// True if `node` can be reached from `entry_node` within the call: the return address of the
+// call stays on the stack until it returns, so the walk stops at the nodes whose stack is too
+// short to hold it.
+fn leads_to<'a>(entry_node: &Node<'a>, node: &Node<'a>, return_address_position: usize) -> bool {
+    let mut visited_nodes: HashSet<u64> = HashSet::new();
+    let mut nodes_to_visit: Vec<Node<'a>> = vec![node.clone()];
+    while let Some(current) = nodes_to_visit.pop() {
+        if &current == entry_node {
+            return true;
+        }
+        if current.clone_initial_context().stack.len() <= return_address_position {
+            continue;
+        }
+        if visited_nodes.insert(calculate_hash(&current)) {
+            nodes_to_visit.extend(current.get_parents());
+        }
+    }
+    return false;
+}
+
+// Connections (returning node, node at the return address) leaving the frame of the call
+// starting at `entry_node`.
+fn get_returns<'a>(
+    graph: &Graph<'a>,
+    entry_node: &Node<'a>,
+    frame_size: usize,
+) -> Vec<(Node<'a>, Node<'a>)> {
+    let entry_context: SimpleContext = entry_node.clone_initial_context();
+    let entry_stack: &Vec<SimpleStackExpression> = entry_context.stack._get_data();
+    let base: usize = entry_stack.len() - frame_size;
+    let return_block: Block<'a> = get_return_address_index(graph, &entry_stack[base..])
+        .and_then(|index| get_jump_destination(graph, &entry_stack[base + index]))
+        .unwrap();
+    let is_return = |node: &Node<'a>| -> bool {
+        let stack: SimpleContext = node.clone_initial_context();
+        return node.get_block() == return_block
+            && stack.stack.len() >= base
+            && stack.stack._get_data()[..base] == entry_stack[..base];
+    };
+    let mut returns: Vec<(Node<'a>, Node<'a>)> = Vec::new();
+    node_dfs(
+        entry_node,
+        is_return,
+        &mut |parent: &Node<'a>, child: &Node<'a>| {
+            if is_return(child) {
+                returns.push((parent.clone(), child.clone()));
+            }
+        },
+    );
+    return returns;
+}
+
+// Where the nested call goes back to, with the results of the function on top of the stack.
+// Unknown as long as the function has not been seen returning.
+fn get_return_context<'a>(
+    graph: &Graph<'a>,
+    recursive_call: &RecursiveCall<'a>,
+) -> Option<(Block<'a>, SimpleContext)> {
+    let entry_context: SimpleContext = recursive_call.entry_node.clone_initial_context();
+    let frame_size: usize = get_frame_size(graph, &entry_context, &recursive_call.context)?;
+    let (_, first_return) = get_returns(graph, &recursive_call.entry_node, frame_size)
+        .first()?
+        .clone();
+    let base: usize = entry_context.stack.len() - frame_size;
+    let n_results: usize = first_return.clone_initial_context().stack.len() - base;
+
+    // the caller of the nested call is the first call, its stack is the one of the entry
+    let stack: &Vec<SimpleStackExpression> = recursive_call.context.stack._get_data();
+    let caller_stack_size: usize = entry_context.stack.len();
+    let return_address_index: usize =
+        get_return_address_index(graph, &entry_context.stack._get_data()[base..])?;
+    let return_block: Block<'a> =
+        get_jump_destination(graph, &stack[caller_stack_size + return_address_index])?;
+
+    let mut return_context: SimpleContext = SimpleContext::new();
+    for expression in &stack[..caller_stack_size] {
+        return_context.stack.push(expression.clone());
+    }
+    for _ in 0..n_results {
+        return_context.stack.push(SimpleStackExpression::OTHER);
+    }
+    return_context.forget_dead_values(&graph.live_slots[&return_block.get_pc_start()]);
+    return Some((return_block, return_context));
+}
+
+// The nested calls are not explored, the execution goes on at their return address once the
+// function is known to return. Calls of functions that never return stay without children.
+pub fn explore_recursive_calls<'a>(graph: &mut Graph<'a>) -> Result<(), PellucidError> {
+    let mut frame_size_per_entry: Vec<(Node<'a>, usize)> = Vec::new();
+    loop {
+        let recursive_calls: Vec<RecursiveCall<'a>> =
+            std::mem::take(&mut graph.pending_recursive_calls);
+        let n_recursive_calls: usize = recursive_calls.len();
+        let mut unresolved_calls: Vec<RecursiveCall<'a>> = Vec::new();
+        for recursive_call in recursive_calls {
+            let entry_context: SimpleContext = recursive_call.entry_node.clone_initial_context();
+            let frame_size: usize =
+                get_frame_size(graph, &entry_context, &recursive_call.context).unwrap();
+            if !frame_size_per_entry
+                .iter()
+                .any(|(entry_node, _)| entry_node == &recursive_call.entry_node)
+            {
+                frame_size_per_entry.push((recursive_call.entry_node.clone(), frame_size));
+            }
+            graph.recursive_calls.insert(
+                recursive_call.calling_node.get_block().get_pc_start(),
+                recursive_call.entry_node.get_block().get_pc_start(),
+            );
+            log::debug!(
+                "Recursive call of the function at {:#x}",
+                recursive_call.entry_node.get_block().get_pc_start()
+            );
+            match get_return_context(graph, &recursive_call) {
+                Some((return_block, return_context)) => {
+                    graph.explore_child(
+                        &recursive_call.calling_node,
+                        &return_block,
+                        return_context,
+                    )?;
+                    graph.explore()?;
+                }
+                None => unresolved_calls.push(recursive_call),
+            }
+        }
+        // the explored returns may contain other recursive calls
+        let is_stuck: bool = unresolved_calls.len() == n_recursive_calls;
+        graph.pending_recursive_calls.extend(unresolved_calls);
+        if graph.pending_recursive_calls.is_empty() || is_stuck {
+            break;
+        }
+    }
+    graph.pending_recursive_calls.clear();
+
+    for (entry_node, frame_size) in frame_size_per_entry {
+        let returning_pc_starts: HashSet<usize> = get_returns(graph, &entry_node, frame_size)
+            .iter()
+            .map(|(returning_node, _)| returning_node.get_block().get_pc_start())
+            .collect();
+        graph
+            .recursive_functions
+            .entry(entry_node.get_block().get_pc_start())
+            .or_default()
+            .extend(returning_pc_starts);
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompiler::Decompiler;
+    use crate::execution_flow::execution_flow::{Scope, MAIN_FUNCTION_LABEL};
+    use crate::execution_flow::with_variables::flow_with_vars::ExecutionFlowWithVars;
+    use crate::execution_flow::with_variables::incorporate_variables::Value;
+    use crate::execution_flow::with_variables::scopes_with_vars::{FunctionKind, FunctionWithVars};
+    use primitive_types::U256;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_explore_recursive_calls() {
+        // fn(n) = n == 0 ? 0 : fn(n - 1) + n, called on calldata[0x00] and calldata[0x20]
+        let decompiler: Decompiler = Decompiler::from_hex(
+            "60086000356019565b60005560146020356019565b600155005b8015602c576028600182036019565b0190565b5060009056",
+        )
+        .unwrap();
+        // the block at 0x1f calls the function at 0x19 again, which returns at 0x28 and 0x2c
+        let graph: Graph = decompiler.build_graph().unwrap();
+        assert_eq!(graph.recursive_calls, HashMap::from([(0x1f, 0x19)]));
+        assert_eq!(
+            graph.recursive_functions,
+            HashMap::from([(0x19, HashSet::from([0x28, 0x2c]))])
+        );
+
+        let flow: ExecutionFlowWithVars = decompiler.decompile().unwrap().execution_flow_with_vars;
+        let internal_functions: Vec<&FunctionWithVars> = flow
+            .functions
+            .values()
+            .filter(|function| function.kind == FunctionKind::Internal)
+            .collect();
+        assert_eq!(internal_functions.len(), 1);
+        assert!(ExecutionFlowWithVars::is_recursive(internal_functions[0]));
+        let mut called_arguments: Vec<Value> = Vec::new();
+        ExecutionFlowWithVars::apply_on_scopes(
+            &flow.functions[&MAIN_FUNCTION_LABEL].content,
+            &mut |scope| {
+                if let Scope::FunctionCall(function_call) = scope {
+                    assert_eq!(function_call.label, internal_functions[0].label);
+                    called_arguments.push(function_call.arguments[1].clone());
+                }
+            },
+        );
+        assert_eq!(
+            called_arguments,
+            [0x00, 0x20].map(|offset| Value::Calculation {
+                opcode: Opcode::CALLDATALOAD,
+                args: vec![Value::Bytes(U256::from(offset))],
+            })
+        );
+    }
+}
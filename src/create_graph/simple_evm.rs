@@ -1,11 +1,15 @@
 use primitive_types::U256;
 use serde::{Deserialize, Serialize};
 
+use super::jump_liveness::LiveSlots;
 use crate::tools::{stack::Stack, utils::u256_to_hex};
 
+#[allow(non_camel_case_types)]
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub enum SimpleStackExpression {
     BYTES(U256),
+    COMPUTED_BYTES(U256), // result of arithmetic on known values, only kept when it may be a jump destination
+    UNUSED_BYTES,         // known value that cannot change the destination of a jump
     OTHER,
 }
 
@@ -13,18 +17,33 @@ impl ToString for SimpleStackExpression {
     fn to_string(&self) -> String {
         return match self {
             SimpleStackExpression::OTHER => String::from("◻"),
+            SimpleStackExpression::UNUSED_BYTES => String::from("◼"),
             SimpleStackExpression::BYTES(value) => format!("{}", u256_to_hex(*value)),
+            SimpleStackExpression::COMPUTED_BYTES(value) => format!("({})", u256_to_hex(*value)),
+        };
+    }
+}
+
+impl SimpleStackExpression {
+    pub fn get_value(&self) -> Option<U256> {
+        return match self {
+            SimpleStackExpression::BYTES(value) | SimpleStackExpression::COMPUTED_BYTES(value) => {
+                Some(*value)
+            }
+            SimpleStackExpression::UNUSED_BYTES | SimpleStackExpression::OTHER => None,
         };
     }
 }
 
 pub type SimpleStack = Stack<SimpleStackExpression>;
 
+#[allow(non_camel_case_types)]
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
 pub enum State {
     RUNNING,
     STOP,
     JUMP(Vec<usize>),
+    UNRESOLVED_JUMP(Vec<usize>), // only the known destinations (the next pc of a JUMPI)
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Hash, Serialize, Deserialize)]
@@ -40,4 +59,59 @@ impl SimpleContext {
             state: State::RUNNING,
         };
     }
+
+    // Slots are counted from the top of the stack. Known values are not mixed up with unknown
+    // ones: the first iteration of a loop, where the counter is still known, must only join the
+    // next ones at the beginning of the loop.
+    pub fn forget_dead_values(&mut self, live_slots: &LiveSlots) {
+        let stack_size: usize = self.stack.len();
+        for (index, expression) in self.stack.iter_mut().enumerate() {
+            if !live_slots.contains(stack_size - 1 - index) && expression.get_value().is_some() {
+                *expression = SimpleStackExpression::UNUSED_BYTES;
+            }
+        }
+    }
+
+    // The values that differ between the two stacks become unknown. Stacks of different sizes
+    // cannot be merged, nor stacks holding different jump destinations: the merged context would
+    // jump anywhere, joining unrelated paths into loops that cannot be cut.
+    pub fn merge(
+        &self,
+        other: &SimpleContext,
+        is_jump_destination: impl Fn(U256) -> bool,
+    ) -> Option<SimpleContext> {
+        if self.stack.len() != other.stack.len() || self.state != other.state {
+            return None;
+        }
+        let mut merged_context: SimpleContext = self.clone();
+        for (expression, other_expression) in
+            merged_context.stack.iter_mut().zip(other.stack.iter())
+        {
+            if expression == other_expression {
+                continue;
+            }
+            if expression
+                .get_value()
+                .into_iter()
+                .chain(other_expression.get_value())
+                .any(&is_jump_destination)
+            {
+                return None;
+            }
+            *expression = SimpleStackExpression::OTHER;
+        }
+        return Some(merged_context);
+    }
+
+    // Computed values are compared like pushed bytes only when they match a jump destination.
+    // Others (loop counters, offsets...) are forgotten, otherwise loops would be unrolled forever.
+    pub fn forget_computed_bytes(&mut self, is_jump_destination: impl Fn(U256) -> bool) {
+        for expression in self.stack.iter_mut() {
+            if let SimpleStackExpression::COMPUTED_BYTES(value) = expression {
+                if !is_jump_destination(*value) {
+                    *expression = SimpleStackExpression::OTHER;
+                }
+            }
+        }
+    }
 }
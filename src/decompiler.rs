@@ -0,0 +1,560 @@
+use crate::bytecode_reader::bytecode::{strip_library_placeholders, Bytecode, LibraryHash};
+use crate::bytecode_reader::creation::CreationBytecode;
+use crate::bytecode_reader::eof::{is_eof, EofContainer};
+use crate::bytecode_reader::hardfork::Hardfork;
+use crate::create_graph::exploration_limits::ExplorationLimits;
+use crate::create_graph::graph::Graph;
+use crate::detect_cycles::acyclic_graph::AcyclicGraph;
+use crate::error::PellucidError;
+use crate::execution_flow::skeleton::skeleton::Skeleton;
+use crate::execution_flow::with_opcodes::eof_sections::merge_eof_section_flows;
+use crate::execution_flow::with_opcodes::flow_with_opcodes::{
+    convert_skeleton_to_execution_flow, ExecutionFlowWithOpcodes,
+};
+use crate::execution_flow::with_variables::events::EventDescription;
+use crate::execution_flow::with_variables::flow_with_vars::{
+    convert_opcode_flow_to_var_flow, ExecutionFlowWithVars,
+};
+use crate::execution_flow::with_variables::storage_layout::StorageLayout;
+use crate::signatures::signature_database::SignatureDatabase;
+use crate::tools::utils::remove_0x;
+
+// The graph, the acyclic graph and the skeleton borrow each other (and the bytecode), so they
+// cannot be returned together. They are handed to an inspector while the pipeline runs instead.
+// With an EOF container, they are produced once per code section.
+pub trait PipelineInspector {
+    fn on_graph(&mut self, _graph: &Graph) {}
+
+    fn on_acyclic_graph(&mut self, _a_graph: &AcyclicGraph) {}
+
+    fn on_skeleton(&mut self, _skeleton: &Skeleton) {}
+
+    fn on_execution_flow_with_opcodes(&mut self, _flow: &ExecutionFlowWithOpcodes) {}
+}
+
+pub struct Decompilation {
+    pub execution_flow_with_opcodes: ExecutionFlowWithOpcodes,
+    pub execution_flow_with_vars: ExecutionFlowWithVars,
+}
+
+impl Decompilation {
+    pub fn apply_signatures(&mut self, signatures: &SignatureDatabase) {
+        self.execution_flow_with_vars.apply_signatures(signatures);
+    }
+
+    pub fn get_storage_layout(&self) -> StorageLayout {
+        return self.execution_flow_with_vars.get_storage_layout();
+    }
+
+    pub fn get_events(&self) -> Vec<EventDescription> {
+        return self.execution_flow_with_vars.get_events();
+    }
+}
+
+impl ToString for Decompilation {
+    fn to_string(&self) -> String {
+        return self.execution_flow_with_vars.to_string();
+    }
+}
+
+pub struct CreationDecompilation {
+    pub constructor: Decompilation,
+    pub runtime: Decompilation,
+    pub constructor_arguments: Vec<u8>,
+}
+
+impl CreationDecompilation {
+    pub fn apply_signatures(&mut self, signatures: &SignatureDatabase) {
+        self.constructor.apply_signatures(signatures);
+        self.runtime.apply_signatures(signatures);
+    }
+}
+
+impl ToString for CreationDecompilation {
+    fn to_string(&self) -> String {
+        let mut res: String = format!(
+            "// constructor\n{}\n\n// runtime\n{}",
+            self.constructor.to_string(),
+            self.runtime.to_string()
+        );
+        if !self.constructor_arguments.is_empty() {
+            res += &format!(
+                "\n\n// constructor arguments: 0x{}",
+                hex::encode(&self.constructor_arguments)
+            );
+        }
+        return res;
+    }
+}
+
+#[derive(Debug)]
+enum Program {
+    Legacy(Bytecode),
+    Eof(EofContainer),
+}
+
+#[derive(Debug)]
+pub struct Decompiler {
+    program: Program,
+    exploration_limits: ExplorationLimits,
+}
+
+struct NoInspection;
+
+impl PipelineInspector for NoInspection {}
+
+impl Decompiler {
+    // The hardfork is guessed from the solc version found in the metadata.
+    pub fn from_hex(raw_bytecode: &str) -> Result<Self, PellucidError> {
+        return Decompiler::from_hex_with_hardfork(raw_bytecode, Hardfork::detect(raw_bytecode));
+    }
+
+    pub fn from_hex_with_hardfork(
+        raw_bytecode: &str,
+        hardfork: Hardfork,
+    ) -> Result<Self, PellucidError> {
+        let (vec_bytecode, library_placeholders) = decode_hex_bytecode(raw_bytecode)?;
+        let mut decompiler: Decompiler = Decompiler::from_bytes(&vec_bytecode, hardfork)?;
+        if let Program::Legacy(bytecode) = &mut decompiler.program {
+            bytecode.link_library_placeholders(&library_placeholders, 0);
+        }
+        return Ok(decompiler);
+    }
+
+    // EOF containers are recognized by their magic, anything else is legacy bytecode.
+    pub fn from_bytes(raw_bytecode: &[u8], hardfork: Hardfork) -> Result<Self, PellucidError> {
+        if is_eof(raw_bytecode) {
+            return Ok(Decompiler::from_eof_container(EofContainer::from_bytes(
+                raw_bytecode,
+                hardfork,
+            )?));
+        }
+        return Ok(Decompiler::from_bytecode(Bytecode::from_bytes(
+            raw_bytecode,
+            hardfork,
+        )));
+    }
+
+    pub fn from_bytecode(bytecode: Bytecode) -> Self {
+        return Decompiler {
+            program: Program::Legacy(bytecode),
+            exploration_limits: ExplorationLimits::default(),
+        };
+    }
+
+    pub fn from_eof_container(eof_container: EofContainer) -> Self {
+        return Decompiler {
+            program: Program::Eof(eof_container),
+            exploration_limits: ExplorationLimits::default(),
+        };
+    }
+
+    // Big contracts may need tighter limits to be decompiled in a reasonable time.
+    pub fn set_exploration_limits(&mut self, exploration_limits: ExplorationLimits) {
+        self.exploration_limits = exploration_limits;
+    }
+
+    // For an EOF container, this is the first code section.
+    pub fn get_bytecode(&self) -> &Bytecode {
+        match &self.program {
+            Program::Legacy(bytecode) => return bytecode,
+            Program::Eof(eof_container) => return &eof_container.code_sections[0],
+        }
+    }
+
+    pub fn get_eof_container(&self) -> Option<&EofContainer> {
+        match &self.program {
+            Program::Legacy(_) => return None,
+            Program::Eof(eof_container) => return Some(eof_container),
+        }
+    }
+
+    pub fn build_graph(&self) -> Result<Graph<'_>, PellucidError> {
+        return Graph::from_with_limits(self.get_bytecode(), self.exploration_limits);
+    }
+
+    pub fn decompile(&self) -> Result<Decompilation, PellucidError> {
+        return self.decompile_with(&mut NoInspection);
+    }
+
+    pub fn decompile_with(
+        &self,
+        inspector: &mut impl PipelineInspector,
+    ) -> Result<Decompilation, PellucidError> {
+        let execution_flow_with_opcodes: ExecutionFlowWithOpcodes = match &self.program {
+            Program::Legacy(bytecode) => {
+                build_opcode_flow(bytecode, self.exploration_limits, inspector)?
+            }
+            Program::Eof(eof_container) => {
+                let mut section_flows: Vec<ExecutionFlowWithOpcodes> = Vec::new();
+                for code_section in &eof_container.code_sections {
+                    section_flows.push(build_opcode_flow(
+                        code_section,
+                        self.exploration_limits,
+                        inspector,
+                    )?);
+                }
+                merge_eof_section_flows(section_flows, &eof_container.types)
+            }
+        };
+        inspector.on_execution_flow_with_opcodes(&execution_flow_with_opcodes);
+        let execution_flow_with_vars: ExecutionFlowWithVars =
+            convert_opcode_flow_to_var_flow(&execution_flow_with_opcodes)?;
+        return Ok(Decompilation {
+            execution_flow_with_opcodes,
+            execution_flow_with_vars,
+        });
+    }
+}
+
+// Creation bytecode holds the constructor, the runtime bytecode it deploys and the constructor
+// arguments. Both codes are decompiled separately.
+#[derive(Debug)]
+pub struct CreationDecompiler {
+    pub constructor: Decompiler,
+    pub runtime: Decompiler,
+    pub constructor_arguments: Vec<u8>,
+}
+
+impl CreationDecompiler {
+    pub fn from_hex(raw_bytecode: &str) -> Result<Self, PellucidError> {
+        let (vec_bytecode, library_placeholders) = decode_hex_bytecode(raw_bytecode)?;
+        let mut creation_bytecode: CreationBytecode =
+            CreationBytecode::from_bytes(&vec_bytecode, Hardfork::detect(raw_bytecode))?;
+        creation_bytecode.link_library_placeholders(&library_placeholders);
+        return Ok(CreationDecompiler {
+            constructor: Decompiler::from_bytecode(creation_bytecode.constructor),
+            runtime: Decompiler::from_bytecode(creation_bytecode.runtime),
+            constructor_arguments: creation_bytecode.constructor_arguments,
+        });
+    }
+
+    pub fn decompile(&self) -> Result<CreationDecompilation, PellucidError> {
+        return self.decompile_with(&mut NoInspection);
+    }
+
+    // The inspector sees the constructor stages first, then the runtime ones.
+    pub fn decompile_with(
+        &self,
+        inspector: &mut impl PipelineInspector,
+    ) -> Result<CreationDecompilation, PellucidError> {
+        return Ok(CreationDecompilation {
+            constructor: self.constructor.decompile_with(inspector)?,
+            runtime: self.runtime.decompile_with(inspector)?,
+            constructor_arguments: self.constructor_arguments.clone(),
+        });
+    }
+}
+
+// Build outputs may not be linked yet, library placeholders are kept aside.
+fn decode_hex_bytecode(
+    raw_bytecode: &str,
+) -> Result<(Vec<u8>, Vec<(usize, LibraryHash)>), PellucidError> {
+    let (hex_bytecode, library_placeholders) = strip_library_placeholders(remove_0x(raw_bytecode));
+    match hex::decode(hex_bytecode) {
+        Ok(res) => return Ok((res, library_placeholders)),
+        Err(error) => return Err(PellucidError::InvalidHex(error.to_string())),
+    }
+}
+
+fn build_opcode_flow(
+    bytecode: &Bytecode,
+    exploration_limits: ExplorationLimits,
+    inspector: &mut impl PipelineInspector,
+) -> Result<ExecutionFlowWithOpcodes, PellucidError> {
+    let mut graph: Graph = Graph::from_with_limits(bytecode, exploration_limits)?;
+    inspector.on_graph(&graph);
+    let mut a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph)?;
+    inspector.on_acyclic_graph(&a_graph);
+    let skeleton: Skeleton = Skeleton::build(&mut a_graph)?;
+    inspector.on_skeleton(&skeleton);
+    return convert_skeleton_to_execution_flow(&skeleton);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution_flow::execution_flow::{AccessContent, MAIN_FUNCTION_LABEL};
+    use crate::execution_flow::with_opcodes::eof_sections::compute_section_label;
+    use crate::execution_flow::with_opcodes::scopes_with_opcodes::FunctionWithOpcodes;
+    use crate::tools::utils::read_file;
+
+    #[derive(Default)]
+    struct StageCounter {
+        n_blocks: usize,
+        n_loops: usize,
+        n_skeleton_scopes: usize,
+        n_opcode_functions: usize,
+    }
+
+    impl PipelineInspector for StageCounter {
+        fn on_graph(&mut self, graph: &Graph) {
+            self.n_blocks = graph.get_all_blocks().len();
+        }
+
+        fn on_acyclic_graph(&mut self, a_graph: &AcyclicGraph) {
+            self.n_loops = a_graph.disconnected_connections.len();
+        }
+
+        fn on_skeleton(&mut self, skeleton: &Skeleton) {
+            self.n_skeleton_scopes = skeleton.main_instructions.len();
+        }
+
+        fn on_execution_flow_with_opcodes(&mut self, flow: &ExecutionFlowWithOpcodes) {
+            self.n_opcode_functions = flow.functions.len();
+        }
+    }
+
+    #[test]
+    fn test_decompiler_stages() {
+        let decompiler: Decompiler =
+            Decompiler::from_hex(&read_file("./contracts/loop/contract_0/bytecode.txt")).unwrap();
+        let mut counter: StageCounter = StageCounter::default();
+        let decompilation: Decompilation = decompiler.decompile_with(&mut counter).unwrap();
+
+        assert!(counter.n_blocks > 0);
+        assert!(counter.n_loops > 0);
+        assert!(counter.n_skeleton_scopes > 0);
+        assert!(counter.n_opcode_functions >= 1);
+        assert!(decompilation
+            .execution_flow_with_vars
+            .functions
+            .contains_key(&MAIN_FUNCTION_LABEL));
+        assert!(!decompilation.to_string().is_empty());
+    }
+
+    #[test]
+    fn test_decompiler_signatures() {
+        let decompiler: Decompiler =
+            Decompiler::from_hex(&read_file("./contracts/simple/contract_0/bytecode.txt")).unwrap();
+        let mut decompilation: Decompilation = decompiler.decompile().unwrap();
+        let mut signatures: SignatureDatabase = SignatureDatabase::with_common_signatures();
+        signatures.add_signature("f()").unwrap();
+        decompilation.apply_signatures(&signatures);
+        let decompiled: String = decompilation.to_string();
+        assert!(decompiled.contains("function f() external"));
+        assert!(decompiled.contains("function selector_0xb28175c4() external"));
+        assert!(decompiled.contains("var_0 = var_0 + 0x01 /* checked */"));
+
+        // storage[0] = calldata[0x24], storage[1] = calldata[0x44]
+        let decompiler: Decompiler =
+            Decompiler::from_hex("60003560e01c80631111111114601157005b60243560005560443560015500")
+                .unwrap();
+        let mut decompilation: Decompilation = decompiler.decompile().unwrap();
+        let mut signatures: SignatureDatabase = SignatureDatabase::new();
+        signatures
+            .add_hashed_signature("0x11111111", "f(uint256[2],uint256)")
+            .unwrap();
+        decompilation.apply_signatures(&signatures);
+        let decompiled: String = decompilation.to_string();
+        assert!(decompiled.contains("function f(uint256[2] arg0, uint256 arg1) external"));
+        assert!(decompiled.contains("storage[0x00] = arg0[1]"));
+        assert!(decompiled.contains("storage[0x01] = arg1"));
+    }
+
+    #[test]
+    fn test_decompiler_checked_arithmetic() {
+        // `i++` in a loop, checked by `i == MAX`
+        let decompiler: Decompiler =
+            Decompiler::from_hex(&read_file("./contracts/simple/contract_0/bytecode.txt")).unwrap();
+        let decompiled: String = decompiler.decompile().unwrap().to_string();
+        assert!(decompiled.contains("var_0 = var_0 + 0x01 /* checked */"));
+        assert!(!decompiled.contains("ARITHMETIC_OVERFLOW"));
+
+        // storage[0] = calldata[0] + calldata[4], checked by `x > ~y`
+        let decompiler: Decompiler = Decompiler::from_hex(
+            "6004356000358119811115602357634e487b7160e01b600052601160045260246000fd5b0160005500",
+        )
+        .unwrap();
+        let decompiled: String = decompiler.decompile().unwrap().to_string();
+        assert!(decompiled
+            .contains("storage[0x00] = (calldata[0x00]) + (calldata[0x04]) /* checked */"));
+        assert!(!decompiled.contains("panic"));
+
+        // storage[0] = calldata[0] / calldata[4], checked by `y == 0`
+        let decompiler: Decompiler = Decompiler::from_hex(
+            "60043560003581601f57634e487b7160e01b600052601260045260246000fd5b0460005500",
+        )
+        .unwrap();
+        let decompiled: String = decompiler.decompile().unwrap().to_string();
+        assert!(decompiled
+            .contains("storage[0x00] = (calldata[0x00]) / (calldata[0x04]) /* checked */"));
+        assert!(!decompiled.contains("panic"));
+
+        // `calldata[0] + calldata[4] == 0` does not guard an increment
+        let decompiler: Decompiler = Decompiler::from_hex(
+            "60043560003501601f57634e487b7160e01b600052601160045260246000fd5b00",
+        )
+        .unwrap();
+        let decompiled: String = decompiler.decompile().unwrap().to_string();
+        assert!(decompiled.contains("panic(ARITHMETIC_OVERFLOW)"));
+    }
+
+    #[test]
+    fn test_decompiler_loops() {
+        let decompiler: Decompiler =
+            Decompiler::from_hex(&read_file("./contracts/simple/contract_0/bytecode.txt")).unwrap();
+        let decompiled: String = decompiler.decompile().unwrap().to_string();
+        assert!(decompiled.contains(
+            "    loop_1: for (var_0 = 0x00; var_0 < 0x0c; var_0 = var_0 + 0x01 /* checked */) {\n"
+        ));
+        assert!(decompiled.contains(
+            "    loop_0: for (var_0 = 0x00; !(var_0 > 0x06); var_0 = 0x01 + var_0) {\n    }\n"
+        ));
+        assert!(!decompiled.contains("begin loop"));
+
+        let decompiler: Decompiler =
+            Decompiler::from_hex(&read_file("./contracts/loop/contract_1/bytecode.txt")).unwrap();
+        let decompiled: String = decompiler.decompile().unwrap().to_string();
+        assert!(decompiled.contains(
+            "    loop_2: for (var_1 = 0x10000; var_1 < 0x10010; var_1 = fn_0(0xc6, var_1)) {\n        loop_0: for (var_2 = 0x20000; var_2 < 0x20010; var_2 = fn_0(0xb2, var_2)) {\n"
+        ));
+
+        // while (i < 10) { if (calldata[i]) break; i++; } storage[0] = i
+        let decompiler: Decompiler =
+            Decompiler::from_hex("60005b80600a111560165780356016576001016002565b60005500").unwrap();
+        let decompiled: String = decompiler.decompile().unwrap().to_string();
+        assert!(decompiled.contains(
+            "    loop_0: while (0x0a > var_0) {\n        if calldata[var_0] {\n            break loop_0\n        }\n"
+        ));
+        assert!(decompiled.contains("    }\n    storage[0x00] = var_0\n    stop\n"));
+    }
+
+    #[test]
+    fn test_decompiler_exploration_limits() {
+        let mut decompiler: Decompiler =
+            Decompiler::from_hex(&read_file("./contracts/simple/contract_0/bytecode.txt")).unwrap();
+        decompiler.set_exploration_limits(ExplorationLimits {
+            max_nodes_per_block: 2,
+            ..ExplorationLimits::default()
+        });
+        let graph: Graph = decompiler.build_graph().unwrap();
+        assert!(!graph.reached_limits.is_empty());
+        // the merged contexts make the graph less precise, not unusable
+        assert!(decompiler.decompile().is_ok());
+
+        // the jump destinations are kept, so the loops can still be cut
+        for contract_index in 0..3 {
+            for max_nodes_per_block in [3, 4, 5, 8, 16] {
+                let mut decompiler: Decompiler = Decompiler::from_hex(&read_file(&format!(
+                    "./contracts/loop/contract_{}/bytecode.txt",
+                    contract_index
+                )))
+                .unwrap();
+                decompiler.set_exploration_limits(ExplorationLimits {
+                    max_nodes_per_block,
+                    ..ExplorationLimits::default()
+                });
+                assert!(decompiler.decompile().is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_decompiler_invalid_hex() {
+        assert!(matches!(
+            Decompiler::from_hex("0x60806"),
+            Err(PellucidError::InvalidHex(_))
+        ));
+    }
+
+    #[test]
+    fn test_decompiler_from_bytes() {
+        let raw_bytecode: String = read_file("./contracts/simple/contract_1/bytecode.txt");
+        let bytes: Vec<u8> = hex::decode(raw_bytecode.trim()).unwrap();
+        let hardfork: Hardfork = Hardfork::detect(&raw_bytecode);
+        assert_eq!(
+            Decompiler::from_bytes(&bytes, hardfork)
+                .unwrap()
+                .get_bytecode(),
+            Decompiler::from_hex(&raw_bytecode).unwrap().get_bytecode()
+        );
+    }
+
+    #[test]
+    fn test_decompiler_eof_container() {
+        // section 0 calls section 1 (x => x + 1) under a condition, cf bytecode_reader::eof
+        let decompiler: Decompiler = Decompiler::from_hex(
+            "ef0001010008020002000d0004ff00020000800002010100016001e100055fe30001005f5ff3600101e4beef",
+        )
+        .unwrap();
+        assert!(decompiler.get_eof_container().is_some());
+        let mut counter: StageCounter = StageCounter::default();
+        let decompilation: Decompilation = decompiler.decompile_with(&mut counter).unwrap();
+
+        let section_function: &FunctionWithOpcodes =
+            &decompilation.execution_flow_with_opcodes.functions[&compute_section_label(1)];
+        assert_eq!(section_function.n_inputs, 1);
+        assert_eq!(section_function.n_outputs, Some(1));
+        assert!(section_function
+            .get_content()
+            .last()
+            .unwrap()
+            .is_function_return());
+        assert_eq!(
+            decompilation
+                .execution_flow_with_opcodes
+                .count_function_uses()[&compute_section_label(1)],
+            1
+        );
+        assert!(decompilation.to_string().contains("0x01 + 0x00"));
+    }
+
+    #[test]
+    fn test_decompiler_eof_jump_table() {
+        // RJUMPV on calldata[0] to `storage[0] = 1`, `storage[0] = 2` and `storage[0] = 1` again,
+        // the default case stops
+        let decompiled: String = Decompiler::from_hex(
+            "ef00010100040200010015ff000000008000025f35e2020001000600010060015f550060025f5500",
+        )
+        .unwrap()
+        .decompile()
+        .unwrap()
+        .to_string();
+        assert!(decompiled.contains("if (calldata[0x00]) == 0x00 {\n        storage[0x00] = 0x01"));
+        assert!(decompiled.contains("if (calldata[0x00]) == 0x01 {\n        storage[0x00] = 0x02"));
+        assert!(decompiled.contains("if !((calldata[0x00]) == 0x02) {\n        stop"));
+    }
+
+    #[test]
+    fn test_decompiler_unlinked_library() {
+        // delegatecall(gas, <library>, 0, 0, 0, 0), then stop
+        let decompiler: Decompiler =
+            Decompiler::from_hex("0x600080808073__$1234567890abcdef1234567890abcdef12$__5af400")
+                .unwrap();
+        assert!(decompiler
+            .decompile()
+            .unwrap()
+            .to_string()
+            .contains("address: library_1234567890abcdef1234567890abcdef12"));
+    }
+
+    #[test]
+    fn test_decompiler_unresolved_jump() {
+        // jumps to calldata[0] if calldata[0x20] is non-zero, otherwise jumps to a STOP
+        let decompiler: Decompiler =
+            Decompiler::from_hex("0x602035600035576001600c565b005b600255").unwrap();
+        let decompiled: String = decompiler.decompile().unwrap().to_string();
+        assert!(decompiled.contains("if calldata[0x20] goto dynamic(calldata[0x00])"));
+        // the code of the possible destinations is shown too
+        assert!(decompiled.contains("function jumpdest_0x000e(var_0) internal {"));
+        assert!(decompiled.contains("storage[0x02] = var_0"));
+    }
+
+    #[test]
+    fn test_decompiler_creation_bytecode() {
+        // constructor: codecopy(0, 0x11, 9), return(0, 9), runtime: storage[0] = 1 + 2
+        let creation_decompiler: CreationDecompiler = CreationDecompiler::from_hex(
+            "0x608060405260098060116000396000f3fe600160020160005500000000000000000000000000000000000000000000000000000000000000002a",
+        )
+        .unwrap();
+        let decompilation: CreationDecompilation = creation_decompiler.decompile().unwrap();
+        assert!(decompilation.constructor.to_string().contains("return"));
+        assert!(decompilation.runtime.to_string().contains("storage"));
+        assert_eq!(decompilation.constructor_arguments.len(), 32);
+        assert!(matches!(
+            CreationDecompiler::from_hex(&read_file("./contracts/simple/contract_0/bytecode.txt")),
+            Err(PellucidError::NotCreationBytecode)
+        ));
+    }
+}
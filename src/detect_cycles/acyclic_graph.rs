@@ -1,4 +1,5 @@
 use crate::create_graph::{block::Block, graph::Graph, node::Node};
+use crate::error::PellucidError;
 use std::{
     collections::{HashMap, HashSet},
     ops::{Deref, DerefMut},
@@ -26,7 +27,7 @@ impl<'a, 'b> DerefMut for AcyclicGraph<'a, 'b> {
     }
 }
 impl<'a, 'b> AcyclicGraph<'a, 'b> {
-    pub fn from(graph: &'b mut Graph<'a>) -> AcyclicGraph<'a, 'b> {
+    pub fn from(graph: &'b mut Graph<'a>) -> Result<AcyclicGraph<'a, 'b>, PellucidError> {
         let loops: BlockLoops = BlockLoops::from(graph);
         // cut loops:
         let mut disconnected_connections: HashMap<usize, usize> = HashMap::new();
@@ -42,7 +43,11 @@ impl<'a, 'b> AcyclicGraph<'a, 'b> {
                             if let Some(already_existing_label) =
                                 disconnected_connections.get(&parent_block.get_pc_start())
                             {
-                                assert!(*already_existing_label == entry_label);
+                                if *already_existing_label != entry_label {
+                                    return Err(PellucidError::CycleNotCut {
+                                        pc_start: parent_block.get_pc_start(),
+                                    });
+                                }
                             } else {
                                 disconnected_connections
                                     .insert(parent_block.get_pc_start(), entry_label);
@@ -57,35 +62,43 @@ impl<'a, 'b> AcyclicGraph<'a, 'b> {
             loops,
             disconnected_connections,
         };
-        acyclic_graph.assert_is_really_acyclic();
-        return acyclic_graph;
+        acyclic_graph.check_is_really_acyclic()?;
+        return Ok(acyclic_graph);
     }
 
-    fn assert_is_really_acyclic(&self) {
+    fn check_is_really_acyclic(&self) -> Result<(), PellucidError> {
         let mut visited: HashSet<Node> = HashSet::new();
         let mut current_parents: HashSet<Node> = HashSet::new();
-        let initial_node: Node = self.graph.get_block(0).get_nodes()[0].clone();
-        Self::_explore_dfs_to_check_acyclic(&mut visited, &mut current_parents, initial_node);
+        for root_node in self.graph.get_root_nodes() {
+            Self::_explore_dfs_to_check_acyclic(&mut visited, &mut current_parents, root_node)?;
+        }
+        return Ok(());
     }
 
     fn _explore_dfs_to_check_acyclic(
         visited: &mut HashSet<Node<'a>>,
         current_parents: &mut HashSet<Node<'a>>,
         node: Node<'a>,
-    ) where
+    ) -> Result<(), PellucidError>
+    where
         'a: 'b,
     {
         if visited.contains(&node) {
-            assert!(!current_parents.contains(&node));
+            if current_parents.contains(&node) {
+                return Err(PellucidError::CycleNotCut {
+                    pc_start: node.get_block().get_pc_start(),
+                });
+            }
         } else {
             visited.insert(node.clone());
             current_parents.insert(node.clone());
 
             for child in node.get_children() {
-                Self::_explore_dfs_to_check_acyclic(visited, current_parents, child);
+                Self::_explore_dfs_to_check_acyclic(visited, current_parents, child)?;
             }
             current_parents.remove(&node);
         }
+        return Ok(());
     }
 }
 
@@ -94,6 +107,7 @@ mod tests {
 
     use super::*;
     use crate::bytecode_reader::bytecode::Bytecode;
+    use crate::bytecode_reader::hardfork::Hardfork;
     use crate::create_graph::graph::tests::SerializableGraph;
     use crate::create_graph::graph::Graph;
     use crate::tools::utils::read_file;
@@ -105,10 +119,10 @@ mod tests {
         //let path: String = String::from("./contracts/simple/contract_0/");
         for path in get_all_bytecode_paths() {
             let bytecode_string: String = fs::read_to_string(path).expect("Unable to read file.");
-            let bytecode: Bytecode = Bytecode::from(&bytecode_string).unwrap();
+            let bytecode: Bytecode = Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
 
-            let mut graph: Graph = Graph::from(&bytecode);
-            let _: AcyclicGraph = AcyclicGraph::from(&mut graph);
+            let mut graph: Graph = Graph::from(&bytecode).unwrap();
+            let _: AcyclicGraph = AcyclicGraph::from(&mut graph).unwrap();
         }
     }
 
@@ -117,9 +131,9 @@ mod tests {
         let path: String = String::from("./contracts/loop/contract_2/");
         let bytecode_string: String =
             fs::read_to_string(path.clone() + "bytecode.txt").expect("Unable to read file.");
-        let bytecode: Bytecode = Bytecode::from(&bytecode_string).unwrap();
-        let mut graph: Graph = Graph::from(&bytecode);
-        let a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph);
+        let bytecode: Bytecode = Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
+        let mut graph: Graph = Graph::from(&bytecode).unwrap();
+        let a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph).unwrap();
 
         let serializable_acyclic_graph: SerializableGraph = SerializableGraph::from(a_graph.graph);
 
@@ -43,10 +43,10 @@ impl<'a> BlockLoops {
     fn compute_loop_entry_block<'b>(node_loops: &NodeLoops<'a, 'b>, label: usize) -> usize {
         let mut moving_node: Node = node_loops.loop_entries[&label].clone();
         loop {
-            let parent_block: Block = node_loops.parent_of[&moving_node]
-                .as_ref()
-                .unwrap()
-                .get_block();
+            let Some(parent_node) = &node_loops.parent_of[&moving_node] else {
+                return moving_node.get_block().get_pc_start(); // root of the graph
+            };
+            let parent_block: Block = parent_node.get_block();
             if !node_loops
                 .get_labels_at_block(&parent_block)
                 .contains(&label)
@@ -183,17 +183,18 @@ impl<'a> BlockLoops {
 mod tests {
     use super::*;
     use crate::create_graph::graph::Graph;
-    use crate::{bytecode_reader::bytecode::Bytecode, tools::utils::read_file};
+    use crate::{
+        bytecode_reader::bytecode::Bytecode, bytecode_reader::hardfork::Hardfork,
+        tools::utils::read_file,
+    };
 
     #[test]
     pub fn test_loop_detection() {
         for (contract_index, n_loops) in [(0, 1), (1, 2), (2, 3)] {
-            let path: String = format!(
-                "./contracts/loop/contract_{}/bytecode.txt",
-                contract_index
-            );
-            let bytecode: Bytecode = Bytecode::from(&read_file(&path)).unwrap();
-            let graph: Graph = Graph::from(&bytecode);
+            let path: String = format!("./contracts/loop/contract_{}/bytecode.txt", contract_index);
+            let bytecode: Bytecode =
+                Bytecode::from(&read_file(&path), Hardfork::default()).unwrap();
+            let graph: Graph = Graph::from(&bytecode).unwrap();
             let block_loops: BlockLoops = BlockLoops::from(&graph);
             assert!(block_loops.get_labels().len() == n_loops);
         }
@@ -35,8 +35,12 @@ impl<'a, 'b> NodeLoops<'a, 'b> {
 
     pub fn from(graph: &'b Graph<'a>) -> NodeLoops<'a, 'b> {
         let mut node_loops: NodeLoops = NodeLoops::new(graph);
-        let initial_node: Node = graph.get_block(0).get_nodes()[0].clone();
-        node_loops.explore_dfs(None, initial_node);
+        // the code only reached by jumps whose destination is unknown has its own roots
+        for root_node in graph.get_root_nodes() {
+            if !node_loops.visited.contains(&root_node) {
+                node_loops.explore_dfs(None, root_node);
+            }
+        }
         return node_loops;
     }
 
@@ -86,11 +90,15 @@ impl<'a, 'b> NodeLoops<'a, 'b> {
                 self.add_label(&moving_node, label);
                 break;
             }
+            // with merged contexts, the path back to the first node may go through an inner
+            // loop before reaching its start, whose nodes are then already labelled
             if let Some(other_label) = self.loop_starting_at.get(&moving_node) {
                 for other_node in self.get_nodes_with_label(*other_label) {
-                    self.add_label(&other_node, label);
+                    if !self.labels[&other_node].contains(&label) {
+                        self.add_label(&other_node, label);
+                    }
                 }
-            } else {
+            } else if !self.labels[&moving_node].contains(&label) {
                 self.add_label(&moving_node, label);
             }
             moving_node = self.get_parent(&moving_node);
@@ -156,7 +164,10 @@ impl<'a, 'b> NodeLoops<'a, 'b> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{bytecode_reader::bytecode::Bytecode, research_and_development::gml::nodes_to_gml};
+    use crate::{
+        bytecode_reader::bytecode::Bytecode, bytecode_reader::hardfork::Hardfork,
+        research_and_development::gml::nodes_to_gml,
+    };
     use std::fs;
 
     #[test]
@@ -165,8 +176,8 @@ mod tests {
         let path: String = String::from("./contracts/simple/contract_0/");
         let bytecode_string: String =
             fs::read_to_string(path.clone() + "bytecode.txt").expect("Unable to read file.");
-        let bytecode: Bytecode = Bytecode::from(&bytecode_string).unwrap();
-        let graph: Graph = Graph::from(&bytecode);
+        let bytecode: Bytecode = Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
+        let graph: Graph = Graph::from(&bytecode).unwrap();
         let node_loops: NodeLoops = NodeLoops::from(&graph);
         let mut tags: HashMap<Node, String> = HashMap::new();
         for (node, loop_labels) in &node_loops.labels {
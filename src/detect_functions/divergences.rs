@@ -79,24 +79,29 @@ fn _dfs<'a>(
                 divergences.add_divergence_block(node, &neighboring_node, node.get_block());
             }
         } else {
-            assert!(node.get_children().len() <= 2);
             for child_index in 0..node.get_children().len() {
                 let next_node: &Node = &node.get_children()[child_index];
                 _dfs(visited_nodes, divergences, next_node);
                 for neighboring_node in &neighboring_nodes {
-                    let next_neighboring_node: &Node =
-                        &neighboring_node.get_children()[child_index];
-                    if next_neighboring_node.get_block() == next_node.get_block() {
-                        let next_divergences: HashSet<Block> = divergences
-                            .get_divergence_blocks(next_node, next_neighboring_node)
-                            .clone();
-                        divergences.add_many_divergence_blocks(
+                    // a neighboring node may miss some children when the exploration was bounded
+                    match neighboring_node.get_children().get(child_index) {
+                        Some(next_neighboring_node)
+                            if next_neighboring_node.get_block() == next_node.get_block() =>
+                        {
+                            let next_divergences: HashSet<Block> = divergences
+                                .get_divergence_blocks(next_node, next_neighboring_node)
+                                .clone();
+                            divergences.add_many_divergence_blocks(
+                                node,
+                                neighboring_node,
+                                next_divergences,
+                            );
+                        }
+                        _ => divergences.add_divergence_block(
                             node,
                             neighboring_node,
-                            next_divergences,
-                        );
-                    } else {
-                        divergences.add_divergence_block(node, neighboring_node, node.get_block());
+                            node.get_block(),
+                        ),
                     }
                 }
             }
@@ -109,8 +114,8 @@ fn _dfs<'a>(
 mod tests {
     use super::*;
     use crate::{
-        bytecode_reader::bytecode::Bytecode, create_graph::graph::Graph,
-        detect_cycles::acyclic_graph::AcyclicGraph,
+        bytecode_reader::bytecode::Bytecode, bytecode_reader::hardfork::Hardfork,
+        create_graph::graph::Graph, detect_cycles::acyclic_graph::AcyclicGraph,
     };
     use std::fs;
 
@@ -122,9 +127,9 @@ mod tests {
 
             println!("Loading {}", path);
             let bytecode_string: String = fs::read_to_string(path).expect("Unable to read file.");
-            let bytecode: Bytecode = Bytecode::from(&bytecode_string).unwrap();
-            let mut graph: Graph = Graph::from(&bytecode);
-            let a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph);
+            let bytecode: Bytecode = Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
+            let mut graph: Graph = Graph::from(&bytecode).unwrap();
+            let a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph).unwrap();
             let divergences: Divergences = compute_divergences(&a_graph.get_all_blocks());
             for block in a_graph.get_all_blocks() {
                 for node_0 in block.get_nodes() {
@@ -51,6 +51,22 @@ fn detect_functions<'a, 'b>(a_graph: &AcyclicGraph<'a, 'b>) -> HashMap<Block<'a>
         }
     }
 
+    // a recursive function may have a single caller, its ends are found when building the graph
+    for (starting_pc_start, returning_pc_starts) in &a_graph.recursive_functions {
+        let starting_block: Block<'a> = a_graph.get_block(*starting_pc_start);
+        all_functions
+            .entry(starting_block.clone())
+            .or_insert_with(|| {
+                return Function::from(&Candidate {
+                    start: starting_block,
+                    ends: returning_pc_starts
+                        .iter()
+                        .map(|pc_start| a_graph.get_block(*pc_start))
+                        .collect(),
+                });
+            });
+    }
+
     return all_functions;
 }
 
@@ -0,0 +1,80 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PellucidError {
+    InvalidHex(String),
+    StackUnderflow { pc: usize },
+    CycleNotCut { pc_start: usize },
+    MetadataMalformed(String),
+    MissingDuplication { pc_start: usize },
+    UnexpectedScopeAfterReturn { pc_start: usize },
+    MissingRecursiveFunction { pc_start: usize },
+    LoopStackMismatch { label: usize },
+    StoppedContext { pc_start: usize },
+    MissingCalledFunction { label: u64 },
+    MissingMainFunction,
+    ScopesMalformed(String),
+    EofMalformed(String),
+    NotCreationBytecode,
+    SignatureFileMalformed(String),
+}
+
+impl fmt::Display for PellucidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PellucidError::InvalidHex(reason) => write!(f, "invalid hex bytecode: {}", reason),
+            PellucidError::StackUnderflow { pc } => {
+                write!(f, "stack underflow at pc {:#x}", pc)
+            }
+            PellucidError::CycleNotCut { pc_start } => write!(
+                f,
+                "the graph is still cyclic after cutting loops (block {:#x})",
+                pc_start
+            ),
+            PellucidError::MetadataMalformed(reason) => {
+                write!(f, "malformed metadata: {}", reason)
+            }
+            PellucidError::MissingDuplication { pc_start } => write!(
+                f,
+                "the function starting at block {:#x} has several outputs and should have been duplicated",
+                pc_start
+            ),
+            PellucidError::UnexpectedScopeAfterReturn { pc_start } => write!(
+                f,
+                "non trivial scope after the end of the function at block {:#x}",
+                pc_start
+            ),
+            PellucidError::MissingRecursiveFunction { pc_start } => write!(
+                f,
+                "no function starts at block {:#x}, although it is called recursively",
+                pc_start
+            ),
+            PellucidError::LoopStackMismatch { label } => write!(
+                f,
+                "the stack size differs between the start of loop {} and a jump back to it",
+                label
+            ),
+            PellucidError::StoppedContext { pc_start } => write!(
+                f,
+                "block {:#x} is executed from a context that is not running",
+                pc_start
+            ),
+            PellucidError::MissingCalledFunction { label } => {
+                write!(f, "function {} is called but was not built", label)
+            }
+            PellucidError::MissingMainFunction => {
+                write!(f, "no function starts at the beginning of the code")
+            }
+            PellucidError::ScopesMalformed(reason) => write!(f, "malformed scopes: {}", reason),
+            PellucidError::EofMalformed(reason) => write!(f, "malformed EOF container: {}", reason),
+            PellucidError::NotCreationBytecode => {
+                write!(f, "no CODECOPY/RETURN deployer pattern found in the bytecode")
+            }
+            PellucidError::SignatureFileMalformed(reason) => {
+                write!(f, "malformed signature file: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PellucidError {}
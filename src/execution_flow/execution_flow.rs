@@ -9,6 +9,13 @@ pub type LoopLabel = usize;
 
 pub const MAIN_FUNCTION_LABEL: u64 = u64::MAX;
 
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum LoopKind {
+    While,
+    DoWhile,
+    For,
+}
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum Scope<
     Instructions: Clone + Length,
@@ -24,6 +31,19 @@ pub enum Scope<
     LoopContinue {
         label: LoopLabel,
     },
+    // leaves the loop, the execution goes on after it
+    LoopBreak {
+        label: LoopLabel,
+    },
+    // A loop whose body is nested. The header holds the lines of the loop condition (the last
+    // `if` line, the loop goes on while it is true), preceded by the initialization and followed
+    // by the increment for `for` loops. It is empty for infinite loops.
+    StructuredLoop {
+        label: LoopLabel,
+        kind: LoopKind,
+        header: Vec<Self>,
+        body: Vec<Self>,
+    },
     Condition {
         instructions_if_true: Vec<Self>,
         instructions_if_false: Vec<Self>,
@@ -148,6 +168,15 @@ impl<
                     Self::count_function_uses_in_scopes(instructions_if_true, function_uses);
                     Self::count_function_uses_in_scopes(instructions_if_false, function_uses);
                 }
+                Scope::StructuredLoop {
+                    label: _,
+                    kind: _,
+                    header,
+                    body,
+                } => {
+                    Self::count_function_uses_in_scopes(header, function_uses);
+                    Self::count_function_uses_in_scopes(body, function_uses);
+                }
                 _ => (),
             }
         }
@@ -166,6 +195,16 @@ impl<
                 Scope::FunctionReturn(_) => size += 1,
                 Scope::Loop { label: _ } => size += 1,
                 Scope::LoopContinue { label: _ } => size += 1,
+                Scope::LoopBreak { label: _ } => size += 1,
+                Scope::StructuredLoop {
+                    label: _,
+                    kind: _,
+                    header,
+                    body,
+                } => {
+                    size +=
+                        Self::compute_size_of_scopes(header) + Self::compute_size_of_scopes(body)
+                }
                 Scope::Condition {
                     instructions_if_true,
                     instructions_if_false,
@@ -203,6 +242,16 @@ impl<
                 Self::apply_on_scopes(instructions_if_true, key);
                 Self::apply_on_scopes(instructions_if_false, key);
             }
+            if let Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } = scope
+            {
+                Self::apply_on_scopes(header, key);
+                Self::apply_on_scopes(body, key);
+            }
         }
     }
 
@@ -229,9 +278,27 @@ impl<
                 Self::apply_on_scopes_mut(instructions_if_true, key);
                 Self::apply_on_scopes_mut(instructions_if_false, key);
             }
+            if let Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } = scope
+            {
+                Self::apply_on_scopes_mut(header, key);
+                Self::apply_on_scopes_mut(body, key);
+            }
         }
     }
 
+    pub fn is_recursive(function: &Function) -> bool {
+        let label: FunctionLabel = function.get_label();
+        return Self::any_scopes(
+            function.get_content(),
+            &mut |scope| matches!(scope, Scope::FunctionCall(function_call) if function_call.get_label() == label),
+        );
+    }
+
     pub fn any_scopes(
         // is there a scope for which 'key' is true ?
         scopes: &Vec<Scope<Instructions, FunctionCall, FunctionReturn>>,
@@ -294,6 +361,16 @@ impl<
                 Self::remove_scopes_by_key(instructions_if_true, to_remove);
                 Self::remove_scopes_by_key(instructions_if_false, to_remove);
             }
+            if let Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } = scope
+            {
+                Self::remove_scopes_by_key(header, to_remove);
+                Self::remove_scopes_by_key(body, to_remove);
+            }
         }
     }
 }
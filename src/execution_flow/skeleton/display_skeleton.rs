@@ -73,6 +73,7 @@ impl ToString for Effect {
 mod tests {
     use crate::{
         bytecode_reader::bytecode::Bytecode,
+        bytecode_reader::hardfork::Hardfork,
         create_graph::graph::Graph,
         detect_cycles::acyclic_graph::AcyclicGraph,
         execution_flow::skeleton::{skeleton::Skeleton, skeleton_scopes::SkeletonScope},
@@ -111,13 +112,13 @@ mod tests {
         let path: String = String::from("./contracts/simple/contract_0/bytecode.txt");
         println!("Loading {}", path);
         let bytecode_string: String = fs::read_to_string(path).expect("Unable to read file.");
-        let bytecode: Bytecode = Bytecode::from(&bytecode_string).unwrap();
-        let mut graph: Graph = Graph::from(&bytecode);
-        let mut a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph);
+        let bytecode: Bytecode = Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
+        let mut graph: Graph = Graph::from(&bytecode).unwrap();
+        let mut a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph).unwrap();
 
-        let skeleton: Skeleton = Skeleton::build(&mut a_graph);
+        let skeleton: Skeleton = Skeleton::build(&mut a_graph).unwrap();
         let skeleton_str: String = skeleton_to_string(&skeleton);
-       // tools::utils::write_file("skeleton.txt", &skeleton_str);
-       let _ = skeleton_str;
+        // tools::utils::write_file("skeleton.txt", &skeleton_str);
+        let _ = skeleton_str;
     }
 }
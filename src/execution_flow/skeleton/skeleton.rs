@@ -7,15 +7,19 @@ use std::{
 use itertools::Itertools;
 
 use crate::{
+    bytecode_reader::{opcode::Opcode, vopcode::Vopcode},
     create_graph::block::Block,
     detect_cycles::acyclic_graph::AcyclicGraph,
     detect_functions::{
         function::{Function, FunctionOutput},
         function_detection::detect_functions_and_duplicate_oddities,
     },
+    error::PellucidError,
 };
 
-use super::skeleton_scopes::{SkeletonFunction, SkeletonIf, SkeletonJunction, SkeletonScope};
+use super::skeleton_scopes::{
+    SkeletonFunction, SkeletonIf, SkeletonJumpTable, SkeletonJunction, SkeletonScope,
+};
 
 #[derive(PartialEq, Eq, Debug)]
 enum Destination<'a> {
@@ -27,6 +31,11 @@ enum Destination<'a> {
     ContinueLoop {
         label: usize,
     },
+    // the block calls the function it belongs to, the execution goes on at the output block
+    RecursiveCall {
+        function_start: usize,
+        output_block: Option<Block<'a>>,
+    },
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -37,6 +46,11 @@ enum BlockOutput<'a> {
         true_dest: Destination<'a>,
         false_dest: Destination<'a>,
     },
+    JumpTable {
+        vopcode: Vopcode,
+        case_dests: Vec<Destination<'a>>,
+        default_dest: Destination<'a>,
+    },
     NonDeterministic,
     Over,
 }
@@ -50,7 +64,7 @@ pub struct Skeleton<'a, 'b> {
 }
 
 impl<'a, 'b> Skeleton<'a, 'b> {
-    pub fn build(a_graph: &'b mut AcyclicGraph<'a, 'b>) -> Self {
+    pub fn build(a_graph: &'b mut AcyclicGraph<'a, 'b>) -> Result<Self, PellucidError> {
         let mut functions: HashMap<Block, Function> =
             detect_functions_and_duplicate_oddities(a_graph);
 
@@ -69,7 +83,9 @@ impl<'a, 'b> Skeleton<'a, 'b> {
                     })),
                 );
                 assert!(a_graph.loops.get_label_of_entry(&block).is_none());
-            } else if block.get_parent_blocks().len() >= 2 {
+            } else if block.get_parent_blocks().len() >= 2
+                || is_dynamic_jump_target(a_graph, &block)
+            {
                 skeleton_junctions.insert(
                     block.clone(),
                     Rc::new(RefCell::new(SkeletonJunction {
@@ -101,20 +117,27 @@ impl<'a, 'b> Skeleton<'a, 'b> {
 
         for (block, skeleton_function) in skeleton.functions.clone() {
             let stop_at: HashSet<Block<'a>> = RefCell::borrow(&skeleton_function).info.ends.clone();
-            let instructions = skeleton.get_instructions_from(block.clone(), stop_at);
+            let instructions = skeleton.get_instructions_from(block.clone(), stop_at)?;
             RefCell::borrow_mut(&skeleton_function).instructions = instructions;
         }
 
         for (block, skeleton_junction) in skeleton.junctions.clone() {
-            let instructions = skeleton.get_instructions_from(block.clone(), HashSet::new());
+            let mut instructions: Vec<SkeletonScope<'a>> = Vec::new();
+            if is_dynamic_jump_target(skeleton.a_graph, &block) {
+                // nothing jumps to the entry of the loop, the junction starts it
+                if let Some(label) = skeleton.a_graph.loops.get_label_of_entry(&block) {
+                    instructions.push(SkeletonScope::Loop { label });
+                }
+            }
+            instructions.extend(skeleton.get_instructions_from(block.clone(), HashSet::new())?);
             RefCell::borrow_mut(&skeleton_junction).instructions = instructions;
         }
 
         // TODO handle case where first block in graph has multiple parents
         skeleton.main_instructions =
-            skeleton.get_instructions_from(skeleton.a_graph.get_block(0), HashSet::new());
+            skeleton.get_instructions_from(skeleton.a_graph.get_block(0), HashSet::new())?;
 
-        return skeleton;
+        return Ok(skeleton);
     }
 
     fn get_instructions_on_dest(
@@ -122,7 +145,7 @@ impl<'a, 'b> Skeleton<'a, 'b> {
         stop_at: HashSet<Block<'a>>,
         current_block: &Block<'a>,
         dest: Destination<'a>,
-    ) -> Vec<SkeletonScope<'a>> {
+    ) -> Result<Vec<SkeletonScope<'a>>, PellucidError> {
         let mut following_instructions: Vec<SkeletonScope<'a>>;
 
         match dest {
@@ -132,31 +155,53 @@ impl<'a, 'b> Skeleton<'a, 'b> {
             }
 
             Destination::Block(next_block) => {
-                if next_block.get_parent_blocks().len() >= 2 {
+                if self.is_shared(&next_block) {
                     following_instructions = self.continue_instructions_at_multi_parents_block(
                         stop_at.clone(),
                         current_block,
                         &next_block,
-                    );
+                    )?;
                 } else {
-                    following_instructions = self.get_instructions_from(next_block, stop_at);
+                    following_instructions = self.get_instructions_from(next_block, stop_at)?;
                 }
             }
 
             Destination::StartLoop { entry_block, label } => {
                 following_instructions = vec![SkeletonScope::Loop { label }];
                 following_instructions
-                    .extend(self.get_instructions_from(entry_block.clone(), stop_at));
+                    .extend(self.get_instructions_from(entry_block.clone(), stop_at)?);
+            }
+
+            Destination::RecursiveCall {
+                function_start,
+                output_block,
+            } => {
+                let skeleton_function: Rc<RefCell<SkeletonFunction<'a>>> = self
+                    .functions
+                    .iter()
+                    .find(|(block, _)| block.get_pc_start() == function_start)
+                    .map(|(_, skeleton_function)| Rc::clone(skeleton_function))
+                    .ok_or(PellucidError::MissingRecursiveFunction {
+                        pc_start: function_start,
+                    })?;
+                following_instructions = vec![SkeletonScope::Function(skeleton_function)];
+                if let Some(returned_to_block) = output_block {
+                    following_instructions.extend(self.get_instructions_on_dest(
+                        stop_at,
+                        current_block,
+                        Destination::Block(returned_to_block),
+                    )?);
+                }
             }
         }
-        return following_instructions;
+        return Ok(following_instructions);
     }
 
     fn get_instructions_from(
         &mut self,
         from_block: Block<'a>,
         stop_at: HashSet<Block<'a>>,
-    ) -> Vec<SkeletonScope<'a>> {
+    ) -> Result<Vec<SkeletonScope<'a>>, PellucidError> {
         // TODO check if 2functions can share some ending blocks
 
         let mut instructions: Vec<SkeletonScope<'a>> = Vec::new();
@@ -168,8 +213,14 @@ impl<'a, 'b> Skeleton<'a, 'b> {
 
         loop {
             block_output = self.get_block_output(&current_block);
+            if let BlockOutput::SingleDestination(Destination::Block(_)) = &block_output {
+                if stop_at.contains(&current_block) {
+                    // the function returns to its only caller
+                    block_output = BlockOutput::Over;
+                }
+            }
             if let BlockOutput::SingleDestination(Destination::Block(next_block)) = &block_output {
-                if next_block.get_parent_blocks().len() == 1 {
+                if !self.is_shared(next_block) {
                     current_block = next_block.clone();
                     instructions.push(SkeletonScope::Block(current_block.clone()));
                     continue;
@@ -183,7 +234,7 @@ impl<'a, 'b> Skeleton<'a, 'b> {
 
             BlockOutput::SingleDestination(dest) => {
                 let following_instructions: Vec<SkeletonScope<'a>> =
-                    self.get_instructions_on_dest(stop_at, &current_block, dest);
+                    self.get_instructions_on_dest(stop_at, &current_block, dest)?;
                 instructions.extend(following_instructions);
             }
 
@@ -192,15 +243,36 @@ impl<'a, 'b> Skeleton<'a, 'b> {
                 false_dest,
             } => {
                 let true_instructions: Vec<SkeletonScope<'a>> =
-                    self.get_instructions_on_dest(stop_at.clone(), &current_block, true_dest);
+                    self.get_instructions_on_dest(stop_at.clone(), &current_block, true_dest)?;
                 let false_instructions: Vec<SkeletonScope<'a>> =
-                    self.get_instructions_on_dest(stop_at.clone(), &current_block, false_dest);
+                    self.get_instructions_on_dest(stop_at.clone(), &current_block, false_dest)?;
                 let skeleton_if = SkeletonIf {
                     true_instructions,
                     false_instructions,
                 };
                 instructions.push(SkeletonScope::If(skeleton_if));
             }
+            BlockOutput::JumpTable {
+                vopcode,
+                case_dests,
+                default_dest,
+            } => {
+                let mut cases: Vec<Vec<SkeletonScope<'a>>> = Vec::new();
+                for case_dest in case_dests {
+                    cases.push(self.get_instructions_on_dest(
+                        stop_at.clone(),
+                        &current_block,
+                        case_dest,
+                    )?);
+                }
+                let default_instructions: Vec<SkeletonScope<'a>> =
+                    self.get_instructions_on_dest(stop_at.clone(), &current_block, default_dest)?;
+                instructions.push(SkeletonScope::JumpTable(SkeletonJumpTable {
+                    vopcode,
+                    cases,
+                    default_instructions,
+                }));
+            }
             BlockOutput::NonDeterministic => {
                 if stop_at.contains(&current_block) {
                     // we just reach an ending block of anfunction, we do nothing
@@ -210,7 +282,7 @@ impl<'a, 'b> Skeleton<'a, 'b> {
             }
         }
 
-        return instructions;
+        return Ok(instructions);
     }
 
     fn continue_instructions_at_multi_parents_block(
@@ -218,7 +290,7 @@ impl<'a, 'b> Skeleton<'a, 'b> {
         stop_at: HashSet<Block<'a>>,
         input_block: &Block<'a>,
         multi_parent_block: &Block<'a>,
-    ) -> Vec<SkeletonScope<'a>> {
+    ) -> Result<Vec<SkeletonScope<'a>>, PellucidError> {
         let mut following_instructions: Vec<SkeletonScope<'a>> = Vec::new();
 
         if let Some(skeleton_functions) = self.functions.get(&multi_parent_block) {
@@ -231,11 +303,13 @@ impl<'a, 'b> Skeleton<'a, 'b> {
                 .get_output(input_block);
             match function_output {
                 FunctionOutput::MultiBlock => {
-                    panic!("A dupplication should have occured here.")
-                }
-                FunctionOutput::SingleBlock(output_block) => {
-                    following_instructions.extend(self.get_instructions_from(output_block, stop_at))
+                    // a dupplication should have occured here
+                    return Err(PellucidError::MissingDuplication {
+                        pc_start: multi_parent_block.get_pc_start(),
+                    });
                 }
+                FunctionOutput::SingleBlock(output_block) => following_instructions
+                    .extend(self.get_instructions_from(output_block, stop_at)?),
                 FunctionOutput::NoOutput => (),
             }
         } else {
@@ -244,7 +318,18 @@ impl<'a, 'b> Skeleton<'a, 'b> {
                 self.junctions[&multi_parent_block].clone(),
             ));
         }
-        return following_instructions;
+        return Ok(following_instructions);
+    }
+
+    // Blocks with several parents, recursive functions and the possible destinations of
+    // unresolved jumps are not inlined: they start a function or a junction.
+    fn is_shared(&self, block: &Block<'a>) -> bool {
+        return block.get_parent_blocks().len() >= 2
+            || self.junctions.contains_key(block)
+            || self
+                .a_graph
+                .recursive_functions
+                .contains_key(&block.get_pc_start());
     }
 
     fn get_block_output(&mut self, block: &Block<'a>) -> BlockOutput<'a> {
@@ -269,7 +354,16 @@ impl<'a, 'b> Skeleton<'a, 'b> {
 
         let block_output: BlockOutput;
 
-        if block.get_child_blocks().len() == 0 {
+        if let Some(function_start) = self.a_graph.recursive_calls.get(&block.get_pc_start()) {
+            block_output = BlockOutput::SingleDestination(Destination::RecursiveCall {
+                function_start: *function_start,
+                output_block: block.get_child_blocks().into_iter().next(),
+            });
+        } else if let Some(jump_table_output) =
+            self.get_jump_table_output(block, continue_loop_label)
+        {
+            block_output = jump_table_output;
+        } else if block.get_child_blocks().len() == 0 {
             if let Some(loop_label) = continue_loop_label {
                 block_output =
                     BlockOutput::SingleDestination(Destination::ContinueLoop { label: loop_label });
@@ -309,4 +403,58 @@ impl<'a, 'b> Skeleton<'a, 'b> {
         }
         return block_output;
     }
+
+    // Every entry of a RJUMPV table is a destination (the one of a removed loop connection
+    // continues the loop), the next block is the default one.
+    fn get_jump_table_output(
+        &self,
+        block: &Block<'a>,
+        continue_loop_label: Option<usize>,
+    ) -> Option<BlockOutput<'a>> {
+        let final_vopcode: &Vopcode = block.get_code().last().unwrap();
+        let Opcode::RJUMPV { max_index } = final_vopcode.opcode else {
+            return None;
+        };
+        if max_index == 0 {
+            return None; // a single entry is a condition
+        }
+        let child_blocks: Vec<Block<'a>> = block.get_child_blocks().into_iter().collect();
+        let get_dest = |pc_start: usize| -> Option<Destination<'a>> {
+            match child_blocks
+                .iter()
+                .find(|child_block| child_block.get_pc_start() == pc_start)
+            {
+                Some(child_block) => match self.a_graph.loops.get_label_of_entry(child_block) {
+                    Some(label) => {
+                        return Some(Destination::StartLoop {
+                            entry_block: child_block.clone(),
+                            label,
+                        })
+                    }
+                    None => return Some(Destination::Block(child_block.clone())),
+                },
+                None => {
+                    return continue_loop_label.map(|label| Destination::ContinueLoop { label })
+                }
+            }
+        };
+        let mut case_dests: Vec<Destination<'a>> = Vec::new();
+        for pc_start in final_vopcode.get_relative_destinations() {
+            case_dests.push(get_dest(pc_start)?);
+        }
+        return Some(BlockOutput::JumpTable {
+            vopcode: final_vopcode.clone(),
+            case_dests,
+            default_dest: get_dest(block.get_next_pc_start())?,
+        });
+    }
+}
+
+// The block is reached by a jump whose destination is unknown: it starts a function or a junction,
+// which is kept even if it is never called.
+pub fn is_dynamic_jump_target<'a>(a_graph: &AcyclicGraph<'a, '_>, block: &Block<'a>) -> bool {
+    return block
+        .get_nodes()
+        .iter()
+        .any(|node| a_graph.is_dynamic_jump_target(node));
 }
@@ -1,6 +1,7 @@
 use std::{cell::RefCell, fmt::Debug, rc::Rc};
 
 use crate::{
+    bytecode_reader::vopcode::Vopcode,
     create_graph::block::Block,
     detect_functions::function::Function,
     tools::utils::{shift_text, usize_to_hex},
@@ -17,6 +18,15 @@ pub struct SkeletonIf<'a> {
     pub true_instructions: Vec<SkeletonScope<'a>>,
     pub false_instructions: Vec<SkeletonScope<'a>>,
 }
+
+#[derive(Debug)]
+pub struct SkeletonJumpTable<'a> {
+    // RJUMPV with several entries: `cases[i]` runs when the index is `i`, `default_instructions` otherwise
+    pub vopcode: Vopcode,
+    pub cases: Vec<Vec<SkeletonScope<'a>>>,
+    pub default_instructions: Vec<SkeletonScope<'a>>,
+}
+
 #[derive(Debug)]
 pub struct SkeletonJunction<'a> {
     pub starting_block: Block<'a>,
@@ -29,6 +39,7 @@ pub enum SkeletonScope<'a> {
     LoopContinue { label: usize },
     Loop { label: usize },
     If(SkeletonIf<'a>),
+    JumpTable(SkeletonJumpTable<'a>),
     Junction(Rc<RefCell<SkeletonJunction<'a>>>),
     Block(Block<'a>),
     Panic,
@@ -63,6 +74,16 @@ impl<'a> SkeletonScope<'a> {
                 res += &shift_text(&false_instructions_str);
                 res
             }
+            SkeletonScope::JumpTable(jump_table) => {
+                let mut res: String = String::new();
+                for (case, case_instructions) in jump_table.cases.iter().enumerate() {
+                    res += &format!("case {}:\n", case);
+                    res += &shift_text(&instructions_to_string(case_instructions));
+                }
+                res += "default:\n";
+                res += &shift_text(&instructions_to_string(&jump_table.default_instructions));
+                res
+            }
             SkeletonScope::Junction(junction_scope) => {
                 let instructions_str: String =
                     instructions_to_string(&RefCell::borrow(junction_scope).instructions);
@@ -94,6 +115,7 @@ impl<'a> SkeletonScope<'a> {
             SkeletonScope::LoopContinue { label: _ } => None,
             SkeletonScope::Loop { label: _ } => None,
             SkeletonScope::If(_) => None,
+            SkeletonScope::JumpTable(_) => None,
             SkeletonScope::Junction(junction_scope) => Some(format!(
                 "junction_{}()",
                 usize_to_hex(
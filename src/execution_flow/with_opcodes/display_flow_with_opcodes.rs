@@ -42,6 +42,21 @@ impl ToString for OpcodeScope {
             }
             Scope::Loop { label } => return format!("loop_{}", label),
             Scope::LoopContinue { label } => return format!("continue_{}", label),
+            Scope::LoopBreak { label } => return format!("break_{}", label),
+            Scope::StructuredLoop {
+                label,
+                kind: _,
+                header,
+                body,
+            } => {
+                let mut res: String = format!("loop_{}", label);
+                res += "{";
+                res += &shift_text(&concat_to_str(header, "\n"));
+                res += "}\n{";
+                res += &shift_text(&concat_to_str(body, "\n"));
+                res += "}";
+                return res;
+            }
             Scope::Condition {
                 instructions_if_true,
                 instructions_if_false,
@@ -67,6 +82,7 @@ mod tests {
 
     use crate::{
         bytecode_reader::bytecode::Bytecode,
+        bytecode_reader::hardfork::Hardfork,
         create_graph::graph::Graph,
         detect_cycles::acyclic_graph::AcyclicGraph,
         execution_flow::{
@@ -83,13 +99,13 @@ mod tests {
         let path: String = String::from("./contracts/simple/contract_0/bytecode.txt");
         println!("Loading {}", path);
         let bytecode_string: String = fs::read_to_string(path).expect("Unable to read file.");
-        let bytecode: Bytecode = Bytecode::from(&bytecode_string).unwrap();
-        let mut graph: Graph = Graph::from(&bytecode);
-        let mut a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph);
+        let bytecode: Bytecode = Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
+        let mut graph: Graph = Graph::from(&bytecode).unwrap();
+        let mut a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph).unwrap();
 
-        let skeleton: Skeleton = Skeleton::build(&mut a_graph);
+        let skeleton: Skeleton = Skeleton::build(&mut a_graph).unwrap();
         let execution_flow_with_opcodes: ExecutionFlowWithOpcodes =
-            convert_skeleton_to_execution_flow(&skeleton);
+            convert_skeleton_to_execution_flow(&skeleton).unwrap();
         // tools::utils::write_file(
         //     "flow_of_opcodes.txt",
         //     &execution_flow_with_opcodes.to_string(),
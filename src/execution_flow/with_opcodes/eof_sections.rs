@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use super::{
+    flow_with_opcodes::ExecutionFlowWithOpcodes,
+    scopes_with_opcodes::{
+        FunctionCallWithOpcodes, FunctionReturnWithOpcodes, FunctionWithOpcodes,
+        InstructionsWithOpcodes, OpcodeScope,
+    },
+};
+use crate::{
+    bytecode_reader::{eof::TypeSection, opcode::Opcode, vopcode::Vopcode},
+    execution_flow::execution_flow::{FunctionLabel, Scope, MAIN_FUNCTION_LABEL},
+    tools::utils::calculate_hash,
+};
+
+// Each EOF code section is decompiled on its own, its main function becomes the function of the section.
+pub fn compute_section_label(section: usize) -> FunctionLabel {
+    if section == 0 {
+        return MAIN_FUNCTION_LABEL;
+    }
+    return calculate_hash(&(section, MAIN_FUNCTION_LABEL));
+}
+
+fn relabel(section: usize, label: FunctionLabel) -> FunctionLabel {
+    // labels are only unique inside a section
+    if label == MAIN_FUNCTION_LABEL {
+        return compute_section_label(section);
+    }
+    return calculate_hash(&(section, label));
+}
+
+pub fn merge_eof_section_flows(
+    section_flows: Vec<ExecutionFlowWithOpcodes>,
+    types: &[TypeSection],
+) -> ExecutionFlowWithOpcodes {
+    let mut functions: HashMap<FunctionLabel, FunctionWithOpcodes> = HashMap::new();
+    for (section, section_flow) in section_flows.into_iter().enumerate() {
+        for (label, function) in section_flow.functions {
+            let new_label: FunctionLabel = relabel(section, label);
+            let mut converted_function: FunctionWithOpcodes = FunctionWithOpcodes {
+                label: new_label,
+                n_inputs: function.n_inputs,
+                n_outputs: function.n_outputs,
+                dynamic_jump_target: function.dynamic_jump_target,
+                content: convert_section_scopes(function.content, section, types),
+            };
+            if label == MAIN_FUNCTION_LABEL {
+                // the type section is authoritative, no need to guess the stack usage
+                converted_function.n_inputs = types[section].inputs as usize;
+                converted_function.n_outputs = if types[section].is_returning() {
+                    Some(types[section].outputs as usize)
+                } else {
+                    None
+                };
+            }
+            functions.insert(new_label, converted_function);
+        }
+    }
+    return ExecutionFlowWithOpcodes::new(functions);
+}
+
+fn convert_section_scopes(
+    scopes: Vec<OpcodeScope>,
+    section: usize,
+    types: &[TypeSection],
+) -> Vec<OpcodeScope> {
+    let mut converted_scopes: Vec<OpcodeScope> = Vec::new();
+    for scope in scopes {
+        match scope {
+            Scope::Instructions(instructions) => {
+                converted_scopes.extend(split_instructions_on_calls(instructions, section, types))
+            }
+            Scope::FunctionCall(call) => {
+                converted_scopes.push(Scope::FunctionCall(FunctionCallWithOpcodes {
+                    label: relabel(section, call.label),
+                }))
+            }
+            Scope::FunctionReturn(function_return) => {
+                converted_scopes.push(Scope::FunctionReturn(FunctionReturnWithOpcodes {
+                    label: relabel(section, function_return.label),
+                }))
+            }
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => converted_scopes.push(Scope::Condition {
+                instructions_if_true: convert_section_scopes(instructions_if_true, section, types),
+                instructions_if_false: convert_section_scopes(
+                    instructions_if_false,
+                    section,
+                    types,
+                ),
+            }),
+            other => converted_scopes.push(other),
+        }
+    }
+    return converted_scopes;
+}
+
+fn split_instructions_on_calls(
+    instructions: InstructionsWithOpcodes,
+    section: usize,
+    types: &[TypeSection],
+) -> Vec<OpcodeScope> {
+    // CALLF, JUMPF and RETF are replaced by the scopes the rest of the pipeline already knows
+    let mut scopes: Vec<OpcodeScope> = Vec::new();
+    let mut pending_code: Vec<Vopcode> = Vec::new();
+    let flush = |pending_code: &mut Vec<Vopcode>, scopes: &mut Vec<OpcodeScope>| {
+        if !pending_code.is_empty() {
+            scopes.push(Scope::Instructions(InstructionsWithOpcodes::new(
+                std::mem::take(pending_code),
+            )));
+        }
+    };
+    for vopcode in instructions.code {
+        match vopcode.opcode {
+            Opcode::CALLF {
+                section: callee, ..
+            } => {
+                flush(&mut pending_code, &mut scopes);
+                scopes.push(Scope::FunctionCall(FunctionCallWithOpcodes {
+                    label: compute_section_label(callee as usize),
+                }));
+            }
+            Opcode::JUMPF {
+                section: callee, ..
+            } => {
+                // tail call: the current section returns whatever the callee returns
+                flush(&mut pending_code, &mut scopes);
+                scopes.push(Scope::FunctionCall(FunctionCallWithOpcodes {
+                    label: compute_section_label(callee as usize),
+                }));
+                if types[section].is_returning() {
+                    scopes.push(Scope::FunctionReturn(FunctionReturnWithOpcodes {
+                        label: compute_section_label(section),
+                    }));
+                }
+            }
+            Opcode::RETF => {
+                flush(&mut pending_code, &mut scopes);
+                scopes.push(Scope::FunctionReturn(FunctionReturnWithOpcodes {
+                    label: compute_section_label(section),
+                }));
+            }
+            _ => pending_code.push(vopcode),
+        }
+    }
+    flush(&mut pending_code, &mut scopes);
+    return scopes;
+}
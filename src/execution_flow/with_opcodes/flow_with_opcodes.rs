@@ -1,3 +1,4 @@
+use primitive_types::U256;
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use super::scopes_with_opcodes::{
@@ -5,12 +6,13 @@ use super::scopes_with_opcodes::{
     FunctionWithOpcodes, InstructionsWithOpcodes, OpcodeScope,
 };
 use crate::{
-    bytecode_reader::vopcode::Vopcode,
+    bytecode_reader::{opcode::Opcode, vopcode::Vopcode},
     create_graph::block::Block,
+    error::PellucidError,
     execution_flow::{
         execution_flow::{ExecutionFlow, FunctionLabel, Scope, MAIN_FUNCTION_LABEL},
         skeleton::{
-            skeleton::Skeleton,
+            skeleton::{is_dynamic_jump_target, Skeleton},
             skeleton_scopes::{SkeletonFunction, SkeletonJunction, SkeletonScope},
         },
     },
@@ -30,7 +32,7 @@ fn compute_function_label(starting_block: &Block) -> u64 {
 
 pub fn convert_skeleton_to_execution_flow<'a>(
     skeleton: &Skeleton<'a, '_>,
-) -> ExecutionFlowWithOpcodes {
+) -> Result<ExecutionFlowWithOpcodes, PellucidError> {
     let mut functions: HashMap<FunctionLabel, FunctionWithOpcodes> = HashMap::new();
 
     functions.insert(
@@ -39,29 +41,30 @@ pub fn convert_skeleton_to_execution_flow<'a>(
             label: MAIN_FUNCTION_LABEL,
             n_inputs: 0,
             n_outputs: None,
+            dynamic_jump_target: None,
             content: convert_skeleton_scopes(
                 &skeleton.main_instructions,
                 &skeleton.returning_blocks,
-            ),
+            )?,
         },
     );
 
     for (starting_block, skeleton_function) in &skeleton.functions {
         let label: FunctionLabel = compute_function_label(starting_block);
         assert!(!functions.contains_key(&label));
-        functions.insert(
-            label,
-            convert_skeleton_function(skeleton_function.clone(), &skeleton.returning_blocks),
-        );
+        let mut converted_function: FunctionWithOpcodes =
+            convert_skeleton_function(skeleton_function.clone(), &skeleton.returning_blocks)?;
+        converted_function.dynamic_jump_target = get_dynamic_jump_target(skeleton, starting_block);
+        functions.insert(label, converted_function);
     }
 
     for (starting_block, skeleton_junction) in &skeleton.junctions {
         let label: FunctionLabel = compute_function_label(starting_block);
         assert!(!functions.contains_key(&label));
-        functions.insert(
-            label,
-            convert_skeleton_junction(skeleton_junction.clone(), &skeleton.returning_blocks),
-        );
+        let mut converted_function: FunctionWithOpcodes =
+            convert_skeleton_junction(skeleton_junction.clone(), &skeleton.returning_blocks)?;
+        converted_function.dynamic_jump_target = get_dynamic_jump_target(skeleton, starting_block);
+        functions.insert(label, converted_function);
     }
 
     fill_n_inputs_and_outputs(&mut functions);
@@ -69,44 +72,54 @@ pub fn convert_skeleton_to_execution_flow<'a>(
     let mut execution_flow_with_opcodes: ExecutionFlowWithOpcodes =
         ExecutionFlowWithOpcodes::new(functions);
     execution_flow_with_opcodes.remove_secondary_functions_containing_loops();
-    return execution_flow_with_opcodes;
+    return Ok(execution_flow_with_opcodes);
+}
+
+fn get_dynamic_jump_target<'a>(
+    skeleton: &Skeleton<'a, '_>,
+    starting_block: &Block<'a>,
+) -> Option<usize> {
+    if is_dynamic_jump_target(skeleton.a_graph, starting_block) {
+        return Some(starting_block.get_pc_start());
+    }
+    return None;
 }
 
 fn convert_skeleton_function<'a>(
     skeleton_function: Rc<RefCell<SkeletonFunction<'a>>>,
     returning_blocks: &HashMap<Block<'a>, Rc<RefCell<SkeletonFunction<'a>>>>,
-) -> FunctionWithOpcodes {
+) -> Result<FunctionWithOpcodes, PellucidError> {
     let inner_scopes: Vec<OpcodeScope> = convert_skeleton_scopes(
         &RefCell::borrow(&skeleton_function).instructions,
         returning_blocks,
-    );
+    )?;
     let mut converted_function: FunctionWithOpcodes =
         FunctionWithOpcodes::new_empty(&RefCell::borrow(&skeleton_function).info.start);
     converted_function.content = inner_scopes;
-    return converted_function;
+    return Ok(converted_function);
 }
 
 fn convert_skeleton_junction<'a>(
     skeleton_junction: Rc<RefCell<SkeletonJunction<'a>>>,
     returning_blocks: &HashMap<Block<'a>, Rc<RefCell<SkeletonFunction<'a>>>>,
-) -> FunctionWithOpcodes {
+) -> Result<FunctionWithOpcodes, PellucidError> {
     let inner_scopes: Vec<OpcodeScope> = convert_skeleton_scopes(
         &RefCell::borrow(&skeleton_junction).instructions,
         returning_blocks,
-    );
+    )?;
     let mut converted_function: FunctionWithOpcodes =
         FunctionWithOpcodes::new_empty(&RefCell::borrow(&skeleton_junction).starting_block);
     converted_function.content = inner_scopes;
     converted_function.n_outputs = None;
-    return converted_function;
+    return Ok(converted_function);
 }
 
 fn convert_skeleton_scopes<'a>(
     skeleton_instructions: &[SkeletonScope<'a>],
     returning_blocks: &HashMap<Block<'a>, Rc<RefCell<SkeletonFunction<'a>>>>,
-) -> Vec<OpcodeScope> {
+) -> Result<Vec<OpcodeScope>, PellucidError> {
     if skeleton_instructions.is_empty() {
-        return Vec::new();
+        return Ok(Vec::new());
     }
 
     let (converted_scopes, next_skeleton_index): (Vec<OpcodeScope>, usize) =
@@ -143,7 +156,7 @@ fn convert_skeleton_scopes<'a>(
                         &consecutive_blocks,
                         returning_blocks,
                         next_skeleton_scope,
-                    ),
+                    )?,
                     first_index_without_block,
                 )
             }
@@ -164,9 +177,9 @@ fn convert_skeleton_scopes<'a>(
             ),
             SkeletonScope::If(skeleton_if) => {
                 let mut instructions_if_true: Vec<OpcodeScope> =
-                    convert_skeleton_scopes(&skeleton_if.true_instructions, returning_blocks);
+                    convert_skeleton_scopes(&skeleton_if.true_instructions, returning_blocks)?;
                 let mut instructions_if_false: Vec<OpcodeScope> =
-                    convert_skeleton_scopes(&skeleton_if.false_instructions, returning_blocks);
+                    convert_skeleton_scopes(&skeleton_if.false_instructions, returning_blocks)?;
                 /*
                     if:                                 if:
                             ----                                ----
@@ -194,20 +207,54 @@ fn convert_skeleton_scopes<'a>(
                 };
                 (concat_and_consume(vec![if_scope], then_scopes), 1)
             }
+            SkeletonScope::JumpTable(jump_table) => {
+                // the index is tested case after case, each branch starts by popping it
+                let pop: OpcodeScope =
+                    Scope::Instructions(InstructionsWithOpcodes::new(vec![Vopcode::new(
+                        Opcode::POP,
+                        None,
+                        jump_table.vopcode.pc,
+                    )]));
+                let mut chained_scopes: Vec<OpcodeScope> = concat_and_consume(
+                    vec![pop.clone()],
+                    convert_skeleton_scopes(&jump_table.default_instructions, returning_blocks)?,
+                );
+                for (case, case_instructions) in jump_table.cases.iter().enumerate().rev() {
+                    let if_scope = Scope::Condition {
+                        instructions_if_true: concat_and_consume(
+                            vec![pop.clone()],
+                            convert_skeleton_scopes(case_instructions, returning_blocks)?,
+                        ),
+                        instructions_if_false: chained_scopes,
+                    };
+                    chained_scopes = if case == 0 {
+                        vec![if_scope] // the first case is tested by the jumping block
+                    } else {
+                        vec![
+                            Scope::Instructions(InstructionsWithOpcodes::new(get_case_test(
+                                &jump_table.vopcode,
+                                case,
+                            ))),
+                            if_scope,
+                        ]
+                    };
+                }
+                (chained_scopes, 1)
+            }
         };
 
     let remaining_skeleton_instructions: &[SkeletonScope] =
         &skeleton_instructions[next_skeleton_index..];
     let remaining_scopes: Vec<OpcodeScope> =
-        convert_skeleton_scopes(remaining_skeleton_instructions, returning_blocks);
-    return concat_and_consume(converted_scopes, remaining_scopes);
+        convert_skeleton_scopes(remaining_skeleton_instructions, returning_blocks)?;
+    return Ok(concat_and_consume(converted_scopes, remaining_scopes));
 }
 
 fn consecutive_blocks_to_scopes<'a>(
     consecutive_blocks: &[Block<'a>],
     returning_blocks: &HashMap<Block<'a>, Rc<RefCell<SkeletonFunction<'a>>>>,
     next_skeleton_scope: Option<&SkeletonScope>,
-) -> Vec<OpcodeScope> {
+) -> Result<Vec<OpcodeScope>, PellucidError> {
     // 'consecutive' doesnt mean that these block have adjacent pc starts, but rather that they are executed consecutively (with jumps from the previous to the next one)
     let mut resulting_scopes: Vec<OpcodeScope> = vec![Scope::Instructions(aggregate_blocks_code(
         consecutive_blocks,
@@ -226,10 +273,14 @@ fn consecutive_blocks_to_scopes<'a>(
                 match _next_skeleton_scope {
                     SkeletonScope::LoopContinue { label: _ } => (), // The last block had been considered to be one end on the currentfunction because its next step is to continue a loop
                     SkeletonScope::Panic => (), // the problem comes from the existence of Panic ...
-                    _ => panic!(
-                        "Non trivial skeleton scope after the end of anfunction: {}",
-                        _next_skeleton_scope.to_string()
-                    ),
+                    _ => {
+                        return Err(PellucidError::UnexpectedScopeAfterReturn {
+                            pc_start: RefCell::borrow(&skeleton_function)
+                                .info
+                                .start
+                                .get_pc_start(),
+                        })
+                    }
                 }
             } else {
                 let label: FunctionLabel =
@@ -238,7 +289,7 @@ fn consecutive_blocks_to_scopes<'a>(
             }
         }
     }
-    return resulting_scopes;
+    return Ok(resulting_scopes);
 }
 
 pub fn aggregate_n_stack_inputs(
@@ -287,9 +338,21 @@ fn aggregate_blocks_code(consecutive_blocks: &[Block]) -> InstructionsWithOpcode
     let mut n_stack_inputs: usize = 0;
     let mut n_stack_outputs: usize = 0;
     for block in consecutive_blocks {
-        code.extend(block.get_code());
+        code.extend(block.get_code().iter().cloned());
+        let mut block_n_stack_outputs: usize = block.clone_symbolic_block().n_outputs();
+        if is_jump_table(code.last().unwrap()) {
+            // cf `SkeletonScope::JumpTable`, the index stays on the stack
+            let jump_table: Vopcode = code.pop().unwrap();
+            code.extend(get_case_test(&jump_table, 0));
+            block_n_stack_outputs += 1;
+        } else if block.has_unresolved_jump() {
+            let final_vopcode: &mut Vopcode = code.last_mut().unwrap();
+            final_vopcode.opcode = match final_vopcode.opcode {
+                Opcode::JUMPI => Opcode::DYNAMIC_JUMPI,
+                _ => Opcode::DYNAMIC_JUMP,
+            };
+        }
         let block_n_stack_inputs: usize = block.clone_symbolic_block().n_args;
-        let block_n_stack_outputs: usize = block.clone_symbolic_block().n_outputs();
         (n_stack_inputs, n_stack_outputs) = aggregate_n_stack_inputs_and_outputs(
             n_stack_inputs,
             n_stack_outputs,
@@ -304,10 +367,30 @@ fn aggregate_blocks_code(consecutive_blocks: &[Block]) -> InstructionsWithOpcode
     };
 }
 
+fn is_jump_table(vopcode: &Vopcode) -> bool {
+    return matches!(vopcode.opcode, Opcode::RJUMPV { max_index } if max_index > 0);
+}
+
+// `index == case`, the index being kept below the result
+fn get_case_test(jump_table: &Vopcode, case: usize) -> Vec<Vopcode> {
+    let case_pc_start: usize = jump_table.get_relative_destinations()[case];
+    return vec![
+        Vopcode::new(
+            Opcode::PUSH { item_size: 1 },
+            Some(U256::from(case)),
+            jump_table.pc,
+        ),
+        Vopcode::new(Opcode::DUP { depth: 2 }, None, jump_table.pc),
+        Vopcode::new(Opcode::EQ, None, jump_table.pc),
+        Vopcode::new_relative_jump(Opcode::RJUMPI, vec![case_pc_start], jump_table.pc),
+    ];
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
         bytecode_reader::bytecode::Bytecode,
+        bytecode_reader::hardfork::Hardfork,
         create_graph::graph::Graph,
         detect_cycles::acyclic_graph::AcyclicGraph,
         execution_flow::{
@@ -328,12 +411,12 @@ mod tests {
         //     String::from("./contracts/simple/contract_0/bytecode.txt");
         println!("Loading {}", path);
         let bytecode_string: String = fs::read_to_string(path).expect("Unable to read file.");
-        let bytecode: Bytecode = Bytecode::from(&bytecode_string).unwrap();
-        let mut graph: Graph = Graph::from(&bytecode);
-        let mut a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph);
+        let bytecode: Bytecode = Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
+        let mut graph: Graph = Graph::from(&bytecode).unwrap();
+        let mut a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph).unwrap();
 
-        let skeleton: Skeleton = Skeleton::build(&mut a_graph);
-        let _: ExecutionFlowWithOpcodes = convert_skeleton_to_execution_flow(&skeleton);
+        let skeleton: Skeleton = Skeleton::build(&mut a_graph).unwrap();
+        let _: ExecutionFlowWithOpcodes = convert_skeleton_to_execution_flow(&skeleton).unwrap();
     }
 
     #[test]
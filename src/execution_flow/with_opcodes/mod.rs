@@ -1,4 +1,5 @@
 pub mod display_flow_with_opcodes;
+pub mod eof_sections;
 pub mod flow_with_opcodes;
 pub mod remove_functions_with_loops;
 pub mod scopes_with_opcodes;
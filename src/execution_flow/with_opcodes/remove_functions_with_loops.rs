@@ -54,6 +54,8 @@ impl ExecutionFlowWithOpcodes {
         self.remove_functions(
             |function: &FunctionWithOpcodes, _: usize| -> bool {
                 function.is_main()
+                    || ExecutionFlowWithOpcodes::is_recursive(function) // cannot be inlined
+                    || function.dynamic_jump_target.is_some() // not called
                     || !ExecutionFlowWithOpcodes::any_scopes(
                         function.get_content(),
                         &mut |scope: &OpcodeScope| scope.is_loop() || scope.is_loop_continue(),
@@ -39,6 +39,7 @@ pub struct FunctionWithOpcodes {
     pub label: FunctionLabel,
     pub n_inputs: usize,
     pub n_outputs: Option<usize>, // 'None' in case it's a junction (cf JunctionSkeletonScope) = anfunction from which we never get out. Here we only consider the output offunctions, that is to say we do not look at the RETURN opcode (but JUMP / JUMPI ...)
+    pub dynamic_jump_target: Option<usize>, // pc of the JUMPDEST when the junction is only reached by jumps whose destination is unknown
     pub content: Vec<OpcodeScope>,
 }
 
@@ -72,6 +73,7 @@ impl FunctionWithOpcodes {
             label: calculate_hash(starting_block),
             n_inputs: 0,
             n_outputs: None,
+            dynamic_jump_target: None,
             content: Vec::new(),
         };
     }
@@ -91,8 +93,26 @@ impl Length for InstructionsWithOpcodes {
 }
 
 impl InstructionsWithOpcodes {
-    pub fn get_last_vopcode(&self) -> Vopcode {
-        return self.code[self.len() - 1];
+    pub fn new(code: Vec<Vopcode>) -> Self {
+        let mut n_stack_inputs: usize = 0;
+        let mut n_stack_outputs: usize = 0;
+        for vopcode in &code {
+            (n_stack_inputs, n_stack_outputs) = aggregate_n_stack_inputs_and_outputs(
+                n_stack_inputs,
+                n_stack_outputs,
+                vopcode.stack_input(),
+                vopcode.stack_output(),
+            );
+        }
+        return InstructionsWithOpcodes {
+            code,
+            n_stack_inputs,
+            n_stack_outputs,
+        };
+    }
+
+    pub fn get_last_vopcode(&self) -> &Vopcode {
+        return &self.code[self.len() - 1];
     }
     pub fn len(&self) -> usize {
         return self.code.len();
@@ -117,12 +137,13 @@ fn _fill_n_inputs_and_outputs(
     function_scopes: &mut HashMap<FunctionLabel, FunctionWithOpcodes>,
 ) {
     let content: Vec<OpcodeScope> = function_scopes[&label].content.clone();
+    // a recursive call is seen as never returning (no inputs, no outputs), the sizes come from the other branches
+    function_alias_already_filled.insert(label);
     let (n_inputs, n_outputs) = get_n_inputs_and_outputs_until_end(
         &content,
         function_alias_already_filled,
         function_scopes,
     );
-    function_alias_already_filled.insert(label);
     function_scopes.get_mut(&label).unwrap().n_inputs = n_inputs;
     function_scopes.get_mut(&label).unwrap().n_outputs = n_outputs;
 }
@@ -215,6 +236,13 @@ fn get_n_inputs_and_outputs_until_end(
             n_inputs_at_first_scope = 0;
             n_outputs_at_first_scope = Some(0);
         }
+        Scope::LoopBreak { label: _ }
+        | Scope::StructuredLoop {
+            label: _,
+            kind: _,
+            header: _,
+            body: _,
+        } => panic!("Loops are only structured once variables are incorporated."),
     }
 
     let (following_n_inputs, following_n_outputs): (usize, Option<usize>) =
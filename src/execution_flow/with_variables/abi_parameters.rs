@@ -0,0 +1,585 @@
+use std::collections::{HashMap, HashSet};
+
+use primitive_types::U256;
+
+use super::{
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::{Line, ParameterField, Value, Variable},
+    scopes_with_vars::{FunctionKind, FunctionWithVars, VarScope},
+    simplify::{
+        initialization_of_vars::enumerate_var_initializations,
+        remove_small_functions::replace_function_call_by_content_in_scopes,
+        simplify_vars::simplify_vars_in_scopes,
+    },
+};
+use crate::{
+    bytecode_reader::opcode::Opcode,
+    execution_flow::execution_flow::{FunctionLabel, Scope},
+};
+
+// Arguments start after the selector, each one in its own word.
+const ARGUMENTS_START: u64 = 0x04;
+const MAX_PARAMETERS: u64 = 0x100;
+
+impl ExecutionFlowWithVars {
+    // The head of the i-th argument of an external function is read at `calldata[4 + 32 * i]`.
+    // For dynamic types (bytes, string, arrays) the head is the offset of the length, which is
+    // followed by the content.
+    pub fn decode_abi_parameters(&mut self) {
+        self.inline_abi_decoders();
+        for function in self.functions.values_mut() {
+            if let FunctionKind::External { selector: _ } = function.kind {
+                decode_abi_parameters_of_function(function);
+            }
+        }
+    }
+
+    // Solidity decodes arguments in helper functions taking the start of the arguments as input,
+    // they are inlined so that the calldata offsets become constant.
+    fn inline_abi_decoders(&mut self) {
+        loop {
+            let n_functions: usize = self.functions.len();
+            self.remove_functions(
+                |function: &FunctionWithVars, _| !is_abi_decoder(function),
+                replace_function_call_by_content_in_scopes,
+            );
+            // a decoder calling another one is only recognised once its callee is inlined
+            if self.functions.len() == n_functions {
+                return;
+            }
+        }
+    }
+}
+
+fn is_abi_decoder(function: &FunctionWithVars) -> bool {
+    if function.kind != FunctionKind::Internal
+        || ExecutionFlowWithVars::any_scopes(&function.content, &mut |scope: &VarScope| {
+            scope.is_loop()
+        })
+    {
+        return false;
+    }
+    // its returns become assignments of the call results, which is only right for returns of its
+    // own ending the function
+    if !returns_only_at_the_end(&function.content, function.label) {
+        return false;
+    }
+    let input_vars: HashSet<Variable> = function.input_vars.iter().copied().collect();
+    let mut reads_calldata_from_input: bool = false;
+    apply_on_values(&function.content, &mut |value: &Value| {
+        if let Value::Calculation {
+            opcode: Opcode::CALLDATALOAD,
+            args,
+        } = value
+        {
+            reads_calldata_from_input =
+                reads_calldata_from_input || uses_vars(&args[0], &input_vars);
+        }
+    });
+    return reads_calldata_from_input;
+}
+
+fn returns_only_at_the_end(scopes: &Vec<VarScope>, label: FunctionLabel) -> bool {
+    for (scope_index, scope) in scopes.iter().enumerate() {
+        let is_last: bool = scope_index + 1 == scopes.len();
+        match scope {
+            Scope::FunctionReturn(function_return) => {
+                if !is_last || function_return.label != label {
+                    return false;
+                }
+            }
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => {
+                if is_last {
+                    if !returns_only_at_the_end(instructions_if_true, label)
+                        || !returns_only_at_the_end(instructions_if_false, label)
+                    {
+                        return false;
+                    }
+                } else if ExecutionFlowWithVars::any_scopes(
+                    &vec![scope.clone()],
+                    &mut |scope: &VarScope| matches!(scope, Scope::FunctionReturn(_)),
+                ) {
+                    return false;
+                }
+            }
+            _ => (),
+        }
+    }
+    return true;
+}
+
+fn decode_abi_parameters_of_function(function: &mut FunctionWithVars) {
+    let mut n_parameters: usize = 0;
+    apply_on_values_mut(&mut function.content, &mut |value: &mut Value| {
+        if let Some(index) = get_head_index(value) {
+            n_parameters = n_parameters.max(index + 1);
+            *value = Value::Parameter {
+                index,
+                field: ParameterField::Word,
+            };
+        }
+    });
+    if n_parameters == 0 {
+        return;
+    }
+
+    // the content of a dynamic parameter is read relatively to its head
+    let head_vars: HashMap<Variable, usize> =
+        find_parameter_vars(&function.content, ParameterField::Word);
+    let mut dynamic_parameters: HashSet<usize> = HashSet::new();
+    apply_on_values_mut(&mut function.content, &mut |value: &mut Value| {
+        let (is_load, address): (bool, &Value) = match value {
+            Value::Calculation {
+                opcode: Opcode::CALLDATALOAD,
+                args,
+            } => (true, &args[0]),
+            Value::Calculation {
+                opcode: Opcode::CALLDATACOPY,
+                args,
+            } => (false, &args[1]),
+            _ => return,
+        };
+        let (constant, terms) = split_sum(address);
+        let heads: Vec<usize> = terms
+            .iter()
+            .filter_map(|term| get_parameter(term, &head_vars, ParameterField::Word))
+            .collect();
+        dynamic_parameters.extend(&heads);
+        // `calldata[4 + offset]`
+        if is_load
+            && terms.len() == 1
+            && heads.len() == 1
+            && constant == U256::from(ARGUMENTS_START)
+        {
+            *value = Value::Parameter {
+                index: heads[0],
+                field: ParameterField::Length,
+            };
+        }
+    });
+    apply_on_values_mut(&mut function.content, &mut |value: &mut Value| {
+        if let Value::Parameter { index, field } = value {
+            if *field == ParameterField::Word && dynamic_parameters.contains(index) {
+                *field = ParameterField::Offset;
+            }
+        }
+    });
+
+    // the length of an array is a number of words, the one of bytes and strings is not
+    let length_vars: HashMap<Variable, usize> =
+        find_parameter_vars(&function.content, ParameterField::Length);
+    let mut array_parameters: HashSet<usize> = HashSet::new();
+    apply_on_values(&function.content, &mut |value: &Value| {
+        let length: Option<&Value> = match value {
+            Value::Calculation {
+                opcode: Opcode::MUL,
+                args,
+            } => {
+                if args[0] == Value::Bytes(U256::from(0x20)) {
+                    Some(&args[1])
+                } else if args[1] == Value::Bytes(U256::from(0x20)) {
+                    Some(&args[0])
+                } else {
+                    None
+                }
+            }
+            Value::Calculation {
+                opcode: Opcode::SHL,
+                args,
+            } if args[0] == Value::Bytes(U256::from(0x05)) => Some(&args[1]),
+            _ => None,
+        };
+        if let Some(index) =
+            length.and_then(|length| get_parameter(length, &length_vars, ParameterField::Length))
+        {
+            array_parameters.insert(index);
+        }
+    });
+
+    function.parameters = (0..n_parameters)
+        .map(|index| {
+            if array_parameters.contains(&index) {
+                return "uint256[]".to_owned();
+            }
+            if dynamic_parameters.contains(&index) {
+                return "bytes".to_owned();
+            }
+            return "uint256".to_owned();
+        })
+        .collect();
+    // copies of the parameters can now be removed
+    simplify_vars_in_scopes(&mut function.content);
+}
+
+// `calldata[4 + 32 * index]`
+fn get_head_index(value: &Value) -> Option<usize> {
+    if let Value::Calculation {
+        opcode: Opcode::CALLDATALOAD,
+        args,
+    } = value
+    {
+        let offset: U256 = args[0].compute_value()?;
+        let start: U256 = U256::from(ARGUMENTS_START);
+        if offset < start
+            || offset >= start + MAX_PARAMETERS * 0x20
+            || (offset - start) % 0x20 != U256::zero()
+        {
+            return None;
+        }
+        return Some(((offset - start) / 0x20).as_usize());
+    }
+    return None;
+}
+
+fn get_parameter(
+    value: &Value,
+    parameter_vars: &HashMap<Variable, usize>,
+    searched_field: ParameterField,
+) -> Option<usize> {
+    match value {
+        Value::Parameter { index, field } if *field == searched_field => return Some(*index),
+        Value::Existing(var) => return parameter_vars.get(var).copied(),
+        _ => return None,
+    }
+}
+
+// Variables only holding a copy of a parameter.
+fn find_parameter_vars(scopes: &Vec<VarScope>, field: ParameterField) -> HashMap<Variable, usize> {
+    let n_initializations_per_var: HashMap<Variable, usize> = enumerate_var_initializations(scopes);
+    let mut parameter_vars: HashMap<Variable, usize> = HashMap::new();
+    loop {
+        let n_parameter_vars: usize = parameter_vars.len();
+        let mut new_parameter_vars: HashMap<Variable, usize> = HashMap::new();
+        ExecutionFlowWithVars::apply_on_scopes(scopes, &mut |scope: &VarScope| {
+            if let Scope::Instructions(instructions) = scope {
+                for line in &instructions.lines {
+                    if let Line::Assignement {
+                        receiving_var: Some(receiving_var),
+                        assigned_value,
+                    } = line
+                    {
+                        if n_initializations_per_var[receiving_var] != 1 {
+                            continue;
+                        }
+                        if let Some(index) = get_parameter(assigned_value, &parameter_vars, field) {
+                            new_parameter_vars.insert(*receiving_var, index);
+                        }
+                    }
+                }
+            }
+        });
+        parameter_vars.extend(new_parameter_vars);
+        if parameter_vars.len() == n_parameter_vars {
+            return parameter_vars;
+        }
+    }
+}
+
+// Constant part and other terms of a sum.
+fn split_sum(value: &Value) -> (U256, Vec<&Value>) {
+    if let Some(constant) = value.compute_value() {
+        return (constant, Vec::new());
+    }
+    if let Value::Calculation {
+        opcode: Opcode::ADD,
+        args,
+    } = value
+    {
+        let (constant_0, mut terms) = split_sum(&args[0]);
+        let (constant_1, terms_1) = split_sum(&args[1]);
+        terms.extend(terms_1);
+        return (constant_0.overflowing_add(constant_1).0, terms);
+    }
+    return (U256::zero(), vec![value]);
+}
+
+fn uses_vars(value: &Value, vars: &HashSet<Variable>) -> bool {
+    match value {
+        Value::Existing(var) => return vars.contains(var),
+        Value::Calculation { opcode: _, args } => {
+            return args.iter().any(|arg| uses_vars(arg, vars));
+        }
+        _ => return false,
+    }
+}
+
+pub fn apply_on_values(scopes: &Vec<VarScope>, key: &mut impl FnMut(&Value)) {
+    ExecutionFlowWithVars::apply_on_scopes(scopes, &mut |scope: &VarScope| {
+        let values: Vec<&Value> = match scope {
+            Scope::Instructions(instructions) => instructions
+                .lines
+                .iter()
+                .flat_map(|line: &Line| match line {
+                    Line::Assignement {
+                        receiving_var: _,
+                        assigned_value,
+                    } => vec![assigned_value],
+                    Line::If { condition } => vec![condition],
+                    Line::DynamicJump {
+                        condition,
+                        destination,
+                    } => condition.iter().chain([destination]).collect(),
+                    Line::Require { condition, error } => {
+                        [condition].into_iter().chain(error).collect()
+                    }
+                    Line::Empty => Vec::new(),
+                })
+                .collect(),
+            Scope::FunctionCall(function_call) => function_call.arguments.iter().collect(),
+            Scope::FunctionReturn(function_return) => {
+                function_return.returned_values.iter().collect()
+            }
+            _ => Vec::new(),
+        };
+        for value in values {
+            apply_on_value(value, key);
+        }
+    });
+}
+
+pub fn apply_on_value(value: &Value, key: &mut impl FnMut(&Value)) {
+    match value {
+        Value::Calculation { opcode: _, args } => {
+            for arg in args {
+                apply_on_value(arg, key);
+            }
+        }
+        Value::FunctionReturnedValue {
+            label: _,
+            arguments,
+            return_index: _,
+        }
+        | Value::Revert {
+            error: _,
+            arguments,
+        } => {
+            for argument in arguments {
+                apply_on_value(argument, key);
+            }
+        }
+        Value::Event { topics, data } => {
+            for argument in topics.iter().chain(data) {
+                apply_on_value(argument, key);
+            }
+        }
+        Value::StorageSlot { slot: _, keys } => {
+            for (_, storage_key) in keys {
+                apply_on_value(storage_key, key);
+            }
+        }
+        _ => (),
+    }
+    key(value);
+}
+
+// Nested values are visited before the ones containing them.
+pub fn apply_on_values_mut(scopes: &mut Vec<VarScope>, key: &mut impl FnMut(&mut Value)) {
+    for scope in scopes {
+        match scope {
+            Scope::Instructions(instructions) => {
+                for line in &mut instructions.lines {
+                    match line {
+                        Line::Assignement {
+                            receiving_var: _,
+                            assigned_value,
+                        } => apply_on_value_mut(assigned_value, key),
+                        Line::If { condition } => apply_on_value_mut(condition, key),
+                        Line::DynamicJump {
+                            condition,
+                            destination,
+                        } => {
+                            if let Some(jump_condition) = condition {
+                                apply_on_value_mut(jump_condition, key);
+                            }
+                            apply_on_value_mut(destination, key);
+                        }
+                        Line::Require { condition, error } => {
+                            apply_on_value_mut(condition, key);
+                            if let Some(revert_error) = error {
+                                apply_on_value_mut(revert_error, key);
+                            }
+                        }
+                        Line::Empty => (),
+                    }
+                }
+            }
+            Scope::FunctionCall(function_call) => {
+                for argument in &mut function_call.arguments {
+                    apply_on_value_mut(argument, key);
+                }
+            }
+            Scope::FunctionReturn(function_return) => {
+                for returned_value in &mut function_return.returned_values {
+                    apply_on_value_mut(returned_value, key);
+                }
+            }
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => {
+                apply_on_values_mut(instructions_if_true, key);
+                apply_on_values_mut(instructions_if_false, key);
+            }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                apply_on_values_mut(header, key);
+                apply_on_values_mut(body, key);
+            }
+            _ => (),
+        }
+    }
+}
+
+pub fn apply_on_value_mut(value: &mut Value, key: &mut impl FnMut(&mut Value)) {
+    match value {
+        Value::Calculation { opcode: _, args } => {
+            for arg in args {
+                apply_on_value_mut(arg, key);
+            }
+        }
+        Value::FunctionReturnedValue {
+            label: _,
+            arguments,
+            return_index: _,
+        }
+        | Value::Revert {
+            error: _,
+            arguments,
+        } => {
+            for argument in arguments {
+                apply_on_value_mut(argument, key);
+            }
+        }
+        Value::Event { topics, data } => {
+            for argument in topics.iter_mut().chain(data) {
+                apply_on_value_mut(argument, key);
+            }
+        }
+        Value::StorageSlot { slot: _, keys } => {
+            for (_, storage_key) in keys {
+                apply_on_value_mut(storage_key, key);
+            }
+        }
+        _ => (),
+    }
+    key(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompiler::Decompiler;
+    use crate::execution_flow::with_variables::scopes_with_vars::FunctionReturnWithVars;
+    use crate::tools::utils::read_file;
+
+    fn get_external_function(bytecode: &str, selector: u32) -> FunctionWithVars {
+        let decompiler: Decompiler = Decompiler::from_hex(bytecode).unwrap();
+        let flow: ExecutionFlowWithVars = decompiler.decompile().unwrap().execution_flow_with_vars;
+        return flow
+            .functions
+            .into_values()
+            .find(|function| function.kind == FunctionKind::External { selector })
+            .unwrap();
+    }
+
+    // (slot, value) of the SSTOREs of the function
+    fn get_stored_values(function: &FunctionWithVars) -> Vec<(Value, Value)> {
+        let mut stored_values: Vec<(Value, Value)> = Vec::new();
+        apply_on_values(&function.content, &mut |value: &Value| {
+            if let Value::Calculation {
+                opcode: Opcode::SSTORE,
+                args,
+            } = value
+            {
+                stored_values.push((args[0].clone(), args[1].clone()));
+            }
+        });
+        return stored_values;
+    }
+
+    #[test]
+    fn test_decode_abi_parameters() {
+        // 0x11111111: storage[0] = length of a dynamic arg0, storage[1] = arg1
+        // 0x22222222: storage[2] = 0x20 * length of a dynamic arg0
+        let bytecode: &str = "60003560e01c80631111111114601b5780632222222214602d57005b60043560040135600055602435600155005b6004356004013560200260025500";
+        let function: FunctionWithVars = get_external_function(bytecode, 0x11111111);
+        assert_eq!(function.parameters, vec!["bytes", "uint256"]);
+        assert_eq!(
+            get_stored_values(&function),
+            vec![
+                (
+                    Value::Bytes(U256::zero()),
+                    Value::Parameter {
+                        index: 0,
+                        field: ParameterField::Length
+                    }
+                ),
+                (
+                    Value::Bytes(U256::one()),
+                    Value::Parameter {
+                        index: 1,
+                        field: ParameterField::Word
+                    }
+                ),
+            ]
+        );
+        let function: FunctionWithVars = get_external_function(bytecode, 0x22222222);
+        assert_eq!(function.parameters, vec!["uint256[]"]);
+    }
+
+    #[test]
+    fn test_returns_only_at_the_end() {
+        let function_return = |label: FunctionLabel| -> VarScope {
+            return Scope::FunctionReturn(FunctionReturnWithVars {
+                label,
+                returned_values: vec![Value::Bytes(U256::one())],
+            });
+        };
+        let condition = |instructions_if_true: Vec<VarScope>| -> VarScope {
+            return Scope::Condition {
+                instructions_if_true,
+                instructions_if_false: vec![Scope::Empty],
+            };
+        };
+        assert!(returns_only_at_the_end(&vec![function_return(1)], 1));
+        assert!(returns_only_at_the_end(
+            &vec![condition(vec![function_return(1)])],
+            1
+        ));
+        // the return of another function stays in the caller once inlined
+        assert!(!returns_only_at_the_end(&vec![function_return(2)], 1));
+        // the code following the condition would be run after the assignment of the results
+        assert!(!returns_only_at_the_end(
+            &vec![condition(vec![function_return(1)]), function_return(1)],
+            1
+        ));
+    }
+
+    #[test]
+    fn test_inline_abi_decoders() {
+        // the argument is decoded in a helper function
+        let function: FunctionWithVars = get_external_function(
+            &read_file("./contracts/loop/contract_1/bytecode.txt"),
+            0x15b09b67,
+        );
+        assert_eq!(function.parameters, vec!["uint256"]);
+        let mut reads_calldata: bool = false;
+        apply_on_values(&function.content, &mut |value: &Value| {
+            if let Value::Calculation {
+                opcode: Opcode::CALLDATALOAD,
+                args: _,
+            } = value
+            {
+                reads_calldata = true;
+            }
+        });
+        assert!(!reads_calldata);
+    }
+}
@@ -0,0 +1,688 @@
+use std::collections::{HashMap, HashSet};
+
+use primitive_types::U256;
+
+use super::{
+    abi_parameters::{apply_on_value, apply_on_value_mut, apply_on_values_mut},
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::{Line, RevertError, Value, Variable},
+    requires::replace_conditions_in_scopes,
+    scopes_with_vars::VarScope,
+    simplify::initialization_of_vars::enumerate_var_initializations,
+};
+use crate::{
+    bytecode_reader::opcode::Opcode,
+    error::PellucidError,
+    execution_flow::execution_flow::{FunctionLabel, Scope},
+};
+
+const ARITHMETIC_OVERFLOW: u64 = 0x11;
+const DIVISION_BY_ZERO: u64 = 0x12;
+
+// The calculation whose result is checked by the condition of a panic.
+#[derive(Clone, Debug)]
+enum CheckedCalculation {
+    Operation { opcode: Opcode, args: [Value; 2] }, // computed after the check
+    Divisor(Value),                                 // `_ / divisor`, computed after the check
+    Result(Variable),                               // computed before the check
+    Inline(Value), // `x + 1` computed in the check and again after it
+}
+
+struct CheckedArithmetic {
+    assigned_values: HashMap<Variable, Value>, // of the variables assigned once, in a function
+    panic_functions: HashMap<FunctionLabel, u64>, // functions that only panic, with their code
+    checked_results: HashSet<Variable>,
+}
+
+impl ExecutionFlowWithVars {
+    // Since solidity 0.8, additions, subtractions, multiplications and divisions are checked and
+    // panic on overflow (code 0x11) or division by zero (code 0x12). The check and its panic
+    // branch are removed, and the operation is displayed as checked.
+    pub fn collapse_checked_arithmetic(&mut self) -> Result<(), PellucidError> {
+        let panic_functions: HashMap<FunctionLabel, u64> = self
+            .functions
+            .values()
+            .filter_map(|function| {
+                get_panic_code(&function.content, &HashMap::new())
+                    .map(|panic_code| (function.label, panic_code))
+            })
+            .collect();
+        for function in self.functions.values_mut() {
+            let mut checked_arithmetic: CheckedArithmetic = CheckedArithmetic {
+                assigned_values: get_single_assignments(&function.content),
+                panic_functions: panic_functions.clone(),
+                checked_results: HashSet::new(),
+            };
+            replace_conditions_in_scopes(&mut function.content, &mut |scopes, scope_index| {
+                return Ok(collapse_checked_condition(
+                    scopes,
+                    scope_index,
+                    &mut checked_arithmetic,
+                ));
+            })?;
+            mark_checked_results(&mut function.content, &checked_arithmetic.checked_results);
+        }
+
+        // the checks were usually the only callers of the functions that panic
+        let reachable_functions: HashSet<FunctionLabel> = self
+            .functions
+            .values()
+            .filter(|function| function.is_entry_point())
+            .flat_map(|function| self.get_reachable_functions(function.label))
+            .collect();
+        self.functions.retain(|label, _| {
+            !panic_functions.contains_key(label) || reachable_functions.contains(label)
+        });
+
+        return Ok(());
+    }
+}
+
+fn get_single_assignments(scopes: &Vec<VarScope>) -> HashMap<Variable, Value> {
+    let n_initializations_per_var: HashMap<Variable, usize> = enumerate_var_initializations(scopes);
+    let mut assigned_values: HashMap<Variable, Value> = HashMap::new();
+    ExecutionFlowWithVars::apply_on_scopes(scopes, &mut |scope| {
+        if let Scope::Instructions(instructions) = scope {
+            for line in &instructions.lines {
+                if let Line::Assignement {
+                    receiving_var: Some(receiving_var),
+                    assigned_value,
+                } = line
+                {
+                    if n_initializations_per_var[receiving_var] == 1 {
+                        assigned_values.insert(*receiving_var, assigned_value.clone());
+                    }
+                }
+            }
+        }
+    });
+    return assigned_values;
+}
+
+// Returns the branch that does not panic if the condition is a recognised check. The `if` line
+// is removed and the checked calculation is marked.
+fn collapse_checked_condition(
+    scopes: &mut [VarScope],
+    scope_index: usize,
+    checked_arithmetic: &mut CheckedArithmetic,
+) -> Option<Vec<VarScope>> {
+    let Scope::Condition {
+        instructions_if_true,
+        instructions_if_false,
+    } = &scopes[scope_index]
+    else {
+        return None;
+    };
+    let (panic_code, panics_if_true): (u64, bool) = match (
+        get_panic_code(instructions_if_true, &checked_arithmetic.panic_functions),
+        get_panic_code(instructions_if_false, &checked_arithmetic.panic_functions),
+    ) {
+        (Some(panic_code), None) => (panic_code, true),
+        (None, Some(panic_code)) => (panic_code, false),
+        _ => return None,
+    };
+    let Scope::Instructions(instructions) = &scopes[scope_index - 1] else {
+        return None;
+    };
+    let Some(Line::If { condition }) = instructions.lines.last() else {
+        return None;
+    };
+    let candidates: Vec<CheckedCalculation> = get_checked_calculations(
+        condition,
+        panics_if_true,
+        panic_code,
+        &checked_arithmetic.assigned_values,
+    );
+
+    let mut kept_scopes: Vec<VarScope> = if panics_if_true {
+        instructions_if_false.clone()
+    } else {
+        instructions_if_true.clone()
+    };
+    let mut is_collapsed: bool = false;
+    for candidate in candidates {
+        if let CheckedCalculation::Result(var) = candidate {
+            checked_arithmetic.checked_results.insert(var);
+            is_collapsed = true;
+            break;
+        }
+        if mark_checked_calculation(
+            &mut kept_scopes,
+            &candidate,
+            &checked_arithmetic.assigned_values,
+        ) {
+            is_collapsed = true;
+            break;
+        }
+    }
+    if !is_collapsed {
+        return None;
+    }
+    if let Scope::Instructions(instructions) = &mut scopes[scope_index - 1] {
+        instructions.lines.pop();
+    }
+    return Some(kept_scopes);
+}
+
+// Code of the panic if the scopes only panic, possibly by calling a function.
+fn get_panic_code(
+    scopes: &[VarScope],
+    panic_functions: &HashMap<FunctionLabel, u64>,
+) -> Option<u64> {
+    let mut panic_code: Option<u64> = None;
+    for scope in scopes {
+        match scope {
+            Scope::Instructions(instructions) => {
+                for line in &instructions.lines {
+                    match line {
+                        Line::Empty => (),
+                        Line::Assignement {
+                            receiving_var: None,
+                            assigned_value:
+                                Value::Revert {
+                                    error: RevertError::Panic(code),
+                                    arguments: _,
+                                },
+                        } if panic_code.is_none() && *code <= U256::from(u64::MAX) => {
+                            panic_code = Some(code.as_u64())
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            Scope::FunctionCall(function_call) if panic_code.is_none() => {
+                panic_code = Some(*panic_functions.get(&function_call.label)?)
+            }
+            Scope::Empty => (),
+            _ => return None,
+        }
+    }
+    return panic_code;
+}
+
+// Candidates are sorted from the most to the least specific.
+fn get_checked_calculations(
+    condition: &Value,
+    panics_if_true: bool,
+    panic_code: u64,
+    assigned_values: &HashMap<Variable, Value>,
+) -> Vec<CheckedCalculation> {
+    // `!!x` is true when `x` is
+    let condition: Value = resolve_copies(condition, assigned_values);
+    let mut panic_condition: &Value = &condition;
+    let mut is_negated: bool = !panics_if_true;
+    loop {
+        match panic_condition {
+            Value::Calculation {
+                opcode: Opcode::ISZERO,
+                args,
+            } => {
+                panic_condition = &args[0];
+                is_negated = !is_negated;
+            }
+            // the result of a comparison
+            Value::Existing(var) => match assigned_values.get(var) {
+                Some(
+                    assigned_value @ Value::Calculation {
+                        opcode:
+                            Opcode::ISZERO
+                            | Opcode::EQ
+                            | Opcode::LT
+                            | Opcode::GT
+                            | Opcode::AND
+                            | Opcode::OR,
+                        args: _,
+                    },
+                ) => panic_condition = assigned_value,
+                _ => break,
+            },
+            _ => break,
+        }
+    }
+
+    let mut candidates: Vec<CheckedCalculation> = Vec::new();
+    if is_negated {
+        // panics if `x == 0`
+        match panic_code {
+            // `x + 1 == 0`
+            ARITHMETIC_OVERFLOW if is_increment(panic_condition) => {
+                candidates.push(CheckedCalculation::Inline(panic_condition.clone()))
+            }
+            ARITHMETIC_OVERFLOW => candidates.push(CheckedCalculation::Operation {
+                opcode: Opcode::SUB,
+                args: [panic_condition.clone(), Value::Bytes(U256::one())],
+            }),
+            DIVISION_BY_ZERO => {
+                candidates.push(CheckedCalculation::Divisor(panic_condition.clone()))
+            }
+            _ => (),
+        }
+    } else if panic_code == DIVISION_BY_ZERO {
+        // panics if `x == 0`
+        if let Value::Calculation {
+            opcode: Opcode::EQ,
+            args,
+        } = panic_condition
+        {
+            for (divisor, zero) in [(&args[0], &args[1]), (&args[1], &args[0])] {
+                if zero.compute_value() == Some(U256::zero()) {
+                    candidates.push(CheckedCalculation::Divisor(divisor.clone()));
+                }
+            }
+        }
+    } else if panic_code == ARITHMETIC_OVERFLOW {
+        get_overflow_checks(panic_condition, &mut candidates);
+    }
+
+    if panic_code == ARITHMETIC_OVERFLOW {
+        // the result of the operation is compared to its operands
+        apply_on_value(panic_condition, &mut |value| {
+            if let Value::Existing(var) = value {
+                if let Some(Value::Calculation {
+                    opcode: opcode @ (Opcode::ADD | Opcode::SUB | Opcode::MUL),
+                    args,
+                }) = assigned_values.get(var)
+                {
+                    let operands: Vec<Value> = args
+                        .iter()
+                        .map(|arg| resolve_copies(arg, assigned_values))
+                        .collect();
+                    if is_result_check(panic_condition, is_negated, value, *opcode, &operands) {
+                        candidates.push(CheckedCalculation::Result(*var));
+                    }
+                }
+            }
+        });
+    }
+    return candidates;
+}
+
+fn is_increment(value: &Value) -> bool {
+    let Value::Calculation {
+        opcode: Opcode::ADD,
+        args,
+    } = value
+    else {
+        return false;
+    };
+    return args
+        .iter()
+        .any(|arg| arg.compute_value() == Some(U256::one()));
+}
+
+// `x > x + y` or `x < x - y` when the panic condition is true, `x == 0 || x * y / x == y` when
+// it is false.
+fn is_result_check(
+    panic_condition: &Value,
+    is_negated: bool,
+    result: &Value,
+    opcode: Opcode,
+    operands: &[Value],
+) -> bool {
+    let Value::Calculation {
+        opcode: condition_opcode,
+        args,
+    } = panic_condition
+    else {
+        return false;
+    };
+    if opcode == Opcode::MUL {
+        if !is_negated {
+            return false;
+        }
+        // the division is only done by a nonzero operand
+        if *condition_opcode == Opcode::OR {
+            return args.iter().any(|arg| {
+                is_result_check(arg, is_negated, result, opcode, operands)
+                    && args.iter().any(|other_arg| {
+                        matches!(
+                            other_arg,
+                            Value::Calculation {
+                                opcode: Opcode::ISZERO,
+                                args: zero_args,
+                            } if operands.contains(&zero_args[0])
+                        )
+                    })
+            });
+        }
+        if *condition_opcode != Opcode::EQ {
+            return false;
+        }
+        for (quotient, divided) in [(&args[0], &args[1]), (&args[1], &args[0])] {
+            if let Value::Calculation {
+                opcode: Opcode::DIV,
+                args: division_args,
+            } = quotient
+            {
+                for (divisor, other_operand) in
+                    [(&operands[0], &operands[1]), (&operands[1], &operands[0])]
+                {
+                    if &division_args[0] == result
+                        && &division_args[1] == divisor
+                        && divided == other_operand
+                    {
+                        return true;
+                    }
+                }
+            }
+        }
+        return false;
+    }
+
+    if is_negated {
+        return false;
+    }
+    let (smaller, greater): (&Value, &Value) = match condition_opcode {
+        Opcode::LT => (&args[0], &args[1]),
+        Opcode::GT => (&args[1], &args[0]),
+        _ => return false,
+    };
+    match opcode {
+        // the sum is smaller than an operand
+        Opcode::ADD => return smaller == result && operands.contains(greater),
+        // the difference is greater than the first operand
+        Opcode::SUB => return greater == result && *smaller == operands[0],
+        _ => return false,
+    }
+}
+
+// Checks done before an operation, when the condition is true.
+fn get_overflow_checks(panic_condition: &Value, candidates: &mut Vec<CheckedCalculation>) {
+    let Value::Calculation { opcode, args } = panic_condition else {
+        return;
+    };
+    match opcode {
+        // `x == MAX` before `x + 1`
+        Opcode::EQ => {
+            for (operand, bound) in [(&args[0], &args[1]), (&args[1], &args[0])] {
+                if bound.compute_value().is_some_and(is_max_value) {
+                    candidates.push(CheckedCalculation::Operation {
+                        opcode: Opcode::ADD,
+                        args: [operand.clone(), Value::Bytes(U256::one())],
+                    });
+                }
+            }
+        }
+        // `x > MAX - y` before `x + y`, or `y > MAX / x` before `x * y`
+        Opcode::GT => {
+            match &args[1] {
+                Value::Calculation {
+                    opcode: Opcode::SUB,
+                    args: bound_args,
+                } if bound_args[0].compute_value().is_some_and(is_max_value) => {
+                    candidates.push(CheckedCalculation::Operation {
+                        opcode: Opcode::ADD,
+                        args: [args[0].clone(), bound_args[1].clone()],
+                    })
+                }
+                Value::Calculation {
+                    opcode: Opcode::NOT,
+                    args: bound_args,
+                } => candidates.push(CheckedCalculation::Operation {
+                    opcode: Opcode::ADD,
+                    args: [args[0].clone(), bound_args[0].clone()],
+                }),
+                Value::Calculation {
+                    opcode: Opcode::DIV,
+                    args: bound_args,
+                } if bound_args[0].compute_value().is_some_and(is_max_value) => {
+                    candidates.push(CheckedCalculation::Operation {
+                        opcode: Opcode::MUL,
+                        args: [bound_args[1].clone(), args[0].clone()],
+                    })
+                }
+                bound => {
+                    // `MAX - y` is computed when `y` is a constant
+                    if let Some(bound_value) = bound.compute_value() {
+                        for max_value in (8..=256).step_by(8).map(get_max_value) {
+                            if bound_value <= max_value {
+                                candidates.push(CheckedCalculation::Operation {
+                                    opcode: Opcode::ADD,
+                                    args: [args[0].clone(), Value::Bytes(max_value - bound_value)],
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            // `y > x` before `x - y`
+            candidates.push(CheckedCalculation::Operation {
+                opcode: Opcode::SUB,
+                args: [args[1].clone(), args[0].clone()],
+            });
+        }
+        // `x < y` before `x - y`
+        Opcode::LT => candidates.push(CheckedCalculation::Operation {
+            opcode: Opcode::SUB,
+            args: [args[0].clone(), args[1].clone()],
+        }),
+        // `x != 0 && y > MAX / x` before `x * y`
+        Opcode::AND => {
+            for arg in args {
+                get_overflow_checks(arg, candidates);
+            }
+        }
+        _ => (),
+    }
+}
+
+// Copies of variables are replaced by the copied variables.
+fn resolve_copies(value: &Value, assigned_values: &HashMap<Variable, Value>) -> Value {
+    let mut resolved_value: Value = value.clone();
+    apply_on_value_mut(&mut resolved_value, &mut |nested_value| {
+        // copies inside a loop may copy each other
+        for _ in 0..assigned_values.len() {
+            let Value::Existing(var) = nested_value else {
+                return;
+            };
+            match assigned_values.get(var) {
+                Some(copied_value @ Value::Existing(_)) => *nested_value = copied_value.clone(),
+                _ => return,
+            }
+        }
+    });
+    return resolved_value;
+}
+
+fn get_max_value(bits: usize) -> U256 {
+    if bits == 256 {
+        return U256::MAX;
+    }
+    return (U256::one() << bits) - 1;
+}
+
+fn is_max_value(value: U256) -> bool {
+    return (8..=256)
+        .step_by(8)
+        .any(|bits| get_max_value(bits) == value);
+}
+
+// Marks the first calculation matching the candidate.
+fn mark_checked_calculation(
+    scopes: &mut Vec<VarScope>,
+    candidate: &CheckedCalculation,
+    assigned_values: &HashMap<Variable, Value>,
+) -> bool {
+    let mut is_marked: bool = false;
+    apply_on_values_mut(scopes, &mut |value| {
+        if is_marked {
+            return;
+        }
+        let resolved_value: Value = resolve_copies(value, assigned_values);
+        let is_inline_calculation: bool = matches!(
+            candidate,
+            CheckedCalculation::Inline(calculation) if &resolved_value == calculation
+        );
+        let Value::Calculation { opcode, args: _ } = value else {
+            return;
+        };
+        let Value::Calculation { opcode: _, args } = &resolved_value else {
+            return;
+        };
+        let is_matching: bool = match candidate {
+            CheckedCalculation::Operation {
+                opcode: checked_opcode,
+                args: checked_args,
+            } => {
+                let is_commutative: bool = matches!(checked_opcode, Opcode::ADD | Opcode::MUL);
+                opcode == checked_opcode
+                    && (args[..] == checked_args[..]
+                        || (is_commutative
+                            && args[0] == checked_args[1]
+                            && args[1] == checked_args[0]))
+            }
+            CheckedCalculation::Divisor(divisor) => *opcode == Opcode::DIV && &args[1] == divisor,
+            CheckedCalculation::Result(_) => false,
+            CheckedCalculation::Inline(_) => is_inline_calculation,
+        };
+        if is_matching {
+            *opcode = get_checked_opcode(*opcode);
+            is_marked = true;
+        }
+    });
+    return is_marked;
+}
+
+fn mark_checked_results(scopes: &mut Vec<VarScope>, checked_results: &HashSet<Variable>) {
+    for scope in scopes {
+        match scope {
+            Scope::Instructions(instructions) => {
+                for line in &mut instructions.lines {
+                    if let Line::Assignement {
+                        receiving_var: Some(receiving_var),
+                        assigned_value: Value::Calculation { opcode, args: _ },
+                    } = line
+                    {
+                        if checked_results.contains(receiving_var) {
+                            *opcode = get_checked_opcode(*opcode);
+                        }
+                    }
+                }
+            }
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => {
+                mark_checked_results(instructions_if_true, checked_results);
+                mark_checked_results(instructions_if_false, checked_results);
+            }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                mark_checked_results(header, checked_results);
+                mark_checked_results(body, checked_results);
+            }
+            _ => (),
+        }
+    }
+}
+
+fn get_checked_opcode(opcode: Opcode) -> Opcode {
+    match opcode {
+        Opcode::ADD => return Opcode::CHECKED_ADD,
+        Opcode::SUB => return Opcode::CHECKED_SUB,
+        Opcode::MUL => return Opcode::CHECKED_MUL,
+        Opcode::DIV => return Opcode::CHECKED_DIV,
+        _ => return opcode,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution_flow::with_variables::scopes_with_vars::InstructionsWithVars;
+
+    fn calculation(opcode: Opcode, args: &[Value]) -> Value {
+        return Value::Calculation {
+            opcode,
+            args: args.to_vec(),
+        };
+    }
+
+    #[test]
+    fn test_result_checks() {
+        // var_2 = var_0 + var_1
+        let [x, y, z, sum]: [Variable; 4] = [0, 1, 2, 3].map(|alias| Variable { alias });
+        let assigned_values: HashMap<Variable, Value> = HashMap::from([(
+            sum,
+            calculation(Opcode::ADD, &[Value::Existing(x), Value::Existing(y)]),
+        )]);
+        let is_result_candidate = |condition: &Value| -> bool {
+            return get_checked_calculations(
+                condition,
+                true,
+                ARITHMETIC_OVERFLOW,
+                &assigned_values,
+            )
+            .iter()
+            .any(|candidate| matches!(candidate, CheckedCalculation::Result(var) if *var == sum));
+        };
+
+        // `x > x + y`
+        assert!(is_result_candidate(&calculation(
+            Opcode::GT,
+            &[Value::Existing(x), Value::Existing(sum)]
+        )));
+        // `x + y < y`
+        assert!(is_result_candidate(&calculation(
+            Opcode::LT,
+            &[Value::Existing(sum), Value::Existing(y)]
+        )));
+        // `z > x + y` does not check the addition
+        assert!(!is_result_candidate(&calculation(
+            Opcode::GT,
+            &[Value::Existing(z), Value::Existing(sum)]
+        )));
+        // `x + y > x` neither
+        assert!(!is_result_candidate(&calculation(
+            Opcode::GT,
+            &[Value::Existing(sum), Value::Existing(x)]
+        )));
+    }
+
+    #[test]
+    fn test_inline_checks() {
+        // `if var_0 + 1 == 0 { panic }`
+        let [x, incremented]: [Variable; 2] = [0, 1].map(|alias| Variable { alias });
+        let increment: Value = calculation(
+            Opcode::ADD,
+            &[Value::Existing(x), Value::Bytes(U256::one())],
+        );
+        let candidate: CheckedCalculation = CheckedCalculation::Inline(increment.clone());
+        let scopes_with = |assigned_value: Value| -> Vec<VarScope> {
+            return vec![Scope::Instructions(InstructionsWithVars {
+                lines: vec![Line::Assignement {
+                    receiving_var: Some(incremented),
+                    assigned_value,
+                }],
+            })];
+        };
+
+        // the increment is computed again after the check
+        let mut scopes: Vec<VarScope> = scopes_with(increment.clone());
+        assert!(mark_checked_calculation(
+            &mut scopes,
+            &candidate,
+            &HashMap::new()
+        ));
+        assert_eq!(
+            scopes,
+            scopes_with(calculation(
+                Opcode::CHECKED_ADD,
+                &[Value::Existing(x), Value::Bytes(U256::one())]
+            ))
+        );
+
+        // it is not, the check is kept
+        let mut scopes: Vec<VarScope> = scopes_with(Value::Existing(x));
+        assert!(!mark_checked_calculation(
+            &mut scopes,
+            &candidate,
+            &HashMap::new()
+        ));
+        assert_eq!(scopes, scopes_with(Value::Existing(x)));
+    }
+}
@@ -0,0 +1,304 @@
+use super::{
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::{Line, Value},
+    requires::{negate, remove_double_negations},
+    scopes_with_vars::VarScope,
+};
+use crate::{error::PellucidError, execution_flow::execution_flow::Scope};
+
+impl ExecutionFlowWithVars {
+    // A branch ending the execution (or leaving a loop iteration) comes first, negating the
+    // condition if needed, and the other branch follows the condition instead of being nested in an
+    // `else`. When both branches end the execution, the shortest one comes first.
+    pub fn flatten_conditions(&mut self) -> Result<(), PellucidError> {
+        for function in self.functions.values_mut() {
+            flatten_conditions_in_scopes(&mut function.content)?;
+        }
+        return Ok(());
+    }
+}
+
+fn flatten_conditions_in_scopes(scopes: &mut Vec<VarScope>) -> Result<(), PellucidError> {
+    let mut scope_index: usize = 0;
+    while scope_index < scopes.len() {
+        if scope_index > 0 && scopes[scope_index - 1].should_be_followed_by_condition_scope() {
+            if let Some(hoisted_scopes) = flatten_condition(scopes, scope_index)? {
+                // the hoisted branch is visited next
+                scopes.splice(scope_index + 1..scope_index + 1, hoisted_scopes);
+            }
+        }
+        match &mut scopes[scope_index] {
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => {
+                flatten_conditions_in_scopes(instructions_if_true)?;
+                flatten_conditions_in_scopes(instructions_if_false)?;
+            }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header: _,
+                body,
+            } => flatten_conditions_in_scopes(body)?,
+            _ => (),
+        }
+        scope_index += 1;
+    }
+    return Ok(());
+}
+
+// Returns the `else` branch if it can follow the condition.
+fn flatten_condition(
+    scopes: &mut [VarScope],
+    scope_index: usize,
+) -> Result<Option<Vec<VarScope>>, PellucidError> {
+    let Scope::Condition {
+        instructions_if_true,
+        instructions_if_false,
+    } = &scopes[scope_index]
+    else {
+        return Ok(None);
+    };
+    let true_branch_terminates: bool = terminates(instructions_if_true);
+    let false_branch_terminates: bool = terminates(instructions_if_false);
+    let should_swap: bool = match (true_branch_terminates, false_branch_terminates) {
+        (true, true) => {
+            ExecutionFlowWithVars::compute_size_of_scopes(instructions_if_false)
+                < ExecutionFlowWithVars::compute_size_of_scopes(instructions_if_true)
+        }
+        (true, false) => false,
+        (false, true) => true,
+        // `if (x) {} else {...}` is `if (!x) {...}`
+        (false, false) => {
+            instructions_if_true.iter().all(|scope| scope.is_empty())
+                && !instructions_if_false.iter().all(|scope| scope.is_empty())
+        }
+    };
+
+    if should_swap {
+        let Scope::Instructions(instructions) = &mut scopes[scope_index - 1] else {
+            return Ok(None);
+        };
+        let Some(Line::If { condition }) = instructions.lines.last_mut() else {
+            return Err(PellucidError::ScopesMalformed(String::from(
+                "a condition scope does not follow an if line",
+            )));
+        };
+        *condition = remove_double_negations(negate(condition.clone()));
+        let Scope::Condition {
+            instructions_if_true,
+            instructions_if_false,
+        } = &mut scopes[scope_index]
+        else {
+            return Ok(None);
+        };
+        std::mem::swap(instructions_if_true, instructions_if_false);
+    }
+    if !true_branch_terminates && !false_branch_terminates {
+        return Ok(None);
+    }
+    let Scope::Condition {
+        instructions_if_true: _,
+        instructions_if_false,
+    } = &mut scopes[scope_index]
+    else {
+        return Ok(None);
+    };
+    return Ok(Some(std::mem::take(instructions_if_false)));
+}
+
+// True if the execution never goes past the end of the scopes.
+fn terminates(scopes: &[VarScope]) -> bool {
+    for scope in scopes.iter().rev() {
+        match scope {
+            Scope::Instructions(instructions) => {
+                match instructions
+                    .lines
+                    .iter()
+                    .rev()
+                    .find(|line| **line != Line::Empty)
+                {
+                    Some(line) => return is_terminating_line(line),
+                    None => continue,
+                }
+            }
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => return terminates(instructions_if_true) && terminates(instructions_if_false),
+            Scope::FunctionReturn(_)
+            | Scope::LoopContinue { label: _ }
+            | Scope::LoopBreak { label: _ }
+            | Scope::Panic => return true,
+            Scope::Empty => continue,
+            _ => return false,
+        }
+    }
+    return false;
+}
+
+fn is_terminating_line(line: &Line) -> bool {
+    let Line::Assignement {
+        receiving_var: None,
+        assigned_value,
+    } = line
+    else {
+        return false;
+    };
+    match assigned_value {
+        Value::Calculation { opcode, args: _ } => {
+            return opcode.is_exiting() || opcode.is_invalid()
+        }
+        Value::Revert {
+            error: _,
+            arguments: _,
+        } => return true,
+        _ => return false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitive_types::U256;
+
+    use super::*;
+    use crate::bytecode_reader::opcode::Opcode;
+    use crate::decompiler::Decompiler;
+    use crate::execution_flow::execution_flow::MAIN_FUNCTION_LABEL;
+    use crate::execution_flow::with_variables::scopes_with_vars::InstructionsWithVars;
+    use crate::tools::utils::read_file;
+
+    fn calculation(opcode: Opcode, args: &[u64]) -> Value {
+        return Value::Calculation {
+            opcode,
+            args: args
+                .iter()
+                .map(|arg| Value::Bytes(U256::from(*arg)))
+                .collect(),
+        };
+    }
+
+    fn instructions(lines: &[Line]) -> VarScope {
+        return Scope::Instructions(InstructionsWithVars {
+            lines: lines.to_vec(),
+        });
+    }
+
+    fn execute(assigned_value: Value) -> Line {
+        return Line::Assignement {
+            receiving_var: None,
+            assigned_value,
+        };
+    }
+
+    fn get_main_content(bytecode: &str) -> Vec<VarScope> {
+        let mut flow: ExecutionFlowWithVars = Decompiler::from_hex(bytecode)
+            .unwrap()
+            .decompile()
+            .unwrap()
+            .execution_flow_with_vars;
+        return flow.functions.remove(&MAIN_FUNCTION_LABEL).unwrap().content;
+    }
+
+    #[test]
+    fn test_flatten_conditions() {
+        // if calldata[0x00] { storage[0x00] = 0x01 } else { revert }
+        let condition: Value = calculation(Opcode::CALLDATALOAD, &[0x00]);
+        let store: Line = execute(calculation(Opcode::SSTORE, &[0x00, 0x01]));
+        let revert: Line = execute(calculation(Opcode::REVERT, &[0x00, 0x00]));
+        let mut scopes: Vec<VarScope> = vec![
+            instructions(&[Line::If {
+                condition: condition.clone(),
+            }]),
+            Scope::Condition {
+                instructions_if_true: vec![instructions(&[store.clone()])],
+                instructions_if_false: vec![instructions(&[revert.clone()])],
+            },
+        ];
+        flatten_conditions_in_scopes(&mut scopes).unwrap();
+        assert_eq!(
+            scopes,
+            vec![
+                instructions(&[Line::If {
+                    condition: negate(condition),
+                }]),
+                Scope::Condition {
+                    instructions_if_true: vec![instructions(&[revert.clone()])],
+                    instructions_if_false: Vec::new(),
+                },
+                instructions(&[store]),
+            ]
+        );
+
+        // the reverting branch comes first, the rest is not nested in an `else`
+        let scopes: Vec<VarScope> =
+            get_main_content(&read_file("./contracts/simple/contract_0/bytecode.txt"));
+        let check_index: usize = scopes
+            .iter()
+            .position(|scope| {
+                scope
+                    == &instructions(&[
+                        execute(calculation(Opcode::MSTORE, &[0x40, 0x80])),
+                        Line::Require {
+                            condition: negate(calculation(Opcode::CALLVALUE, &[])),
+                            error: None,
+                        },
+                        Line::If {
+                            condition: Value::Calculation {
+                                opcode: Opcode::LT,
+                                args: vec![
+                                    calculation(Opcode::CALLDATASIZE, &[]),
+                                    Value::Bytes(U256::from(0x04)),
+                                ],
+                            },
+                        },
+                    ])
+            })
+            .unwrap();
+        assert_eq!(
+            scopes[check_index + 1..check_index + 3],
+            [
+                Scope::Condition {
+                    instructions_if_true: vec![instructions(&[revert.clone()])],
+                    instructions_if_false: Vec::new(),
+                },
+                instructions(&[revert]),
+            ]
+        );
+
+        // if calldata[0x00] {...} else if calldata[0x20] {...} else {...}, nothing is flattened
+        let store_in_branch = |stored_value: u64| -> Vec<VarScope> {
+            return vec![instructions(&[execute(calculation(
+                Opcode::SSTORE,
+                &[0x00, stored_value],
+            ))])];
+        };
+        assert_eq!(
+            get_main_content(
+                "600035601d5760203560145760036000556023565b60026000556023565b60016000555b600160015500"
+            ),
+            vec![
+                instructions(&[Line::If {
+                    condition: calculation(Opcode::CALLDATALOAD, &[0x00]),
+                }]),
+                Scope::Condition {
+                    instructions_if_true: store_in_branch(0x01),
+                    instructions_if_false: vec![
+                        instructions(&[Line::If {
+                            condition: calculation(Opcode::CALLDATALOAD, &[0x20]),
+                        }]),
+                        Scope::Condition {
+                            instructions_if_true: store_in_branch(0x02),
+                            instructions_if_false: store_in_branch(0x03),
+                        },
+                    ],
+                },
+                instructions(&[
+                    execute(calculation(Opcode::SSTORE, &[0x01, 0x01])),
+                    execute(calculation(Opcode::STOP, &[])),
+                ]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::{HashMap, HashSet};
+
+use primitive_types::U256;
+
+use super::{
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::{Line, Value, Variable},
+    scopes_with_vars::{FunctionKind, FunctionWithVars, VarScope},
+};
+use crate::{
+    bytecode_reader::opcode::Opcode,
+    error::PellucidError,
+    execution_flow::execution_flow::{FunctionLabel, Scope, MAIN_FUNCTION_LABEL},
+    tools::utils::calculate_hash,
+};
+
+impl ExecutionFlowWithVars {
+    // Solidity and Vyper start with a dispatcher comparing the first 4 bytes of calldata to each
+    // selector. Every matching branch becomes its own external function, and what remains of main
+    // (no selector matched) is the fallback.
+    pub fn extract_external_functions(&mut self) -> Result<(), PellucidError> {
+        let Some(main_function) = self.functions.get_mut(&MAIN_FUNCTION_LABEL) else {
+            return Err(PellucidError::MissingMainFunction);
+        };
+        let selector_vars: HashSet<Variable> = find_selector_vars(&main_function.content);
+        let mut entry_points: Vec<(FunctionKind, Vec<VarScope>)> = Vec::new();
+        extract_entry_points_in_scopes(
+            &mut main_function.content,
+            &selector_vars,
+            &mut entry_points,
+        );
+        if entry_points.is_empty() {
+            return Ok(());
+        }
+        main_function.kind = FunctionKind::Fallback;
+
+        for (kind, content) in entry_points {
+            let label: FunctionLabel = calculate_hash(&kind);
+            // the same entry point can be reached through several paths of the dispatcher
+            if self.functions.contains_key(&label) {
+                continue;
+            }
+            self.functions.insert(
+                label,
+                FunctionWithVars {
+                    label,
+                    kind,
+                    signature: None,
+                    parameters: Vec::new(),
+                    var_types: HashMap::new(),
+                    input_vars: Vec::new(),
+                    n_outputs: 0,
+                    returns: false,
+                    content,
+                },
+            );
+        }
+        return Ok(());
+    }
+}
+
+fn extract_entry_points_in_scopes(
+    scopes: &mut Vec<VarScope>,
+    selector_vars: &HashSet<Variable>,
+    entry_points: &mut Vec<(FunctionKind, Vec<VarScope>)>,
+) {
+    let mut scope_index: usize = 0;
+    while scope_index < scopes.len() {
+        let condition: Option<Value> = get_condition_of_scope(scopes, scope_index);
+        if let (
+            Some(condition),
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            },
+        ) = (condition, &mut scopes[scope_index])
+        {
+            let remaining: Option<Vec<VarScope>> = if let Some((kind, is_taken_if_true)) =
+                get_entry_point(&condition, selector_vars)
+            {
+                let (entry_point, remaining) = if is_taken_if_true {
+                    (instructions_if_true, instructions_if_false)
+                } else {
+                    (instructions_if_false, instructions_if_true)
+                };
+                entry_points.push((kind, entry_point.clone()));
+                Some(remaining.clone())
+            } else {
+                extract_entry_points_in_scopes(instructions_if_true, selector_vars, entry_points);
+                extract_entry_points_in_scopes(instructions_if_false, selector_vars, entry_points);
+                // binary search splits (selector > pivot) end the same way once emptied
+                if uses_selector(&condition, selector_vars)
+                    && instructions_if_true == instructions_if_false
+                {
+                    Some(instructions_if_true.clone())
+                } else {
+                    None
+                }
+            };
+
+            if let Some(remaining) = remaining {
+                // the condition is resolved: the `if` line disappears with its branches
+                scopes.splice(scope_index..scope_index + 1, remaining);
+                if let Scope::Instructions(instructions) = &mut scopes[scope_index - 1] {
+                    instructions.lines.pop();
+                    if instructions.lines.is_empty() {
+                        scopes.remove(scope_index - 1);
+                        scope_index -= 1;
+                    }
+                }
+                continue; // the spliced scopes may hold the next comparison
+            }
+        }
+        scope_index += 1;
+    }
+}
+
+// The condition of a `Scope::Condition` is the last line of the instructions before it.
+fn get_condition_of_scope(scopes: &[VarScope], scope_index: usize) -> Option<Value> {
+    if scope_index == 0 || !scopes[scope_index - 1].should_be_followed_by_condition_scope() {
+        return None;
+    }
+    if let Scope::Instructions(instructions) = &scopes[scope_index - 1] {
+        if let Some(Line::If { condition }) = instructions.lines.last() {
+            return Some(condition.clone());
+        }
+    }
+    return None;
+}
+
+// The entry point a condition leads to, and whether it is taken when the condition is true.
+fn get_entry_point(
+    condition: &Value,
+    selector_vars: &HashSet<Variable>,
+) -> Option<(FunctionKind, bool)> {
+    match condition {
+        Value::Calculation {
+            opcode: Opcode::ISZERO,
+            args,
+        } => {
+            if let Value::Calculation {
+                opcode: Opcode::CALLDATASIZE,
+                args: _,
+            } = &args[0]
+            {
+                return Some((FunctionKind::Receive, true));
+            }
+            let (kind, is_taken_if_true) = get_entry_point(&args[0], selector_vars)?;
+            return Some((kind, !is_taken_if_true));
+        }
+        Value::Calculation {
+            opcode: Opcode::EQ,
+            args,
+        } => {
+            let selector: u32 = get_compared_selector(args, selector_vars)?;
+            return Some((FunctionKind::External { selector }, true));
+        }
+        // Vyper jumps over the function when `selector ^ calldata_selector` (or the difference)
+        // is not zero
+        Value::Calculation {
+            opcode: Opcode::XOR | Opcode::SUB,
+            args,
+        } => {
+            let selector: u32 = get_compared_selector(args, selector_vars)?;
+            return Some((FunctionKind::External { selector }, false));
+        }
+        _ => return None,
+    }
+}
+
+// The selector constant compared to the first 4 bytes of calldata.
+fn get_compared_selector(args: &[Value], selector_vars: &HashSet<Variable>) -> Option<u32> {
+    for (selector, compared) in [(&args[0], &args[1]), (&args[1], &args[0])] {
+        if let Value::Bytes(selector) = selector {
+            if *selector <= U256::from(u32::MAX) && is_selector_expression(compared, selector_vars)
+            {
+                return Some(selector.as_u32());
+            }
+        }
+    }
+    return None;
+}
+
+fn uses_selector(value: &Value, selector_vars: &HashSet<Variable>) -> bool {
+    if is_selector_expression(value, selector_vars) {
+        return true;
+    }
+    if let Value::Calculation { opcode: _, args } = value {
+        return args.iter().any(|arg| uses_selector(arg, selector_vars));
+    }
+    return false;
+}
+
+// `calldata[0] >> 0xe0`, `calldata[0] / 2**224`, optionally masked with 0xffffffff
+fn is_selector_expression(value: &Value, selector_vars: &HashSet<Variable>) -> bool {
+    let is_first_calldata_word = |value: &Value| -> bool {
+        return *value
+            == Value::Calculation {
+                opcode: Opcode::CALLDATALOAD,
+                args: vec![Value::Bytes(U256::zero())],
+            };
+    };
+    match value {
+        Value::Existing(var) => return selector_vars.contains(var),
+        Value::Calculation {
+            opcode: Opcode::SHR,
+            args,
+        } => return args[0] == Value::Bytes(U256::from(0xe0)) && is_first_calldata_word(&args[1]),
+        Value::Calculation {
+            opcode: Opcode::DIV,
+            args,
+        } => {
+            return is_first_calldata_word(&args[0]) && args[1] == Value::Bytes(U256::one() << 224);
+        }
+        Value::Calculation {
+            opcode: Opcode::AND,
+            args,
+        } => {
+            let mask: Value = Value::Bytes(U256::from(u32::MAX));
+            return (args[0] == mask && is_selector_expression(&args[1], selector_vars))
+                || (args[1] == mask && is_selector_expression(&args[0], selector_vars));
+        }
+        _ => return false,
+    }
+}
+
+// Variables holding the selector, when it is used by several comparisons.
+fn find_selector_vars(scopes: &Vec<VarScope>) -> HashSet<Variable> {
+    let mut selector_vars: HashSet<Variable> = HashSet::new();
+    loop {
+        let n_selector_vars: usize = selector_vars.len();
+        let mut new_selector_vars: HashSet<Variable> = HashSet::new();
+        ExecutionFlowWithVars::apply_on_scopes(scopes, &mut |scope: &VarScope| {
+            if let Scope::Instructions(instructions) = scope {
+                for line in &instructions.lines {
+                    if let Line::Assignement {
+                        receiving_var: Some(receiving_var),
+                        assigned_value,
+                    } = line
+                    {
+                        if is_selector_expression(assigned_value, &selector_vars) {
+                            new_selector_vars.insert(*receiving_var);
+                        }
+                    }
+                }
+            }
+        });
+        selector_vars.extend(new_selector_vars);
+        if selector_vars.len() == n_selector_vars {
+            return selector_vars;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompiler::Decompiler;
+    use crate::tools::utils::read_file;
+
+    fn calculation(opcode: Opcode, args: &[Value]) -> Value {
+        return Value::Calculation {
+            opcode,
+            args: args.to_vec(),
+        };
+    }
+
+    fn get_function_kinds(bytecode: &str) -> HashSet<FunctionKind> {
+        let decompiler: Decompiler = Decompiler::from_hex(bytecode).unwrap();
+        return decompiler
+            .decompile()
+            .unwrap()
+            .execution_flow_with_vars
+            .functions
+            .values()
+            .map(|function| function.kind)
+            .collect();
+    }
+
+    #[test]
+    fn test_get_entry_point() {
+        let selector_vars: HashSet<Variable> = HashSet::new();
+        let selector: Value = Value::Bytes(U256::from(0x11111111));
+        let calldata_selector: Value = calculation(
+            Opcode::SHR,
+            &[
+                Value::Bytes(U256::from(0xe0)),
+                calculation(Opcode::CALLDATALOAD, &[Value::Bytes(U256::zero())]),
+            ],
+        );
+        let kind: FunctionKind = FunctionKind::External {
+            selector: 0x11111111,
+        };
+
+        let equality: Value =
+            calculation(Opcode::EQ, &[selector.clone(), calldata_selector.clone()]);
+        assert_eq!(
+            get_entry_point(&equality, &selector_vars),
+            Some((kind, true))
+        );
+        assert_eq!(
+            get_entry_point(&calculation(Opcode::ISZERO, &[equality]), &selector_vars),
+            Some((kind, false))
+        );
+        // Vyper skips the function when the selectors differ
+        for opcode in [Opcode::XOR, Opcode::SUB] {
+            let difference: Value =
+                calculation(opcode, &[calldata_selector.clone(), selector.clone()]);
+            assert_eq!(
+                get_entry_point(&difference, &selector_vars),
+                Some((kind, false))
+            );
+        }
+        assert_eq!(
+            get_entry_point(
+                &calculation(Opcode::ISZERO, &[calculation(Opcode::CALLDATASIZE, &[])]),
+                &selector_vars
+            ),
+            Some((FunctionKind::Receive, true))
+        );
+        // calldata[4] is not the selector
+        let other_word: Value = calculation(Opcode::CALLDATALOAD, &[Value::Bytes(U256::from(4))]);
+        assert_eq!(
+            get_entry_point(
+                &calculation(Opcode::EQ, &[selector, other_word]),
+                &selector_vars
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extract_external_functions() {
+        let kinds: HashSet<FunctionKind> =
+            get_function_kinds(&read_file("./contracts/simple/contract_0/bytecode.txt"));
+        for selector in [0x26121ff0, 0xb28175c4, 0xe2179b8e] {
+            assert!(kinds.contains(&FunctionKind::External { selector }));
+        }
+        assert!(kinds.contains(&FunctionKind::Fallback));
+        assert!(!kinds.contains(&FunctionKind::Main));
+
+        // Vyper: `if (calldata[0] >> 0xe0) ^ 0x11111111 { stop } storage[0] = 1`
+        let kinds: HashSet<FunctionKind> =
+            get_function_kinds("60003560e01c631111111181186016576001600055005b00");
+        assert!(kinds.contains(&FunctionKind::External {
+            selector: 0x11111111
+        }));
+        assert!(kinds.contains(&FunctionKind::Fallback));
+    }
+}
@@ -1,60 +1,153 @@
 use itertools::Itertools;
+use primitive_types::U256;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::{
-    bytecode_reader::opcode::calculation_to_str,
-    execution_flow::execution_flow::{AccessContent, GetFunctionLabel, Scope},
+    bytecode_reader::opcode::{calculation_to_str, Opcode},
+    execution_flow::execution_flow::{GetFunctionLabel, LoopKind, Scope},
+    signatures::signature_database::split_signature,
     tools::utils::{concat_to_str, shift_text, u256_to_hex},
 };
 
 use super::{
+    events::{event_name, EventDescription},
     flow_with_vars::ExecutionFlowWithVars,
-    incorporate_variables::{Line, Value, Variable, VariablesStack},
-    scopes_with_vars::VarScope,
+    incorporate_variables::{
+        Line, ParameterField, RevertError, StorageKind, Value, Variable, VariablesStack,
+    },
+    scopes_with_vars::{FunctionKind, FunctionWithVars, VarScope},
+    type_inference::VarType,
 };
 
 impl ToString for ExecutionFlowWithVars {
     fn to_string(&self) -> String {
         let mut res: String = String::new();
+        let storage_variables: BTreeMap<U256, (StorageKind, usize)> = self.get_storage_variables();
+        if !storage_variables.is_empty() {
+            res += "// storage layout\n";
+            for (slot, (kind, n_keys)) in storage_variables {
+                res += &format!(
+                    "// slot {}: {} ({})\n",
+                    u256_to_hex(slot),
+                    storage_variable_name(slot, kind),
+                    storage_variable_description(kind, n_keys)
+                );
+            }
+            res += "\n";
+        }
+        let events: Vec<EventDescription> = self.get_events();
+        if !events.is_empty() {
+            res += "// events\n";
+            for event in events {
+                res += &format!("// {}\n", event_description(&event));
+            }
+            res += "\n";
+        }
+        res += &self
+            .functions
+            .values()
+            .sorted_by_key(|function| (function.kind, function.label))
+            .map(|function| function.to_string())
+            .join("\n\n");
+        return res;
+    }
+}
+
+impl ToString for FunctionWithVars {
+    fn to_string(&self) -> String {
+        // typed variables are declared at their first assignment
+        let mut undeclared_vars: HashMap<Variable, VarType> = self.var_types.clone();
+        let mut res: String = String::new();
+        match self.kind {
+            FunctionKind::Main | FunctionKind::Receive | FunctionKind::Fallback => {
+                res += &format!("function {}() external", self.get_name())
+            }
+            FunctionKind::External { selector: _ } => {
+                let parameters: Vec<String> = self
+                    .parameters
+                    .iter()
+                    .enumerate()
+                    .map(|(index, type_)| format!("{} arg{}", type_, index))
+                    .collect();
+                res += &format!(
+                    "function {}({}) external",
+                    self.get_name(),
+                    parameters.join(", ")
+                );
+            }
+            FunctionKind::JumpTarget { pc: _ } | FunctionKind::Internal => {
+                res += &format!("function {}(", self.get_name());
+                res += &self
+                    .input_vars
+                    .iter()
+                    .map(|input_var| declaration_to_string(input_var, &mut undeclared_vars))
+                    .join(", ");
+                res += ") internal";
+                if self.n_outputs > 0 {
+                    res += " returns(";
+                    res += &concat_to_str(
+                        &(0..self.n_outputs)
+                            .map(|index| format!("r{}", index))
+                            .collect_vec(),
+                        ", ",
+                    );
+                    res += ")";
+                }
+            }
+        }
         res += &format!(
-            "function main() external {{\n{}}}",
+            " {{\n{}}}",
             shift_text(&scopes_with_var_to_string(
-                self.get_main_function().get_content().as_slice()
+                &self.content,
+                &mut undeclared_vars
             ))
         );
+        return res;
+    }
+}
 
-        for function in self.get_secondary_functions() {
-            res += &format!("\n\nfunction fn_{}(", function.get_label());
-            res += &concat_to_str(&function.input_vars, ", ");
-            res += ") internal";
-            if function.n_outputs > 0 {
-                res += " returns(";
-                res += &concat_to_str(
-                    &(0..function.n_outputs)
-                        .map(|index| format!("r{}", index))
-                        .collect_vec(),
-                    ", ",
-                );
-                res += ")";
-            }
-            res += " {\n";
-            res += &shift_text(&scopes_with_var_to_string(&function.content));
-            res += "}";
+impl FunctionWithVars {
+    pub fn get_name(&self) -> String {
+        match self.kind {
+            FunctionKind::Main => return "main".to_owned(),
+            FunctionKind::External { selector } => match &self.signature {
+                Some(signature) => return split_signature(signature).0.to_owned(),
+                None => return format!("selector_0x{:08x}", selector),
+            },
+            FunctionKind::Receive => return "receive".to_owned(),
+            FunctionKind::Fallback => return "fallback".to_owned(),
+            FunctionKind::JumpTarget { pc } => return format!("jumpdest_0x{:04x}", pc),
+            FunctionKind::Internal => return format!("fn_{}", self.get_label()),
         }
+    }
+}
 
-        return res;
+fn declaration_to_string(
+    var: &Variable,
+    undeclared_vars: &mut HashMap<Variable, VarType>,
+) -> String {
+    match undeclared_vars.remove(var) {
+        Some(var_type) => return format!("{} {}", var_type.to_string(), var.to_string()),
+        None => return var.to_string(),
     }
 }
 
-fn scopes_with_var_to_string(scopes: &[VarScope]) -> String {
+fn scopes_with_var_to_string(
+    scopes: &[VarScope],
+    undeclared_vars: &mut HashMap<Variable, VarType>,
+) -> String {
     let mut res: String = String::new();
     for (scope_index, scope) in scopes.iter().enumerate() {
         if let Scope::Loop { label } = scope {
             res += &format!("begin loop_{} {{\n", label);
-            res += &shift_text(&scopes_with_var_to_string(&scopes[scope_index + 1..]));
+            res += &shift_text(&scopes_with_var_to_string(
+                &scopes[scope_index + 1..],
+                undeclared_vars,
+            ));
             res += "}";
             return res;
         } else {
-            res += &scope.to_string();
+            res += &scope_with_vars_to_string(scope, undeclared_vars);
             if scope_index != scopes.len() - 1 && !scope.should_be_followed_by_condition_scope() {
                 res += "\n";
             }
@@ -71,84 +164,210 @@ impl ToString for Variable {
 
 impl ToString for Line {
     fn to_string(&self) -> String {
-        match self {
-            Line::Assignement {
-                receiving_var,
-                assigned_value: assigned,
-            } => {
-                let mut res: String = String::new();
-                if let Some(_receiving_var) = receiving_var {
-                    res += &format!("{} = ", _receiving_var.to_string());
-                }
-                res += &assigned.to_string();
-                return res;
+        return line_to_string(self, &mut HashMap::new());
+    }
+}
+
+fn line_to_string(line: &Line, undeclared_vars: &mut HashMap<Variable, VarType>) -> String {
+    match line {
+        Line::Assignement {
+            receiving_var,
+            assigned_value: assigned,
+        } => {
+            let mut res: String = String::new();
+            if let Some(_receiving_var) = receiving_var {
+                res += &format!(
+                    "{} = ",
+                    declaration_to_string(_receiving_var, undeclared_vars)
+                );
             }
-            Line::If { condition } => format!("if {}", condition.to_string()),
-            Line::Empty => "".to_owned(),
+            res += &assigned.to_string();
+            return res;
         }
+        Line::If { condition } => format!("if {}", condition.to_string()),
+        Line::DynamicJump {
+            condition,
+            destination,
+        } => {
+            let mut res: String = String::new();
+            if let Some(_condition) = condition {
+                res += &format!("if {} ", _condition.to_string());
+            }
+            res += &format!("goto dynamic({})", destination.to_string());
+            return res;
+        }
+        Line::Require { condition, error } => match error {
+            Some(_error) => format!(
+                "require({}, {})",
+                condition.to_string(),
+                error_to_string(_error)
+            ),
+            None => format!("require({})", condition.to_string()),
+        },
+        Line::Empty => "".to_owned(),
     }
 }
+
 impl ToString for VarScope {
     fn to_string(&self) -> String {
-        match self {
-            Scope::Instructions(instructions_with_vars) => {
-                return concat_to_str(&instructions_with_vars.lines, "\n");
-            }
-            Scope::FunctionCall(function_call_with_vars) => {
-                let mut res: String = String::new();
-                if function_call_with_vars.results.len() > 0 {
-                    if function_call_with_vars.results.len() > 1 {
-                        res += "(";
-                    }
-                    res += &concat_to_str(&function_call_with_vars.results, ", ");
-                    if function_call_with_vars.results.len() > 1 {
-                        res += ")";
+        return scope_with_vars_to_string(self, &mut HashMap::new());
+    }
+}
+
+fn scope_with_vars_to_string(
+    scope: &VarScope,
+    undeclared_vars: &mut HashMap<Variable, VarType>,
+) -> String {
+    match scope {
+        Scope::Instructions(instructions_with_vars) => {
+            return instructions_with_vars
+                .lines
+                .iter()
+                .map(|line| line_to_string(line, undeclared_vars))
+                .join("\n");
+        }
+        Scope::FunctionCall(function_call_with_vars) => {
+            let mut res: String = String::new();
+            if function_call_with_vars.results.len() > 0 {
+                if function_call_with_vars.results.len() > 1 {
+                    res += "(";
+                }
+                res += &concat_to_str(&function_call_with_vars.results, ", ");
+                if function_call_with_vars.results.len() > 1 {
+                    res += ")";
+                }
+                res += " = ";
+            }
+            res += &format!("fn_{}(", function_call_with_vars.get_label());
+            res += &concat_to_str(&function_call_with_vars.arguments, ", ");
+            res += ")";
+            return res;
+        }
+        Scope::FunctionReturn(function_return_with_vars) => {
+            if function_return_with_vars.returned_values.is_empty() {
+                return format!(
+                    "// end of function {}",
+                    function_return_with_vars.get_label()
+                );
+            }
+            let mut res: String = String::new();
+            res += "(";
+            res += &concat_to_str(
+                &(0..function_return_with_vars.returned_values.len())
+                    .map(|index| format!("r{}", index))
+                    .collect_vec(),
+                ", ",
+            );
+            res += ") = (";
+            res += &concat_to_str(&function_return_with_vars.returned_values, ", ");
+            res += ")";
+
+            return res;
+        }
+        Scope::Loop { label } => return format!("begin loop_{}", label),
+        Scope::LoopContinue { label } => return format!("continue loop_{}", label),
+        Scope::LoopBreak { label } => return format!("break loop_{}", label),
+        Scope::StructuredLoop {
+            label,
+            kind,
+            header,
+            body,
+        } => {
+            // the initialization of `for` loops comes first, where its variable is declared
+            let mut initialization: Vec<String> = Vec::new();
+            let mut condition: Option<&Value> = None;
+            let mut increment: Vec<String> = Vec::new();
+            for header_scope in header {
+                let Scope::Instructions(instructions) = header_scope else {
+                    increment.push(scope_with_vars_to_string(header_scope, undeclared_vars));
+                    continue;
+                };
+                for line in &instructions.lines {
+                    match (line, condition) {
+                        (
+                            Line::If {
+                                condition: loop_condition,
+                            },
+                            _,
+                        ) => condition = Some(loop_condition),
+                        (_, None) => initialization.push(line_to_string(line, undeclared_vars)),
+                        (_, Some(_)) => increment.push(line_to_string(line, undeclared_vars)),
                     }
-                    res += " = ";
                 }
-                res += &format!("fn_{}(", function_call_with_vars.get_label());
-                res += &concat_to_str(&function_call_with_vars.arguments, ", ");
-                res += ")";
-                return res;
             }
-            Scope::FunctionReturn(function_return_with_vars) => {
-                if function_return_with_vars.returned_values.is_empty() {
+            let body: String = if body.iter().all(|scope| scope.is_empty()) {
+                String::new()
+            } else {
+                shift_text(&scopes_with_var_to_string(body, undeclared_vars))
+            };
+            let Some(condition) = condition else {
+                return format!("loop_{}: while (true) {{\n{}}}", label, body);
+            };
+            match kind {
+                LoopKind::While => {
                     return format!(
-                        "// end of function {}",
-                        function_return_with_vars.get_label()
-                    );
+                        "loop_{}: while ({}) {{\n{}}}",
+                        label,
+                        condition.to_string(),
+                        body
+                    )
+                }
+                LoopKind::DoWhile => {
+                    return format!(
+                        "loop_{}: do {{\n{}}} while ({})",
+                        label,
+                        body,
+                        condition.to_string()
+                    )
+                }
+                LoopKind::For => {
+                    return format!(
+                        "loop_{}: for ({}; {}; {}) {{\n{}}}",
+                        label,
+                        initialization.join(", "),
+                        condition.to_string(),
+                        increment.join(", "),
+                        body
+                    )
                 }
-                let mut res: String = String::new();
-                res += "(";
-                res += &concat_to_str(
-                    &(0..function_return_with_vars.returned_values.len())
-                        .map(|index| format!("r{}", index))
-                        .collect_vec(),
-                    ", ",
-                );
-                res += ") = (";
-                res += &concat_to_str(&function_return_with_vars.returned_values, ", ");
-                res += ")";
-
-                return res;
             }
-            Scope::Loop { label } => return format!("begin loop_{}", label),
-            Scope::LoopContinue { label } => return format!("continue loop_{}", label),
-            Scope::Condition {
+        }
+        Scope::Condition {
+            instructions_if_true,
+            instructions_if_false,
+        } => {
+            let mut res: String = String::new();
+            res += " {\n";
+            res += &shift_text(&scopes_with_var_to_string(
                 instructions_if_true,
-                instructions_if_false,
-            } => {
-                let mut res: String = String::new();
-                res += " {\n";
-                res += &shift_text(&scopes_with_var_to_string(instructions_if_true));
-                res += "}\nelse {\n";
-                res += &shift_text(&scopes_with_var_to_string(instructions_if_false));
-                res += "}";
+                undeclared_vars,
+            ));
+            res += "}";
+            // conditional exits of loops have no else branch
+            if instructions_if_false.is_empty() {
                 return res;
             }
-            Scope::Panic => return "// Panic".to_owned(),
-            Scope::Empty => return "".to_owned(),
+            // `else { if (y) {...} }` is `else if (y) {...}`
+            if let [Scope::Instructions(instructions), nested_condition] =
+                instructions_if_false.as_slice()
+            {
+                if let [Line::If { condition: _ }] = instructions.lines.as_slice() {
+                    res += "\nelse ";
+                    res += &line_to_string(&instructions.lines[0], undeclared_vars);
+                    res += &scope_with_vars_to_string(nested_condition, undeclared_vars);
+                    return res;
+                }
+            }
+            res += "\nelse {\n";
+            res += &shift_text(&scopes_with_var_to_string(
+                instructions_if_false,
+                undeclared_vars,
+            ));
+            res += "}";
+            return res;
         }
+        Scope::Panic => return "// Panic".to_owned(),
+        Scope::Empty => return "".to_owned(),
     }
 }
 
@@ -161,8 +380,57 @@ impl ToString for VariablesStack {
     }
 }
 
+pub fn storage_variable_name(slot: U256, kind: StorageKind) -> String {
+    match kind {
+        StorageKind::Mapping => return format!("mapping_{}", slot),
+        StorageKind::Array => return format!("array_{}", slot),
+    }
+}
+
+pub fn storage_variable_description(kind: StorageKind, n_keys: usize) -> String {
+    match kind {
+        StorageKind::Mapping if n_keys == 1 => return "mapping, 1 key".to_owned(),
+        StorageKind::Mapping => return format!("mapping, {} keys", n_keys),
+        StorageKind::Array if n_keys <= 1 => return "dynamic array".to_owned(),
+        StorageKind::Array => return format!("dynamic array, {} dimensions", n_keys),
+    }
+}
+
+fn event_description(event: &EventDescription) -> String {
+    let mut res: String = match &event.signature {
+        Some(signature) => signature.clone(),
+        None => event.name.clone(),
+    };
+    res += &match event.n_indexed {
+        1 => ": 1 indexed topic".to_owned(),
+        n_indexed => format!(": {} indexed topics", n_indexed),
+    };
+    res += &match event.n_data_words {
+        Some(1) => ", 1 data word".to_owned(),
+        Some(n_data_words) => format!(", {} data words", n_data_words),
+        None => ", undecoded data".to_owned(),
+    };
+    if !event.emitted_by.is_empty() {
+        res += &format!(", emitted by {}", event.emitted_by.join(", "));
+    }
+    return res;
+}
+
 fn value_to_string(value: &Value, is_nested: bool) -> String {
     match value {
+        // recognised storage variables are accessed by name
+        Value::Calculation {
+            opcode: Opcode::SLOAD,
+            args,
+        } if matches!(args[0], Value::StorageSlot { slot: _, keys: _ }) => {
+            return args[0].to_string();
+        }
+        Value::Calculation {
+            opcode: Opcode::SSTORE,
+            args,
+        } if matches!(args[0], Value::StorageSlot { slot: _, keys: _ }) => {
+            return format!("{} = {}", args[0].to_string(), args[1].to_string());
+        }
         Value::Calculation { opcode, args } => {
             let mut res: String =
                 calculation_to_str(*opcode, |arg_index: usize, is_nested: bool| {
@@ -176,6 +444,13 @@ fn value_to_string(value: &Value, is_nested: bool) -> String {
         }
         Value::Existing(existing_var) => return existing_var.to_string(),
         Value::Bytes(bytes) => return u256_to_hex(*bytes),
+        Value::Named { bytes: _, name } => return name.clone(),
+        Value::Parameter { index, field } => match field {
+            ParameterField::Word => return format!("arg{}", index),
+            ParameterField::Offset => return format!("arg{}.offset", index),
+            ParameterField::Length => return format!("arg{}.length", index),
+            ParameterField::Element(word) => return format!("arg{}[{}]", index, word),
+        },
         Value::FunctionReturnedValue {
             label,
             arguments,
@@ -188,9 +463,88 @@ fn value_to_string(value: &Value, is_nested: bool) -> String {
             res += &format!(".{}", return_index);
             return res;
         }
+        Value::StorageSlot { slot, keys } => match keys.first() {
+            Some((kind, _)) => {
+                let mut res: String = storage_variable_name(*slot, *kind);
+                for (_, key) in keys {
+                    res += &format!("[{}]", key.to_string());
+                }
+                return res;
+            }
+            None => {
+                return format!(
+                    "length({})",
+                    storage_variable_name(*slot, StorageKind::Array)
+                )
+            }
+        },
+        Value::Revert {
+            error,
+            arguments: _,
+        } => match error {
+            RevertError::Message(_) => return format!("revert({})", error_to_string(value)),
+            RevertError::Panic(code) => return format!("panic({})", panic_code_to_str(*code)),
+            RevertError::Custom {
+                selector: _,
+                name: _,
+            } => return format!("revert {}", error_to_string(value)),
+        },
+        Value::Event { topics, data } => {
+            let arguments: Vec<String> = topics
+                .iter()
+                .skip(1)
+                .map(|topic| format!("indexed {}", topic.to_string()))
+                .chain(data.iter().map(|word| word.to_string()))
+                .collect();
+            return format!(
+                "emit {}({})",
+                event_name(topics.first()),
+                arguments.join(", ")
+            );
+        }
+    }
+}
+
+// The message or the custom error of a revert, as given to `revert` and `require`.
+fn error_to_string(value: &Value) -> String {
+    match value {
+        Value::Revert {
+            error: RevertError::Message(message),
+            arguments: _,
+        } => return format!("{:?}", message),
+        Value::Revert {
+            error: RevertError::Custom { selector, name },
+            arguments,
+        } => {
+            let name: String = match name {
+                Some(name) => name.clone(),
+                None => format!("CustomError_0x{:08x}", selector),
+            };
+            return format!("{}({})", name, concat_to_str(arguments, ", "));
+        }
+        _ => return value.to_string(),
     }
 }
 
+// Codes of the panics raised by Solidity.
+fn panic_code_to_str(code: U256) -> String {
+    let name: &str = match code.low_u64() {
+        _ if code > U256::from(u64::MAX) => return u256_to_hex(code),
+        0x00 => "GENERIC",
+        0x01 => "ASSERTION_FAILED",
+        0x11 => "ARITHMETIC_OVERFLOW",
+        0x12 => "DIVISION_BY_ZERO",
+        0x21 => "INVALID_ENUM_VALUE",
+        0x22 => "INVALID_STORAGE_BYTE_ARRAY",
+        0x31 => "EMPTY_ARRAY_POP",
+        0x32 => "ARRAY_OUT_OF_BOUNDS",
+        0x41 => "OUT_OF_MEMORY",
+        0x51 => "INVALID_INTERNAL_FUNCTION",
+        _ => return u256_to_hex(code),
+    };
+    return name.to_owned();
+}
+
 impl ToString for Value {
     fn to_string(&self) -> String {
         return value_to_string(self, false);
@@ -202,6 +556,7 @@ mod tests {
     use super::*;
     use crate::{
         bytecode_reader::bytecode::Bytecode,
+        bytecode_reader::hardfork::Hardfork,
         create_graph::graph::Graph,
         detect_cycles::acyclic_graph::AcyclicGraph,
         execution_flow::{
@@ -220,14 +575,14 @@ mod tests {
         //let path: String = String::from("./contracts/complex/bytecode.txt");
         println!("Loading {}", path);
         let bytecode_string: String = fs::read_to_string(path).expect("Unable to read file.");
-        let bytecode: Bytecode = Bytecode::from(&bytecode_string).unwrap();
-        let mut graph: Graph = Graph::from(&bytecode);
-        let mut a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph);
-        let skeleton: Skeleton = Skeleton::build(&mut a_graph);
+        let bytecode: Bytecode = Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
+        let mut graph: Graph = Graph::from(&bytecode).unwrap();
+        let mut a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph).unwrap();
+        let skeleton: Skeleton = Skeleton::build(&mut a_graph).unwrap();
         let execution_flow_with_opcodes: ExecutionFlowWithOpcodes =
-            convert_skeleton_to_execution_flow(&skeleton);
+            convert_skeleton_to_execution_flow(&skeleton).unwrap();
         let execution_flow_with_vars: ExecutionFlowWithVars =
-            convert_opcode_flow_to_var_flow(&execution_flow_with_opcodes);
+            convert_opcode_flow_to_var_flow(&execution_flow_with_opcodes).unwrap();
         crate::tools::utils::write_file("decompiled.txt", &execution_flow_with_vars.to_string());
         let _ = execution_flow_with_vars;
     }
@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use primitive_types::U256;
+use serde::Serialize;
+
+use super::{
+    abi_parameters::apply_on_values,
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::Value,
+    revert_reasons::{
+        decode_memory_data_in_scopes, get_data_size, read_words, resolve_address, Memory,
+        MemoryDecoding,
+    },
+    scopes_with_vars::FunctionWithVars,
+    storage_variables::LineId,
+};
+use crate::{
+    bytecode_reader::opcode::Opcode, execution_flow::execution_flow::FunctionLabel,
+    signatures::signature_database::split_signature,
+};
+
+// An event is identified by its first topic, the hash of its signature, unless it is anonymous.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct EventDescription {
+    pub topic: Option<U256>,
+    pub name: String,
+    pub signature: Option<String>,   // from the signature database
+    pub n_indexed: usize,            // topics following the first one
+    pub n_data_words: Option<usize>, // None if the data is not decoded
+    pub emitted_by: Vec<String>,
+}
+
+impl ExecutionFlowWithVars {
+    // The data of an event is a list of words, written in memory just before logging it like the
+    // data of a revert.
+    pub fn decode_events(&mut self) {
+        for function in self.functions.values_mut() {
+            decode_memory_data_in_scopes(&mut function.content, decode_event);
+        }
+    }
+
+    // Events emitted by the code, the ones of internal functions are attributed to the entry
+    // points calling them.
+    pub fn get_events(&self) -> Vec<EventDescription> {
+        let emitted_events: HashMap<FunctionLabel, Vec<EventDescription>> = self
+            .functions
+            .iter()
+            .map(|(label, function)| (*label, get_emitted_events(function)))
+            .collect();
+
+        let mut events: BTreeMap<(Option<U256>, usize), EventDescription> = BTreeMap::new();
+        for event in emitted_events.values().flatten() {
+            let known_event: &mut EventDescription = events
+                .entry((event.topic, event.n_indexed))
+                .or_insert_with(|| event.clone());
+            // the data of the event may only be decoded where it is emitted directly
+            if known_event.n_data_words.is_none() {
+                known_event.n_data_words = event.n_data_words;
+            }
+        }
+
+        let mut emitted_by: HashMap<(Option<U256>, usize), BTreeSet<String>> = HashMap::new();
+        for entry_point in self.functions.values() {
+            if !entry_point.is_entry_point() {
+                continue;
+            }
+            for label in self.get_reachable_functions(entry_point.label) {
+                for event in &emitted_events[&label] {
+                    emitted_by
+                        .entry((event.topic, event.n_indexed))
+                        .or_default()
+                        .insert(entry_point.get_name());
+                }
+            }
+        }
+        return events
+            .into_iter()
+            .map(|(key, mut event)| {
+                event.emitted_by = emitted_by
+                    .remove(&key)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect();
+                return event;
+            })
+            .collect();
+    }
+}
+
+fn decode_event(
+    value: &mut Value,
+    memory: &Memory,
+    decoding: &MemoryDecoding,
+) -> Option<Vec<LineId>> {
+    let Value::Calculation {
+        opcode: Opcode::LOG { topic_count },
+        args,
+    } = value
+    else {
+        return None;
+    };
+    let topics: Vec<Value> = args[2..2 + *topic_count].to_vec();
+    // the event is named after its first topic
+    if topics
+        .first()
+        .is_some_and(|topic| topic.compute_value().is_none())
+    {
+        return None;
+    }
+    let size: usize = get_data_size(&args[1], decoding)?;
+    if size % 0x20 != 0 {
+        return None;
+    }
+    let (data, decoded_writes) = if size == 0 {
+        (Vec::new(), Vec::new())
+    } else {
+        read_words(memory, resolve_address(&args[0], decoding)?, size / 0x20)?
+    };
+    *value = Value::Event { topics, data };
+    return Some(decoded_writes);
+}
+
+pub fn event_name(topic: Option<&Value>) -> String {
+    match topic {
+        Some(Value::Named { bytes: _, name }) => return split_signature(name).0.to_owned(),
+        Some(Value::Bytes(topic)) => return format!("Event_0x{:064x}", topic),
+        Some(_) => return "Event".to_owned(),
+        None => return "AnonymousEvent".to_owned(),
+    }
+}
+
+fn get_emitted_events(function: &FunctionWithVars) -> Vec<EventDescription> {
+    let mut events: Vec<EventDescription> = Vec::new();
+    apply_on_values(&function.content, &mut |value: &Value| {
+        let (topics, n_data_words): (&[Value], Option<usize>) = match value {
+            Value::Event { topics, data } => (topics, Some(data.len())),
+            // the data of the log could not be decoded
+            Value::Calculation {
+                opcode: Opcode::LOG { topic_count },
+                args,
+            } => (&args[2..2 + topic_count], None),
+            _ => return,
+        };
+        let topic: Option<U256> = match topics.first() {
+            Some(first_topic) => match first_topic.compute_value() {
+                Some(topic) => Some(topic),
+                None => return,
+            },
+            None => None,
+        };
+        let signature: Option<String> = match topics.first() {
+            Some(Value::Named { bytes: _, name }) => Some(name.clone()),
+            _ => None,
+        };
+        events.push(EventDescription {
+            topic,
+            name: event_name(topics.first()),
+            signature,
+            n_indexed: topics.len().saturating_sub(1),
+            n_data_words,
+            emitted_by: Vec::new(),
+        });
+    });
+    return events;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompiler::Decompiler;
+    use crate::signatures::signature_database::SignatureDatabase;
+
+    fn calculation(opcode: Opcode, args: &[Value]) -> Value {
+        return Value::Calculation {
+            opcode,
+            args: args.to_vec(),
+        };
+    }
+
+    fn get_decoded_events(flow: &ExecutionFlowWithVars) -> Vec<Value> {
+        let mut events: Vec<Value> = Vec::new();
+        for function in flow.functions.values() {
+            apply_on_values(&function.content, &mut |value: &Value| {
+                if let Value::Event { topics: _, data: _ } = value {
+                    events.push(value.clone());
+                }
+            });
+        }
+        return events;
+    }
+
+    #[test]
+    fn test_decode_events() {
+        // mstore(0x00, calldata[0x40]), then log3 with the topic of Transfer(address,address,uint256)
+        let mut flow: ExecutionFlowWithVars = Decompiler::from_hex(
+            "604035600052600035337fddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef60206000a300",
+        )
+        .unwrap()
+        .decompile()
+        .unwrap()
+        .execution_flow_with_vars;
+        let topic: U256 = U256::from_str_radix(
+            "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+            16,
+        )
+        .unwrap();
+        let event_with_topic = |first_topic: Value| -> Value {
+            return Value::Event {
+                topics: vec![
+                    first_topic,
+                    calculation(Opcode::CALLER, &[]),
+                    calculation(Opcode::CALLDATALOAD, &[Value::Bytes(U256::zero())]),
+                ],
+                data: vec![calculation(
+                    Opcode::CALLDATALOAD,
+                    &[Value::Bytes(U256::from(0x40))],
+                )],
+            };
+        };
+        assert_eq!(
+            get_decoded_events(&flow),
+            vec![event_with_topic(Value::Bytes(topic))]
+        );
+
+        flow.apply_signatures(&SignatureDatabase::with_common_signatures());
+        let signature: String = String::from("Transfer(address,address,uint256)");
+        assert_eq!(
+            get_decoded_events(&flow),
+            vec![event_with_topic(Value::Named {
+                bytes: topic,
+                name: signature.clone(),
+            })]
+        );
+        assert_eq!(
+            flow.get_events(),
+            vec![EventDescription {
+                topic: Some(topic),
+                name: String::from("Transfer"),
+                signature: Some(signature),
+                n_indexed: 2,
+                n_data_words: Some(1),
+                emitted_by: vec![String::from("main")],
+            }]
+        );
+    }
+}
@@ -1,12 +1,15 @@
 use super::{
     incorporate_variables::{convert_vopcodes_to_lines, Line, Value, Variable, VariablesStack},
     scopes_with_vars::{
-        FunctionCallWithVars, FunctionReturnWithVars, FunctionWithVars, InstructionsWithVars,
-        VarScope,
+        FunctionCallWithVars, FunctionKind, FunctionReturnWithVars, FunctionWithVars,
+        InstructionsWithVars, VarScope,
     },
 };
+use crate::error::PellucidError;
 use crate::execution_flow::{
-    execution_flow::{ExecutionFlow, FunctionLabel, GetFunctionLabel, LoopLabel, Scope},
+    execution_flow::{
+        ExecutionFlow, FunctionLabel, GetFunctionLabel, LoopLabel, Scope, MAIN_FUNCTION_LABEL,
+    },
     with_opcodes::{
         flow_with_opcodes::ExecutionFlowWithOpcodes,
         scopes_with_opcodes::{FunctionWithOpcodes, OpcodeScope},
@@ -23,7 +26,7 @@ pub type ExecutionFlowWithVars = ExecutionFlow<
 
 pub fn convert_opcode_flow_to_var_flow(
     opcode_flow: &ExecutionFlowWithOpcodes,
-) -> ExecutionFlowWithVars {
+) -> Result<ExecutionFlowWithVars, PellucidError> {
     let mut var_equivalences: Vec<(Variable, Variable)> = Vec::new();
 
     let mut functions_with_vars: HashMap<FunctionLabel, FunctionWithVars> = HashMap::new();
@@ -43,10 +46,20 @@ pub fn convert_opcode_flow_to_var_flow(
             &opcode_flow.functions,
             &mut HashMap::new(),
             &mut var_equivalences,
-        );
+        )?;
 
         let converted_function: FunctionWithVars = FunctionWithVars {
             label: *label,
+            kind: if *label == MAIN_FUNCTION_LABEL {
+                FunctionKind::Main
+            } else if let Some(pc) = function_with_opcodes.dynamic_jump_target {
+                FunctionKind::JumpTarget { pc }
+            } else {
+                FunctionKind::Internal
+            },
+            signature: None,
+            parameters: Vec::new(),
+            var_types: HashMap::new(),
             input_vars,
             n_outputs,
             returns,
@@ -63,17 +76,29 @@ pub fn convert_opcode_flow_to_var_flow(
 
     execution_flow_with_vars.rename_variables(&var_mapping);
 
-    execution_flow_with_vars.simplify();
-    return execution_flow_with_vars;
+    execution_flow_with_vars.simplify()?;
+    return Ok(execution_flow_with_vars);
 }
 
 impl ExecutionFlowWithVars {
-    pub fn simplify(&mut self) {
+    pub fn simplify(&mut self) -> Result<(), PellucidError> {
         self.simplify_vars();
         self.remove_empty_elements();
         self.remove_small_functions(); // must be done before variable renaming
+        self.extract_external_functions()?;
+        self.decode_abi_parameters();
+        self.recognise_storage_variables();
+        self.decode_reverts();
+        self.decode_events();
+        self.collapse_checked_arithmetic()?; // once panics are decoded
+        self.recognise_requires()?;
+        self.structure_loops()?;
+        self.flatten_conditions()?; // once loops have their bodies
+        self.remove_empty_elements();
         self.rename_variables_starting_from_zero();
         self.rename_functions_starting_from_zero();
+        self.infer_types(); // once variables have their final names
+        return Ok(());
     }
 }
 
@@ -83,7 +108,7 @@ fn convert_opcode_scopes_to_var_scopes(
     functions_with_opcodes: &HashMap<FunctionLabel, FunctionWithOpcodes>,
     stack_at_loop_starts: &mut HashMap<LoopLabel, VariablesStack>,
     var_equivalences: &mut Vec<(Variable, Variable)>,
-) -> (VariablesStack, Vec<VarScope>) {
+) -> Result<(VariablesStack, Vec<VarScope>), PellucidError> {
     let mut var_scopes: Vec<VarScope> = Vec::new();
     let mut current_stack: VariablesStack = initial_stack.clone();
     for (opcode_scope_index, opcode_scope) in opcode_scopes.iter().enumerate() {
@@ -95,7 +120,9 @@ fn convert_opcode_scopes_to_var_scopes(
             }
             Scope::LoopContinue { label } => {
                 if let Some(stack_at_loop_start) = stack_at_loop_starts.get(label) {
-                    assert!(stack_at_loop_start.len() == current_stack.len());
+                    if stack_at_loop_start.len() != current_stack.len() {
+                        return Err(PellucidError::LoopStackMismatch { label: *label });
+                    }
                     for stack_depth in 0..stack_at_loop_start.len() {
                         var_equivalences.push((
                             *stack_at_loop_start.peek_at(stack_depth),
@@ -110,12 +137,25 @@ fn convert_opcode_scopes_to_var_scopes(
             }
             Scope::Panic => var_scopes.push(Scope::Panic),
             Scope::Empty => (), // ne need to keep it
+            Scope::LoopBreak { label: _ }
+            | Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header: _,
+                body: _,
+            } => {
+                return Err(PellucidError::ScopesMalformed(String::from(
+                    "loops are only structured once variables are incorporated",
+                )))
+            }
             Scope::FunctionReturn(function_return_with_opcodes) => {
                 assert!(opcode_scope_index == opcode_scopes.len() - 1);
+                // like its calls, a function whose outputs are unknown returns nothing (merged
+                // contexts may even leave a return in a function that was removed)
                 let n_returned_vars: usize = functions_with_opcodes
-                    [&function_return_with_opcodes.get_label()]
-                    .n_outputs
-                    .unwrap();
+                    .get(&function_return_with_opcodes.get_label())
+                    .and_then(|function| function.n_outputs)
+                    .unwrap_or(0);
                 var_scopes.push(Scope::FunctionReturn(FunctionReturnWithVars {
                     label: function_return_with_opcodes.get_label(),
                     returned_values: Value::from_vars(&current_stack.multi_pop(n_returned_vars)),
@@ -124,16 +164,19 @@ fn convert_opcode_scopes_to_var_scopes(
             Scope::Instructions(opcode_instructions) => {
                 let instructions: Vec<Line>;
                 (current_stack, instructions) =
-                    convert_vopcodes_to_lines(&current_stack, &opcode_instructions.code);
+                    convert_vopcodes_to_lines(&current_stack, &opcode_instructions.code)?;
                 var_scopes.push(VarScope::Instructions(InstructionsWithVars {
                     lines: instructions,
                 }));
             }
             Scope::FunctionCall(call) => {
-                let n_arguments: usize = functions_with_opcodes[&call.get_label()].n_inputs;
-                let n_results: usize = functions_with_opcodes[&call.get_label()]
-                    .n_outputs
-                    .unwrap_or(0);
+                let Some(called_function) = functions_with_opcodes.get(&call.get_label()) else {
+                    return Err(PellucidError::MissingCalledFunction {
+                        label: call.get_label(),
+                    });
+                };
+                let n_arguments: usize = called_function.n_inputs;
+                let n_results: usize = called_function.n_outputs.unwrap_or(0);
 
                 let mut arguments: Vec<Value> =
                     Value::from_vars(&current_stack.multi_pop(n_arguments));
@@ -158,7 +201,7 @@ fn convert_opcode_scopes_to_var_scopes(
                     functions_with_opcodes,
                     stack_at_loop_starts,
                     var_equivalences,
-                );
+                )?;
                 let (resulting_stack_if_false, converted_instructions_if_false): (
                     VariablesStack,
                     Vec<VarScope>,
@@ -168,7 +211,7 @@ fn convert_opcode_scopes_to_var_scopes(
                     functions_with_opcodes,
                     stack_at_loop_starts,
                     var_equivalences,
-                );
+                )?;
 
                 let (stack_to_change, target_stack): (VariablesStack, VariablesStack) =
                     if resulting_stack_if_true.len() < resulting_stack_if_false.len() {
@@ -198,7 +241,7 @@ fn convert_opcode_scopes_to_var_scopes(
             }
         }
     }
-    return (current_stack, var_scopes);
+    return Ok((current_stack, var_scopes));
 }
 
 fn convert_aquivalences_to_var_mapping(
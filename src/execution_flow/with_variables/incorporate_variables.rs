@@ -3,6 +3,7 @@ use std::ops::{Deref, DerefMut};
 
 use crate::{
     bytecode_reader::{opcode::Opcode, vopcode::Vopcode},
+    error::PellucidError,
     execution_flow::execution_flow::FunctionLabel,
     tools::stack::Stack,
 };
@@ -20,11 +21,62 @@ pub enum Value {
     },
     Existing(Variable),
     Bytes(U256),
+    Named {
+        // bytes recognised as the hash of a known signature
+        bytes: U256,
+        name: String,
+    },
+    Parameter {
+        // argument of an external function, decoded from calldata
+        index: usize,
+        field: ParameterField,
+    },
     FunctionReturnedValue {
         label: FunctionLabel,
         arguments: Vec<Value>,
         return_index: usize,
     },
+    StorageSlot {
+        // `keccak(key . slot)` for each mapping key, `keccak(slot) + index` for each array index,
+        // the length of a dynamic array when there is no key
+        slot: U256,
+        keys: Vec<(StorageKind, Value)>,
+    },
+    Revert {
+        // revert data decoded from the memory it is read from
+        error: RevertError,
+        arguments: Vec<Value>,
+    },
+    Event {
+        // a log whose data is decoded from memory, the first topic is the hash of the signature
+        // unless the event is anonymous
+        topics: Vec<Value>,
+        data: Vec<Value>,
+    },
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum ParameterField {
+    Word,           // the whole value of a static parameter
+    Offset,         // the head of a dynamic parameter, pointing to its length
+    Length,         // number of bytes (bytes, string) or elements (arrays) of a dynamic parameter
+    Element(usize), // a word of a static array or tuple, in the order of the calldata
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum StorageKind {
+    Mapping,
+    Array,
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub enum RevertError {
+    Message(String), // `Error(string)` with a constant message
+    Panic(U256),     // `Panic(uint256)` with its code
+    Custom {
+        selector: u32,
+        name: Option<String>, // from the signature database
+    },
 }
 
 impl Value {
@@ -42,13 +94,31 @@ impl Value {
         return false;
     }
 
+    // Value of a calculation made only of constants.
+    pub fn compute_value(&self) -> Option<U256> {
+        match self {
+            Value::Bytes(bytes) | Value::Named { bytes, name: _ } => return Some(*bytes),
+            Value::Calculation { opcode, args } => {
+                let eval_function = opcode.get_function()?;
+                let mut params: Vec<U256> = Vec::new();
+                for arg in args {
+                    params.push(arg.compute_value()?);
+                }
+                return Some(eval_function(params));
+            }
+            _ => return None,
+        }
+    }
+
     pub fn size(&self) -> usize {
         match self {
             Value::Calculation { opcode: _, args } => {
                 1 + args.iter().map(|arg: &Value| arg.size()).sum::<usize>()
             }
             Value::Existing(_) => 1,
-            Value::Bytes(_) => 1,
+            Value::Bytes(_)
+            | Value::Named { bytes: _, name: _ }
+            | Value::Parameter { index: _, field: _ } => 1,
             Value::FunctionReturnedValue {
                 label: _,
                 arguments,
@@ -59,6 +129,25 @@ impl Value {
                     .map(|arg: &Value| arg.size())
                     .sum::<usize>()
             }
+            Value::StorageSlot { slot: _, keys } => {
+                1 + keys.iter().map(|(_, key)| key.size()).sum::<usize>()
+            }
+            Value::Revert {
+                error: _,
+                arguments,
+            } => {
+                1 + arguments
+                    .iter()
+                    .map(|arg: &Value| arg.size())
+                    .sum::<usize>()
+            }
+            Value::Event { topics, data } => {
+                1 + topics
+                    .iter()
+                    .chain(data)
+                    .map(|arg: &Value| arg.size())
+                    .sum::<usize>()
+            }
         }
     }
 }
@@ -73,6 +162,16 @@ pub enum Line {
     If {
         condition: Value,
     },
+    DynamicJump {
+        // 'goto dynamic(destination)', only taken when `condition` is non-zero if there is one
+        condition: Option<Value>,
+        destination: Value,
+    },
+    Require {
+        // 'require(condition, error)', reverts with `error` (nothing if None) if `condition` is zero
+        condition: Value,
+        error: Option<Value>,
+    },
     Empty,
 }
 
@@ -145,14 +244,21 @@ impl DerefMut for VariablesStack {
 pub fn convert_vopcodes_to_lines(
     initial_stack: &VariablesStack,
     vopcodes: &[Vopcode],
-) -> (VariablesStack, Vec<Line>) {
+) -> Result<(VariablesStack, Vec<Line>), PellucidError> {
     let mut current_stack: VariablesStack = initial_stack.clone();
     let mut lines: Vec<Line> = Vec::new();
 
     for vopcode in vopcodes {
         let opcode: Opcode = vopcode.opcode;
+        let required_stack_len: usize = match opcode {
+            Opcode::DUP { depth } | Opcode::DUPN { depth } => depth,
+            _ => opcode.stack_input(),
+        };
+        if current_stack.len() < required_stack_len {
+            return Err(PellucidError::StackUnderflow { pc: vopcode.pc });
+        }
         match opcode {
-            Opcode::DUP { depth } => {
+            Opcode::DUP { depth } | Opcode::DUPN { depth } => {
                 let new_var: Variable = current_stack.create_single_variable();
                 let assigned: Value = Value::Existing(current_stack.peek_at(depth - 1).clone());
                 lines.push(Line::Assignement {
@@ -161,8 +267,8 @@ pub fn convert_vopcodes_to_lines(
                 });
                 current_stack.push(new_var);
             }
-            Opcode::PUSH { item_size: _ } => {
-                let pushed_value: U256 = vopcode.value.unwrap();
+            Opcode::PUSH { item_size: _ } | Opcode::PUSH0 => {
+                let pushed_value: U256 = vopcode.value.unwrap_or(U256::zero());
                 let new_var: Variable = current_stack.create_single_variable();
                 let assigned: Value = Value::Bytes(pushed_value);
                 lines.push(Line::Assignement {
@@ -171,19 +277,56 @@ pub fn convert_vopcodes_to_lines(
                 });
                 current_stack.push(new_var);
             }
-            Opcode::SWAP { depth } => {
+            Opcode::SWAP { depth } | Opcode::SWAPN { depth } => {
+                current_stack.swap(depth);
+            }
+            Opcode::EXCHANGE { depth, distance } => {
+                current_stack.swap(depth);
+                current_stack.swap(depth + distance);
                 current_stack.swap(depth);
             }
             Opcode::POP | Opcode::JUMP => {
                 current_stack.pop();
             }
-            Opcode::JUMPDEST => (),
+            Opcode::JUMPDEST | Opcode::RJUMP | Opcode::RETF => (),
             Opcode::JUMPI => {
                 current_stack.pop();
                 lines.push(Line::If {
                     condition: Value::Existing(current_stack.pop()),
                 });
             }
+            Opcode::DYNAMIC_JUMP => {
+                lines.push(Line::DynamicJump {
+                    condition: None,
+                    destination: Value::Existing(current_stack.pop()),
+                });
+            }
+            Opcode::DYNAMIC_JUMPI => {
+                let destination: Value = Value::Existing(current_stack.pop());
+                lines.push(Line::DynamicJump {
+                    condition: Some(Value::Existing(current_stack.pop())),
+                    destination,
+                });
+            }
+            Opcode::RJUMPI => {
+                lines.push(Line::If {
+                    condition: Value::Existing(current_stack.pop()),
+                });
+            }
+            Opcode::RJUMPV { max_index } => {
+                // a single entry, larger tables are lowered to one condition per entry (cf
+                // `SkeletonJumpTable`). The jump is taken when the index is within the table.
+                let table_size: U256 = U256::from(max_index as usize + 1);
+                lines.push(Line::If {
+                    condition: Value::Calculation {
+                        opcode: Opcode::LT,
+                        args: vec![
+                            Value::Existing(current_stack.pop()),
+                            Value::Bytes(table_size),
+                        ],
+                    },
+                });
+            }
             _ => {
                 let receiving_var: Option<Variable> = if opcode.stack_output() > 0 {
                     assert!(opcode.stack_output() == 1); // Opcodes only have 0 or 1 stack output in EVM
@@ -204,5 +347,5 @@ pub fn convert_vopcodes_to_lines(
         }
     }
 
-    return (current_stack, lines);
+    return Ok((current_stack, lines));
 }
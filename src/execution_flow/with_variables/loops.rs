@@ -0,0 +1,694 @@
+use std::collections::HashMap;
+
+use super::{
+    abi_parameters::{apply_on_value, apply_on_value_mut, apply_on_values},
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::{Line, Value, Variable},
+    requires::{negate, remove_double_negations},
+    scopes_with_vars::{InstructionsWithVars, VarScope},
+    simplify::initialization_of_vars::enumerate_var_initializations,
+};
+use crate::{
+    error::PellucidError,
+    execution_flow::execution_flow::{LoopKind, LoopLabel, Scope},
+};
+
+struct Copies {
+    n_initializations_per_var: HashMap<Variable, usize>,
+    n_uses_per_var: HashMap<Variable, usize>,
+}
+
+impl ExecutionFlowWithVars {
+    // A loop starts with a condition whose one branch continues the loop. This branch becomes the
+    // body of a `while` loop, or of a `for` loop when the variable of the condition is initialized
+    // right before the loop and updated at the end of the body. The other branch follows the loop.
+    pub fn structure_loops(&mut self) -> Result<(), PellucidError> {
+        for function in self.functions.values_mut() {
+            let mut n_uses_per_var: HashMap<Variable, usize> = HashMap::new();
+            apply_on_values(&function.content, &mut |value: &Value| {
+                if let Value::Existing(var) = value {
+                    *n_uses_per_var.entry(*var).or_insert(0) += 1;
+                }
+            });
+            let copies: Copies = Copies {
+                n_initializations_per_var: enumerate_var_initializations(&function.content),
+                n_uses_per_var,
+            };
+            structure_loops_in_scopes(&mut function.content, &copies)?;
+        }
+        return Ok(());
+    }
+}
+
+// Inner loops are structured first, so that their exits are already in the body of outer loops.
+fn structure_loops_in_scopes(
+    scopes: &mut Vec<VarScope>,
+    copies: &Copies,
+) -> Result<(), PellucidError> {
+    // inlined functions leave consecutive instructions
+    let mut scope_index: usize = 1;
+    while scope_index < scopes.len() {
+        if let [Scope::Instructions(previous_instructions), Scope::Instructions(instructions)] =
+            &mut scopes[scope_index - 1..=scope_index]
+        {
+            previous_instructions.lines.append(&mut instructions.lines);
+            scopes.remove(scope_index);
+        } else {
+            scope_index += 1;
+        }
+    }
+    for scope in scopes.iter_mut() {
+        if let Scope::Condition {
+            instructions_if_true,
+            instructions_if_false,
+        } = scope
+        {
+            structure_loops_in_scopes(instructions_if_true, copies)?;
+            structure_loops_in_scopes(instructions_if_false, copies)?;
+        }
+    }
+    for scope_index in (0..scopes.len()).rev() {
+        if let Scope::Loop { label } = scopes[scope_index] {
+            if let Some(structured_scopes) = structure_loop(scopes, scope_index, label, copies)? {
+                scopes.splice(scope_index.., structured_scopes);
+            }
+        }
+    }
+    return Ok(());
+}
+
+// The loop and the scopes following it, if the loop starts with a condition exiting it.
+fn structure_loop(
+    scopes: &mut [VarScope],
+    loop_index: usize,
+    label: LoopLabel,
+    copies: &Copies,
+) -> Result<Option<Vec<VarScope>>, PellucidError> {
+    let [Scope::Instructions(first_instructions), Scope::Condition {
+        instructions_if_true,
+        instructions_if_false,
+    }, following_scopes @ ..] = &scopes[loop_index + 1..]
+    else {
+        return Ok(None);
+    };
+    if !matches!(
+        first_instructions.lines.last(),
+        Some(Line::If { condition: _ })
+    ) {
+        return Ok(None);
+    }
+    let (mut body, mut exit_scopes, continues_if_true): (Vec<VarScope>, Vec<VarScope>, bool) =
+        match (
+            continues_loop(instructions_if_true, label),
+            continues_loop(instructions_if_false, label),
+        ) {
+            (true, false) => (
+                instructions_if_true.clone(),
+                instructions_if_false.clone(),
+                true,
+            ),
+            (false, true) => (
+                instructions_if_false.clone(),
+                instructions_if_true.clone(),
+                false,
+            ),
+            _ => return Ok(None),
+        };
+    // the scopes following the condition are reached when exiting the loop
+    if !following_scopes.is_empty() && !always_continues(&body, label) {
+        return Ok(None);
+    }
+    exit_scopes.extend_from_slice(following_scopes);
+
+    // lines computed at each iteration before checking the condition
+    let mut first_scopes: Vec<VarScope> = instructions_scope(first_instructions.lines.clone());
+    fold_copies(&mut first_scopes, copies);
+    let Some(Scope::Instructions(InstructionsWithVars {
+        lines: mut first_lines,
+    })) = first_scopes.pop()
+    else {
+        return Err(PellucidError::ScopesMalformed(String::from(
+            "the lines before a loop condition are split in several scopes",
+        )));
+    };
+    let Some(Line::If { condition }) = first_lines.pop() else {
+        return Err(PellucidError::ScopesMalformed(String::from(
+            "a loop does not start with a condition",
+        )));
+    };
+    let condition: Value = fold_last_lines_in_condition(&mut first_lines, condition, copies);
+    let loop_condition: Value = if continues_if_true {
+        remove_double_negations(condition)
+    } else {
+        remove_double_negations(negate(condition))
+    };
+    first_lines.retain(|line| line != &Line::Empty);
+    fold_copies_in_scopes(&mut body, copies);
+    remove_trailing_continues(&mut body, label);
+    replace_exits_by_breaks(&mut body, &exit_scopes, label);
+
+    let (kind, header, body): (LoopKind, Vec<VarScope>, Vec<VarScope>) = if first_lines.is_empty() {
+        let condition_line: Line = Line::If {
+            condition: loop_condition,
+        };
+        match take_for_loop_header(scopes, loop_index, &condition_line, &mut body) {
+            Some(header) => (LoopKind::For, header, body),
+            None => (
+                LoopKind::While,
+                instructions_scope(vec![condition_line]),
+                body,
+            ),
+        }
+    } else if body.iter().all(|scope| scope.is_empty()) {
+        let header: Vec<VarScope> = instructions_scope(vec![Line::If {
+            condition: loop_condition,
+        }]);
+        (LoopKind::DoWhile, header, instructions_scope(first_lines))
+    } else {
+        // the loop is exited in the middle of its body
+        first_lines.push(Line::If {
+            condition: remove_double_negations(negate(loop_condition)),
+        });
+        let mut infinite_loop_body: Vec<VarScope> = instructions_scope(first_lines);
+        infinite_loop_body.push(Scope::Condition {
+            instructions_if_true: vec![Scope::LoopBreak { label }],
+            instructions_if_false: Vec::new(),
+        });
+        infinite_loop_body.extend(body);
+        (LoopKind::While, Vec::new(), infinite_loop_body)
+    };
+
+    let mut structured_scopes: Vec<VarScope> = vec![Scope::StructuredLoop {
+        label,
+        kind,
+        header,
+        body,
+    }];
+    structured_scopes.extend(exit_scopes);
+    return Ok(Some(structured_scopes));
+}
+
+fn instructions_scope(lines: Vec<Line>) -> Vec<VarScope> {
+    return vec![Scope::Instructions(InstructionsWithVars { lines })];
+}
+
+fn continues_loop(scopes: &[VarScope], label: LoopLabel) -> bool {
+    return scopes.iter().any(|scope| match scope {
+        Scope::LoopContinue {
+            label: continued_label,
+        } => *continued_label == label,
+        Scope::Condition {
+            instructions_if_true,
+            instructions_if_false,
+        } => {
+            continues_loop(instructions_if_true, label)
+                || continues_loop(instructions_if_false, label)
+        }
+        Scope::StructuredLoop {
+            label: _,
+            kind: _,
+            header: _,
+            body,
+        } => continues_loop(body, label),
+        _ => false,
+    });
+}
+
+fn always_continues(scopes: &[VarScope], label: LoopLabel) -> bool {
+    match scopes.last() {
+        Some(Scope::LoopContinue {
+            label: continued_label,
+        }) => return *continued_label == label,
+        Some(Scope::Condition {
+            instructions_if_true,
+            instructions_if_false,
+        }) => {
+            return always_continues(instructions_if_true, label)
+                && always_continues(instructions_if_false, label)
+        }
+        _ => return false,
+    }
+}
+
+// Continuing the loop at the end of its body is implicit.
+fn remove_trailing_continues(scopes: &mut Vec<VarScope>, label: LoopLabel) {
+    // the end of an unstructured loop is not the end of the body
+    if scopes.iter().any(|scope| scope.is_loop()) {
+        return;
+    }
+    match scopes.last_mut() {
+        Some(Scope::LoopContinue {
+            label: continued_label,
+        }) if *continued_label == label => {
+            scopes.pop();
+        }
+        Some(Scope::Condition {
+            instructions_if_true,
+            instructions_if_false,
+        }) => {
+            remove_trailing_continues(instructions_if_true, label);
+            remove_trailing_continues(instructions_if_false, label);
+        }
+        _ => (),
+    }
+}
+
+// The code following the loop is duplicated at the end of the paths leaving it early.
+fn replace_exits_by_breaks(scopes: &mut Vec<VarScope>, exit_scopes: &[VarScope], label: LoopLabel) {
+    if exit_scopes.is_empty() || scopes.iter().any(|scope| scope.is_loop()) {
+        return;
+    }
+    if scopes.ends_with(exit_scopes) {
+        scopes.truncate(scopes.len() - exit_scopes.len());
+        scopes.push(Scope::LoopBreak { label });
+        return;
+    }
+    if let Some(Scope::Condition {
+        instructions_if_true,
+        instructions_if_false,
+    }) = scopes.last_mut()
+    {
+        replace_exits_by_breaks(instructions_if_true, exit_scopes, label);
+        replace_exits_by_breaks(instructions_if_false, exit_scopes, label);
+    }
+}
+
+// The header of a `for` loop, if the variable of the condition is initialized by the last line
+// before the loop and updated at the end of the body, by a line or a call. These are moved to the
+// header.
+fn take_for_loop_header(
+    scopes: &mut [VarScope],
+    loop_index: usize,
+    condition_line: &Line,
+    body: &mut Vec<VarScope>,
+) -> Option<Vec<VarScope>> {
+    let Line::If { condition } = condition_line else {
+        return None;
+    };
+    if loop_index == 0 {
+        return None;
+    }
+    let Scope::Instructions(previous_instructions) = &mut scopes[loop_index - 1] else {
+        return None;
+    };
+    let initialization_index: usize = previous_instructions
+        .lines
+        .iter()
+        .rposition(|line| line != &Line::Empty)?;
+    let Line::Assignement {
+        receiving_var: Some(induction_var),
+        assigned_value: _,
+    } = previous_instructions.lines[initialization_index]
+    else {
+        return None;
+    };
+    if !uses_var(condition, induction_var) {
+        return None;
+    }
+
+    let increment: VarScope = match body.last_mut()? {
+        Scope::Instructions(last_instructions) => {
+            let increment_index: usize = last_instructions
+                .lines
+                .iter()
+                .rposition(|line| line != &Line::Empty)?;
+            let Line::Assignement {
+                receiving_var: Some(incremented_var),
+                assigned_value,
+            } = &last_instructions.lines[increment_index]
+            else {
+                return None;
+            };
+            if *incremented_var != induction_var || !uses_var(assigned_value, induction_var) {
+                return None;
+            }
+            let increment: Line =
+                std::mem::replace(&mut last_instructions.lines[increment_index], Line::Empty);
+            Scope::Instructions(InstructionsWithVars {
+                lines: vec![increment],
+            })
+        }
+        Scope::FunctionCall(function_call) => {
+            if function_call.results != vec![induction_var]
+                || !function_call
+                    .arguments
+                    .iter()
+                    .any(|argument| uses_var(argument, induction_var))
+            {
+                return None;
+            }
+            body.pop()?
+        }
+        _ => return None,
+    };
+    let initialization: Line = std::mem::replace(
+        &mut previous_instructions.lines[initialization_index],
+        Line::Empty,
+    );
+    return Some(vec![
+        Scope::Instructions(InstructionsWithVars {
+            lines: vec![initialization, condition_line.clone()],
+        }),
+        increment,
+    ]);
+}
+
+fn uses_var(value: &Value, var: Variable) -> bool {
+    let mut is_used: bool = false;
+    apply_on_value(value, &mut |value: &Value| {
+        is_used = is_used || value == &Value::Existing(var);
+    });
+    return is_used;
+}
+
+fn fold_copies_in_scopes(scopes: &mut Vec<VarScope>, copies: &Copies) {
+    fold_copies(scopes, copies);
+    for scope in scopes {
+        match scope {
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => {
+                fold_copies_in_scopes(instructions_if_true, copies);
+                fold_copies_in_scopes(instructions_if_false, copies);
+            }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                fold_copies_in_scopes(header, copies);
+                fold_copies_in_scopes(body, copies);
+            }
+            _ => (),
+        }
+    }
+}
+
+// Loop variables are assigned several times, so their copies are not simplified. A copy used once
+// further in the same sequence of instructions and calls is replaced by the copied variable, unless
+// the latter is reassigned in between.
+fn fold_copies(scopes: &mut [VarScope], copies: &Copies) {
+    for scope_index in 0..scopes.len() {
+        let Scope::Instructions(instructions) = &scopes[scope_index] else {
+            continue;
+        };
+        for line_index in 0..instructions.lines.len() {
+            let Scope::Instructions(instructions) = &scopes[scope_index] else {
+                continue;
+            };
+            let Some((copy, copied_var)) =
+                get_foldable_copy(&instructions.lines[line_index], copies)
+            else {
+                continue;
+            };
+            if replace_next_use(&mut scopes[scope_index..], line_index + 1, copy, copied_var) {
+                let Scope::Instructions(instructions) = &mut scopes[scope_index] else {
+                    continue;
+                };
+                instructions.lines[line_index] = Line::Empty;
+            }
+        }
+    }
+}
+
+fn replace_next_use(
+    scopes: &mut [VarScope],
+    first_line_index: usize,
+    copy: Variable,
+    copied_var: Variable,
+) -> bool {
+    for (scope_offset, scope) in scopes.iter_mut().enumerate() {
+        match scope {
+            Scope::Instructions(instructions) => {
+                let first_line_index: usize = if scope_offset == 0 {
+                    first_line_index
+                } else {
+                    0
+                };
+                for line in &mut instructions.lines[first_line_index..] {
+                    let mut values: Vec<&mut Value> = get_line_values_mut(line);
+                    if values.iter().any(|value| uses_var(value, copy)) {
+                        for value in &mut values {
+                            replace_var(value, copy, &Value::Existing(copied_var));
+                        }
+                        return true;
+                    }
+                    if let Line::Assignement {
+                        receiving_var: Some(assigned_var),
+                        assigned_value: _,
+                    } = line
+                    {
+                        if *assigned_var == copied_var {
+                            return false;
+                        }
+                    }
+                }
+            }
+            Scope::FunctionCall(function_call) => {
+                if function_call
+                    .arguments
+                    .iter()
+                    .any(|argument| uses_var(argument, copy))
+                {
+                    for argument in &mut function_call.arguments {
+                        replace_var(argument, copy, &Value::Existing(copied_var));
+                    }
+                    return true;
+                }
+                if function_call.results.contains(&copied_var) {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+    return false;
+}
+
+// The values computed right before the loop condition, and only used by it, are moved to it.
+fn fold_last_lines_in_condition(
+    lines: &mut [Line],
+    mut condition: Value,
+    copies: &Copies,
+) -> Value {
+    while let Some(line_index) = lines.iter().rposition(|line| line != &Line::Empty) {
+        let Line::Assignement {
+            receiving_var: Some(var),
+            assigned_value,
+        } = &lines[line_index]
+        else {
+            break;
+        };
+        if copies.n_initializations_per_var.get(var) != Some(&1)
+            || copies.n_uses_per_var.get(var) != Some(&1)
+            || !uses_var(&condition, *var)
+            || has_effect(assigned_value)
+        {
+            break;
+        }
+        replace_var(&mut condition, *var, assigned_value);
+        lines[line_index] = Line::Empty;
+    }
+    return condition;
+}
+
+fn has_effect(value: &Value) -> bool {
+    let mut has_effect: bool = false;
+    apply_on_value(value, &mut |value: &Value| match value {
+        Value::Calculation { opcode, args: _ } => has_effect = has_effect || opcode.has_effect(),
+        Value::FunctionReturnedValue {
+            label: _,
+            arguments: _,
+            return_index: _,
+        } => has_effect = true,
+        _ => (),
+    });
+    return has_effect;
+}
+
+fn get_foldable_copy(line: &Line, copies: &Copies) -> Option<(Variable, Variable)> {
+    let Line::Assignement {
+        receiving_var: Some(copy),
+        assigned_value: Value::Existing(copied_var),
+    } = line
+    else {
+        return None;
+    };
+    if copies.n_initializations_per_var.get(copy) != Some(&1)
+        || copies.n_uses_per_var.get(copy) != Some(&1)
+    {
+        return None;
+    }
+    return Some((*copy, *copied_var));
+}
+
+fn replace_var(value: &mut Value, var: Variable, new_value: &Value) {
+    apply_on_value_mut(value, &mut |value: &mut Value| {
+        if value == &Value::Existing(var) {
+            *value = new_value.clone();
+        }
+    });
+}
+
+fn get_line_values_mut(line: &mut Line) -> Vec<&mut Value> {
+    match line {
+        Line::Assignement {
+            receiving_var: _,
+            assigned_value,
+        } => return vec![assigned_value],
+        Line::If { condition } => return vec![condition],
+        Line::DynamicJump {
+            condition,
+            destination,
+        } => return condition.iter_mut().chain([destination]).collect(),
+        Line::Require { condition, error } => {
+            return [condition].into_iter().chain(error).collect()
+        }
+        Line::Empty => return Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode_reader::opcode::Opcode;
+    use primitive_types::U256;
+
+    fn assignment(receiving_var: Option<Variable>, assigned_value: Value) -> Line {
+        return Line::Assignement {
+            receiving_var,
+            assigned_value,
+        };
+    }
+
+    fn calculation(opcode: Opcode, args: &[Value]) -> Value {
+        return Value::Calculation {
+            opcode,
+            args: args.to_vec(),
+        };
+    }
+
+    fn store(value: Value) -> Line {
+        return assignment(
+            None,
+            calculation(Opcode::SSTORE, &[Value::Bytes(U256::zero()), value]),
+        );
+    }
+
+    #[test]
+    fn test_take_for_loop_header() {
+        let var: Variable = Variable { alias: 0 };
+        let initialization: Line = assignment(Some(var), Value::Bytes(U256::zero()));
+        let increment: Line = assignment(
+            Some(var),
+            calculation(
+                Opcode::ADD,
+                &[Value::Existing(var), Value::Bytes(U256::one())],
+            ),
+        );
+        let condition_line: Line = Line::If {
+            condition: calculation(
+                Opcode::LT,
+                &[Value::Existing(var), Value::Bytes(U256::from(0x0c))],
+            ),
+        };
+        let mut scopes: Vec<VarScope> = vec![
+            Scope::Instructions(InstructionsWithVars {
+                lines: vec![initialization.clone()],
+            }),
+            Scope::Loop { label: 0 },
+        ];
+        let mut body: Vec<VarScope> =
+            instructions_scope(vec![store(Value::Existing(var)), increment.clone()]);
+
+        let header: Vec<VarScope> =
+            take_for_loop_header(&mut scopes, 1, &condition_line, &mut body).unwrap();
+        assert_eq!(
+            header,
+            vec![
+                Scope::Instructions(InstructionsWithVars {
+                    lines: vec![initialization, condition_line],
+                }),
+                Scope::Instructions(InstructionsWithVars {
+                    lines: vec![increment],
+                }),
+            ]
+        );
+        assert_eq!(scopes[..1], instructions_scope(vec![Line::Empty]));
+        assert_eq!(
+            body,
+            instructions_scope(vec![store(Value::Existing(var)), Line::Empty])
+        );
+
+        // the condition does not use the initialized variable
+        let mut scopes: Vec<VarScope> = vec![
+            Scope::Instructions(InstructionsWithVars {
+                lines: vec![assignment(Some(var), Value::Bytes(U256::zero()))],
+            }),
+            Scope::Loop { label: 0 },
+        ];
+        let other_condition_line: Line = Line::If {
+            condition: Value::Existing(Variable { alias: 1 }),
+        };
+        assert_eq!(
+            take_for_loop_header(&mut scopes, 1, &other_condition_line, &mut body),
+            None
+        );
+    }
+
+    #[test]
+    fn test_replace_exits_by_breaks() {
+        let exit_scopes: Vec<VarScope> = instructions_scope(vec![store(Value::Bytes(U256::one()))]);
+        let mut if_true: Vec<VarScope> =
+            instructions_scope(vec![store(Value::Bytes(U256::zero()))]);
+        if_true.extend(exit_scopes.clone());
+        let mut body: Vec<VarScope> = vec![Scope::Condition {
+            instructions_if_true: if_true,
+            instructions_if_false: vec![Scope::LoopContinue { label: 0 }],
+        }];
+
+        replace_exits_by_breaks(&mut body, &exit_scopes, 0);
+        let mut expected_if_true: Vec<VarScope> =
+            instructions_scope(vec![store(Value::Bytes(U256::zero()))]);
+        expected_if_true.push(Scope::LoopBreak { label: 0 });
+        assert_eq!(
+            body,
+            vec![Scope::Condition {
+                instructions_if_true: expected_if_true,
+                instructions_if_false: vec![Scope::LoopContinue { label: 0 }],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_fold_copies() {
+        let [var, copy]: [Variable; 2] = [0, 1].map(|alias| Variable { alias });
+        let copies: Copies = Copies {
+            n_initializations_per_var: HashMap::from([(var, 2), (copy, 1)]),
+            n_uses_per_var: HashMap::from([(var, 1), (copy, 1)]),
+        };
+
+        // `copy = var`, then `storage[0x00] = copy`
+        let mut scopes: Vec<VarScope> = instructions_scope(vec![
+            assignment(Some(copy), Value::Existing(var)),
+            store(Value::Existing(copy)),
+        ]);
+        fold_copies(&mut scopes, &copies);
+        assert_eq!(
+            scopes,
+            instructions_scope(vec![Line::Empty, store(Value::Existing(var))])
+        );
+
+        // the copied variable is reassigned before the copy is used
+        let lines: Vec<Line> = vec![
+            assignment(Some(copy), Value::Existing(var)),
+            assignment(Some(var), Value::Bytes(U256::zero())),
+            store(Value::Existing(copy)),
+        ];
+        let mut scopes: Vec<VarScope> = instructions_scope(lines.clone());
+        fold_copies(&mut scopes, &copies);
+        assert_eq!(scopes, instructions_scope(lines));
+    }
+}
@@ -1,5 +1,17 @@
+pub mod abi_parameters;
+pub mod checked_arithmetic;
+pub mod conditions;
+pub mod dispatcher;
 pub mod display_flow_with_vars;
+pub mod events;
 pub mod flow_with_vars;
 pub mod incorporate_variables;
+pub mod loops;
+pub mod requires;
+pub mod revert_reasons;
 pub mod scopes_with_vars;
+pub mod signatures;
 pub mod simplify;
+pub mod storage_layout;
+pub mod storage_variables;
+pub mod type_inference;
@@ -0,0 +1,248 @@
+use primitive_types::U256;
+
+use super::{
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::{Line, RevertError, Value},
+    scopes_with_vars::VarScope,
+};
+use crate::{
+    bytecode_reader::opcode::Opcode, error::PellucidError, execution_flow::execution_flow::Scope,
+};
+
+impl ExecutionFlowWithVars {
+    // `require(condition, error)` is compiled to a condition whose one branch only reverts. The
+    // condition becomes a `require` line, followed by the other branch.
+    pub fn recognise_requires(&mut self) -> Result<(), PellucidError> {
+        for function in self.functions.values_mut() {
+            replace_conditions_in_scopes(&mut function.content, &mut replace_condition_by_require)?;
+        }
+        return Ok(());
+    }
+}
+
+// Visits the condition scopes, nested ones included. The scopes returned by `replace_condition`
+// replace the condition and are visited next.
+pub fn replace_conditions_in_scopes(
+    scopes: &mut Vec<VarScope>,
+    replace_condition: &mut impl FnMut(
+        &mut [VarScope],
+        usize,
+    ) -> Result<Option<Vec<VarScope>>, PellucidError>,
+) -> Result<(), PellucidError> {
+    let mut scope_index: usize = 0;
+    while scope_index < scopes.len() {
+        if scope_index > 0 && scopes[scope_index - 1].should_be_followed_by_condition_scope() {
+            if let Some(kept_scopes) = replace_condition(scopes, scope_index)? {
+                scopes.splice(scope_index..scope_index + 1, kept_scopes);
+                continue;
+            }
+        }
+        if let Scope::Condition {
+            instructions_if_true,
+            instructions_if_false,
+        } = &mut scopes[scope_index]
+        {
+            replace_conditions_in_scopes(instructions_if_true, replace_condition)?;
+            replace_conditions_in_scopes(instructions_if_false, replace_condition)?;
+        }
+        scope_index += 1;
+    }
+    return Ok(());
+}
+
+// Returns the branch that does not revert, once the `if` line is replaced by a `require` line.
+fn replace_condition_by_require(
+    scopes: &mut [VarScope],
+    scope_index: usize,
+) -> Result<Option<Vec<VarScope>>, PellucidError> {
+    let Scope::Condition {
+        instructions_if_true,
+        instructions_if_false,
+    } = &scopes[scope_index]
+    else {
+        return Ok(None);
+    };
+    let (error, reverts_if_true, kept_scopes): (Option<Value>, bool, Vec<VarScope>) = match (
+        get_revert_error(instructions_if_true),
+        get_revert_error(instructions_if_false),
+    ) {
+        (Some(error), None) => (error, true, instructions_if_false.clone()),
+        (None, Some(error)) => (error, false, instructions_if_true.clone()),
+        _ => return Ok(None), // nothing to flatten if both branches revert
+    };
+
+    let Scope::Instructions(instructions) = &mut scopes[scope_index - 1] else {
+        return Ok(None);
+    };
+    let Some(Line::If { condition }) = instructions.lines.pop() else {
+        return Err(PellucidError::ScopesMalformed(String::from(
+            "a condition scope does not follow an if line",
+        )));
+    };
+    let condition: Value = if reverts_if_true {
+        negate(condition)
+    } else {
+        condition
+    };
+    instructions.lines.push(Line::Require {
+        condition: remove_double_negations(condition),
+        error,
+    });
+    return Ok(Some(kept_scopes));
+}
+
+// The error of the revert (None if there is no revert data) if the scopes only revert. Panics
+// are the failures of compiler checks and are kept.
+fn get_revert_error(scopes: &[VarScope]) -> Option<Option<Value>> {
+    let mut error: Option<Option<Value>> = None;
+    for scope in scopes {
+        match scope {
+            Scope::Instructions(instructions) => {
+                for line in &instructions.lines {
+                    let Line::Assignement {
+                        receiving_var: None,
+                        assigned_value,
+                    } = line
+                    else {
+                        if line == &Line::Empty {
+                            continue;
+                        }
+                        return None;
+                    };
+                    if error.is_some() {
+                        return None;
+                    }
+                    match assigned_value {
+                        Value::Calculation {
+                            opcode: Opcode::REVERT,
+                            args,
+                        } if args[1].compute_value() == Some(U256::zero()) => error = Some(None),
+                        Value::Revert {
+                            error:
+                                RevertError::Message(_)
+                                | RevertError::Custom {
+                                    selector: _,
+                                    name: _,
+                                },
+                            arguments: _,
+                        } => error = Some(Some(assigned_value.clone())),
+                        _ => return None,
+                    }
+                }
+            }
+            Scope::Empty => (),
+            _ => return None,
+        }
+    }
+    return error;
+}
+
+// Only the truthiness of a condition matters, `!!(x)` is `x`.
+pub fn remove_double_negations(mut condition: Value) -> Value {
+    while let Value::Calculation {
+        opcode: Opcode::ISZERO,
+        args,
+    } = &condition
+    {
+        match &args[0] {
+            Value::Calculation {
+                opcode: Opcode::ISZERO,
+                args: negated_args,
+            } => condition = negated_args[0].clone(),
+            _ => break,
+        }
+    }
+    return condition;
+}
+
+pub fn negate(condition: Value) -> Value {
+    match condition {
+        Value::Calculation {
+            opcode: Opcode::ISZERO,
+            mut args,
+        } => return args.remove(0),
+        _ => {
+            return Value::Calculation {
+                opcode: Opcode::ISZERO,
+                args: vec![condition],
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompiler::Decompiler;
+    use crate::tools::utils::read_file;
+
+    fn calculation(opcode: Opcode, args: &[Value]) -> Value {
+        return Value::Calculation {
+            opcode,
+            args: args.to_vec(),
+        };
+    }
+
+    // The require lines, with the line following each of them.
+    fn get_requires(bytecode: &str) -> Vec<(Line, Option<Line>)> {
+        let flow: ExecutionFlowWithVars = Decompiler::from_hex(bytecode)
+            .unwrap()
+            .decompile()
+            .unwrap()
+            .execution_flow_with_vars;
+        let mut requires: Vec<(Line, Option<Line>)> = Vec::new();
+        flow.apply_on_all_scopes(&mut |scope| {
+            if let Scope::Instructions(instructions) = scope {
+                for (line_index, line) in instructions.lines.iter().enumerate() {
+                    if let Line::Require {
+                        condition: _,
+                        error: _,
+                    } = line
+                    {
+                        requires.push((
+                            line.clone(),
+                            instructions.lines.get(line_index + 1).cloned(),
+                        ));
+                    }
+                }
+            }
+        });
+        return requires;
+    }
+
+    #[test]
+    fn test_recognise_requires() {
+        // the non-payable check
+        let requires: Vec<(Line, Option<Line>)> =
+            get_requires(&read_file("./contracts/simple/contract_0/bytecode.txt"));
+        assert!(requires.iter().any(|(require, _)| require
+            == &Line::Require {
+                condition: calculation(Opcode::ISZERO, &[calculation(Opcode::CALLVALUE, &[])]),
+                error: None,
+            }));
+
+        // reverts with "hello" if calldata[0] is zero, then storage[0] = 1
+        let requires: Vec<(Line, Option<Line>)> = get_requires(
+            "600035604c57604051806308c379a060e01b815260208160040152600581602401527f68656c6c6f0000000000000000000000000000000000000000000000000000008160440152606490fd5b600160005500",
+        );
+        assert_eq!(
+            requires,
+            vec![(
+                Line::Require {
+                    condition: calculation(Opcode::CALLDATALOAD, &[Value::Bytes(U256::zero())]),
+                    error: Some(Value::Revert {
+                        error: RevertError::Message(String::from("hello")),
+                        arguments: Vec::new(),
+                    }),
+                },
+                Some(Line::Assignement {
+                    receiving_var: None,
+                    assigned_value: calculation(
+                        Opcode::SSTORE,
+                        &[Value::Bytes(U256::zero()), Value::Bytes(U256::one())]
+                    ),
+                })
+            )]
+        );
+    }
+}
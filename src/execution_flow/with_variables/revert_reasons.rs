@@ -0,0 +1,643 @@
+use std::collections::{HashMap, HashSet};
+
+use primitive_types::U256;
+
+use super::{
+    abi_parameters::apply_on_values,
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::{RevertError, Value, Variable, VariablesStack},
+    scopes_with_vars::{FunctionCallWithVars, FunctionKind, FunctionWithVars, VarScope},
+    simplify::{
+        initialization_of_vars::enumerate_var_initializations, rename::rename_variables_in_scopes,
+        replace_var::replace_vars_until_second_assignment,
+    },
+    storage_variables::{
+        reads_memory, remove_lines_in_scopes, track_memory_in_scopes, LineId, MemoryTracker,
+    },
+};
+use crate::{
+    bytecode_reader::opcode::Opcode,
+    execution_flow::execution_flow::{FunctionLabel, Scope},
+};
+
+const FREE_MEMORY_POINTER: u64 = 0x40;
+const ERROR_SELECTOR: u32 = 0x08c379a0; // Error(string)
+const PANIC_SELECTOR: u32 = 0x4e487b71; // Panic(uint256)
+
+// Addresses of memory are constant or relative to a pointer.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum MemoryBase {
+    Constant,
+    FreeMemoryPointer(usize), // the value of `memory[0x40]` between two of its updates
+    Var(Variable),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+struct MemoryWrite {
+    value: Value,
+    line_id: LineId,
+    valid_bytes: usize, // the next writes may overwrite the end of the word
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Memory {
+    words: HashMap<(MemoryBase, U256), MemoryWrite>,
+    free_memory_pointer: usize,
+}
+
+// Decodes a value from the data written in memory, returning the lines writing the data.
+pub type DataDecoder = fn(&mut Value, &Memory, &MemoryDecoding) -> Option<Vec<LineId>>;
+
+pub struct MemoryDecoding {
+    decode_data: DataDecoder,
+    next_free_memory_pointer: usize,
+    n_initializations_per_var: HashMap<Variable, usize>,
+    free_memory_pointer_vars: HashMap<Variable, (usize, LineId)>,
+    decoded_writes: HashSet<LineId>,
+    read_writes: HashSet<LineId>, // read for something else than a revert or a log
+}
+
+impl ExecutionFlowWithVars {
+    // Revert data is an ABI encoded error: `Error(string)` for `require` and `revert` messages,
+    // `Panic(uint256)` for failed checks, or a custom error. It is written in memory just before
+    // reverting, either in the scratch space or at the free memory pointer.
+    pub fn decode_reverts(&mut self) {
+        loop {
+            for function in self.functions.values_mut() {
+                decode_memory_data_in_scopes(&mut function.content, decode_revert);
+            }
+            // Solidity encodes the data in helper functions, called once the selector is written.
+            // They are inlined until the whole data is written in the reverting function.
+            let n_functions: usize = self.functions.len();
+            self.remove_functions(
+                |function: &FunctionWithVars, _| !is_revert_helper(function),
+                replace_function_call_by_renamed_content_in_scopes,
+            );
+            if self.functions.len() == n_functions {
+                return;
+            }
+        }
+    }
+}
+
+// A function without branches that reverts with data it cannot decode alone.
+fn is_revert_helper(function: &FunctionWithVars) -> bool {
+    if function.kind != FunctionKind::Internal
+        || ExecutionFlowWithVars::is_recursive(function)
+        || !function.content.iter().all(|scope| {
+            matches!(
+                scope,
+                Scope::Instructions(_) | Scope::FunctionCall(_) | Scope::Empty
+            )
+        })
+    {
+        return false;
+    }
+    let mut reverts_with_data: bool = false;
+    apply_on_values(&function.content, &mut |value: &Value| {
+        if let Value::Calculation {
+            opcode: Opcode::REVERT,
+            args,
+        } = value
+        {
+            reverts_with_data = reverts_with_data || args[1].compute_value() != Some(U256::zero());
+        }
+    });
+    return reverts_with_data;
+}
+
+// The variables of the function are renamed at each call, so that they keep a single
+// initialization.
+fn replace_function_call_by_renamed_content_in_scopes(
+    scopes: &mut Vec<VarScope>,
+    label_to_replace: FunctionLabel,
+    function: &FunctionWithVars,
+) {
+    let mut variables: VariablesStack = VariablesStack::new();
+    let initialized_vars: Vec<Variable> = enumerate_var_initializations(&function.content)
+        .into_iter()
+        .filter(|(var, n_initializations)| {
+            *n_initializations > 0 && !function.input_vars.contains(var)
+        })
+        .map(|(var, _)| var)
+        .collect();
+    replace_function_call_in_scopes(scopes, label_to_replace, &mut |function_call| {
+        let var_mapping: HashMap<Variable, Variable> = initialized_vars
+            .iter()
+            .map(|var| (*var, variables.create_single_variable()))
+            .collect();
+        let mut content: Vec<VarScope> = function.content.clone();
+        rename_variables_in_scopes(&mut content, &var_mapping);
+        let arguments: HashMap<Variable, Value> = function
+            .input_vars
+            .iter()
+            .copied()
+            .zip(function_call.arguments.iter().cloned())
+            .collect();
+        replace_vars_until_second_assignment(&mut content, &arguments);
+        return content;
+    });
+}
+
+fn replace_function_call_in_scopes(
+    scopes: &mut Vec<VarScope>,
+    label_to_replace: FunctionLabel,
+    get_content: &mut impl FnMut(&FunctionCallWithVars) -> Vec<VarScope>,
+) {
+    for scope_index in (0..scopes.len()).rev() {
+        match &mut scopes[scope_index] {
+            Scope::FunctionCall(function_call) if function_call.label == label_to_replace => {
+                let content: Vec<VarScope> = get_content(function_call);
+                scopes.splice(scope_index..scope_index + 1, content);
+            }
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => {
+                replace_function_call_in_scopes(
+                    instructions_if_true,
+                    label_to_replace,
+                    get_content,
+                );
+                replace_function_call_in_scopes(
+                    instructions_if_false,
+                    label_to_replace,
+                    get_content,
+                );
+            }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                replace_function_call_in_scopes(header, label_to_replace, get_content);
+                replace_function_call_in_scopes(body, label_to_replace, get_content);
+            }
+            _ => (),
+        }
+    }
+}
+
+// The lines writing the decoded data are removed, unless the data is also read elsewhere.
+pub fn decode_memory_data_in_scopes(scopes: &mut Vec<VarScope>, decode_data: DataDecoder) {
+    let mut decoding: MemoryDecoding = MemoryDecoding {
+        decode_data,
+        next_free_memory_pointer: 1,
+        n_initializations_per_var: enumerate_var_initializations(scopes),
+        free_memory_pointer_vars: HashMap::new(),
+        decoded_writes: HashSet::new(),
+        read_writes: HashSet::new(),
+    };
+    let mut memory: Memory = Memory {
+        words: HashMap::new(),
+        free_memory_pointer: 0,
+    };
+    track_memory_in_scopes(scopes, &mut memory, &mut decoding, &mut 0);
+    if decoding.decoded_writes.is_empty() {
+        return;
+    }
+
+    let lines_to_remove: HashSet<LineId> = decoding
+        .decoded_writes
+        .difference(&decoding.read_writes)
+        .copied()
+        .collect();
+    remove_lines_in_scopes(scopes, &lines_to_remove, &mut 0);
+    // the loads of the free memory pointer may not be used anymore
+    let mut used_vars: HashSet<Variable> = HashSet::new();
+    apply_on_values(scopes, &mut |value: &Value| {
+        if let Value::Existing(var) = value {
+            used_vars.insert(*var);
+        }
+    });
+    let lines_to_remove: HashSet<LineId> = decoding
+        .free_memory_pointer_vars
+        .iter()
+        .filter(|(var, _)| !used_vars.contains(var))
+        .map(|(_, (_, line_id))| *line_id)
+        .collect();
+    remove_lines_in_scopes(scopes, &lines_to_remove, &mut 0);
+}
+
+impl MemoryTracker for MemoryDecoding {
+    type Memory = Memory;
+
+    fn visit_value(&mut self, value: &mut Value, memory: &mut Memory) {
+        decode_memory_data(value, memory, self);
+    }
+
+    fn visit_assignment(
+        &mut self,
+        receiving_var: Option<Variable>,
+        assigned_value: &Value,
+        line_id: LineId,
+        memory: &mut Memory,
+    ) {
+        update_memory(receiving_var, assigned_value, line_id, memory, self);
+    }
+
+    fn read_memory(&mut self, memory: &Memory) {
+        self.read_writes
+            .extend(memory.words.values().map(|write| write.line_id));
+    }
+
+    fn forget_memory(&mut self, memory: &mut Memory) {
+        clear_memory(memory, self);
+    }
+}
+
+fn clear_memory(memory: &mut Memory, decoding: &mut MemoryDecoding) {
+    memory.words.clear();
+    memory.free_memory_pointer = decoding.next_free_memory_pointer;
+    decoding.next_free_memory_pointer += 1;
+}
+
+fn update_memory(
+    receiving_var: Option<Variable>,
+    assigned_value: &Value,
+    line_id: LineId,
+    memory: &mut Memory,
+    decoding: &mut MemoryDecoding,
+) {
+    let (opcode, args) = match assigned_value {
+        Value::Calculation { opcode, args } => (*opcode, args),
+        _ => return,
+    };
+    // the returned data is written in memory
+    if matches!(
+        opcode,
+        Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL | Opcode::STATICCALL
+    ) {
+        return clear_memory(memory, decoding);
+    }
+    // `var = memory[0x40]`
+    if let Some(var) = receiving_var {
+        if opcode == Opcode::MLOAD
+            && args[0].compute_value() == Some(U256::from(FREE_MEMORY_POINTER))
+            && decoding.n_initializations_per_var[&var] == 1
+        {
+            decoding
+                .free_memory_pointer_vars
+                .insert(var, (memory.free_memory_pointer, line_id));
+        }
+        return;
+    }
+
+    let (destination, size): (&Value, Option<U256>) = match opcode {
+        Opcode::MSTORE => (&args[0], Some(U256::from(0x20))),
+        Opcode::MSTORE8 => (&args[0], Some(U256::one())),
+        Opcode::CALLDATACOPY
+        | Opcode::CODECOPY
+        | Opcode::RETURNDATACOPY
+        | Opcode::MCOPY
+        | Opcode::DATACOPY => (&args[0], args[2].compute_value()),
+        Opcode::EXTCODECOPY => (&args[1], args[3].compute_value()),
+        _ => return,
+    };
+    let (base, offset) = match (resolve_address(destination, decoding), size) {
+        (Some(address), Some(size)) if size <= U256::from(u32::MAX) => {
+            overwrite_memory(memory, address, size.as_usize());
+            address
+        }
+        _ => return clear_memory(memory, decoding),
+    };
+    if opcode == Opcode::MSTORE {
+        memory.words.insert(
+            (base, offset),
+            MemoryWrite {
+                value: args[1].clone(),
+                line_id,
+                valid_bytes: 0x20,
+            },
+        );
+        if base == MemoryBase::Constant && offset == U256::from(FREE_MEMORY_POINTER) {
+            memory.free_memory_pointer = decoding.next_free_memory_pointer;
+            decoding.next_free_memory_pointer += 1;
+        }
+    }
+}
+
+// Words written at other bases are assumed to be elsewhere in memory.
+fn overwrite_memory(memory: &mut Memory, (base, offset): (MemoryBase, U256), size: usize) {
+    let end: U256 = offset.saturating_add(U256::from(size));
+    memory
+        .words
+        .retain(|(word_base, word_offset), write: &mut MemoryWrite| {
+            let word_end: U256 = word_offset.saturating_add(U256::from(write.valid_bytes));
+            if *word_base != base || end <= *word_offset || word_end <= offset {
+                return true;
+            }
+            if offset > *word_offset {
+                write.valid_bytes = (offset - *word_offset).as_usize();
+                return true;
+            }
+            return false;
+        });
+}
+
+pub fn resolve_address(value: &Value, decoding: &MemoryDecoding) -> Option<(MemoryBase, U256)> {
+    if let Some(constant) = value.compute_value() {
+        return Some((MemoryBase::Constant, constant));
+    }
+    match value {
+        Value::Existing(var) => {
+            if let Some((free_memory_pointer, _)) = decoding.free_memory_pointer_vars.get(var) {
+                return Some((
+                    MemoryBase::FreeMemoryPointer(*free_memory_pointer),
+                    U256::zero(),
+                ));
+            }
+            if decoding.n_initializations_per_var.get(var) == Some(&1) {
+                return Some((MemoryBase::Var(*var), U256::zero()));
+            }
+            return None;
+        }
+        Value::Calculation {
+            opcode: Opcode::ADD,
+            args,
+        } => {
+            let (base_0, offset_0) = resolve_address(&args[0], decoding)?;
+            let (base_1, offset_1) = resolve_address(&args[1], decoding)?;
+            let base: MemoryBase = match (base_0, base_1) {
+                (MemoryBase::Constant, base) | (base, MemoryBase::Constant) => base,
+                _ => return None,
+            };
+            return Some((base, offset_0.overflowing_add(offset_1).0));
+        }
+        // the size of the data between two addresses
+        Value::Calculation {
+            opcode: Opcode::SUB,
+            args,
+        } => {
+            let (base_0, offset_0) = resolve_address(&args[0], decoding)?;
+            let (base_1, offset_1) = resolve_address(&args[1], decoding)?;
+            let base: MemoryBase = match (base_0, base_1) {
+                _ if base_0 == base_1 => MemoryBase::Constant,
+                (base, MemoryBase::Constant) => base,
+                _ => return None,
+            };
+            return Some((base, offset_0.overflowing_sub(offset_1).0));
+        }
+        _ => return None,
+    }
+}
+
+fn decode_memory_data(value: &mut Value, memory: &Memory, decoding: &mut MemoryDecoding) {
+    if let Some(decoded_writes) = (decoding.decode_data)(value, memory, decoding) {
+        decoding.decoded_writes.extend(decoded_writes);
+        return;
+    }
+    let (opcode, args) = match value {
+        Value::Calculation { opcode, args } => (*opcode, args),
+        _ => return,
+    };
+    // a load only reads the words it overlaps
+    if opcode == Opcode::MLOAD {
+        if let Some((base, offset)) = resolve_address(&args[0], decoding) {
+            let end: U256 = offset.saturating_add(U256::from(0x20));
+            decoding.read_writes.extend(
+                memory
+                    .words
+                    .iter()
+                    .filter(|((word_base, word_offset), write)| {
+                        *word_base == base
+                            && *word_offset < end
+                            && offset < word_offset.saturating_add(U256::from(write.valid_bytes))
+                    })
+                    .map(|(_, write)| write.line_id),
+            );
+            return;
+        }
+    }
+    if reads_memory(opcode) {
+        decoding.read_memory(memory);
+    }
+}
+
+// The size of the data, if it is constant.
+pub fn get_data_size(size: &Value, decoding: &MemoryDecoding) -> Option<usize> {
+    match resolve_address(size, decoding)? {
+        (MemoryBase::Constant, size) if size <= U256::from(u32::MAX) => {
+            return Some(size.as_usize())
+        }
+        _ => return None,
+    }
+}
+
+// The words fully written from the address, with the lines writing them.
+pub fn read_words(
+    memory: &Memory,
+    (base, start): (MemoryBase, U256),
+    n_words: usize,
+) -> Option<(Vec<Value>, Vec<LineId>)> {
+    let mut words: Vec<Value> = Vec::new();
+    let mut decoded_writes: Vec<LineId> = Vec::new();
+    for index in 0..n_words {
+        let offset: U256 = start.overflowing_add(U256::from(0x20 * index)).0;
+        let write: &MemoryWrite = memory.words.get(&(base, offset))?;
+        if write.valid_bytes < 0x20 {
+            return None;
+        }
+        words.push(write.value.clone());
+        decoded_writes.push(write.line_id);
+    }
+    return Some((words, decoded_writes));
+}
+
+fn decode_revert(
+    value: &mut Value,
+    memory: &Memory,
+    decoding: &MemoryDecoding,
+) -> Option<Vec<LineId>> {
+    let Value::Calculation {
+        opcode: Opcode::REVERT,
+        args,
+    } = value
+    else {
+        return None;
+    };
+    let (error, arguments, decoded_writes) = get_revert_error(args, memory, decoding)?;
+    *value = Value::Revert { error, arguments };
+    return Some(decoded_writes);
+}
+
+fn get_revert_error(
+    args: &[Value],
+    memory: &Memory,
+    decoding: &MemoryDecoding,
+) -> Option<(RevertError, Vec<Value>, Vec<LineId>)> {
+    let (base, start) = resolve_address(&args[0], decoding)?;
+    let size: usize = get_data_size(&args[1], decoding)?;
+    if size < 4 || (size - 4) % 0x20 != 0 {
+        return None;
+    }
+    let selector_write: &MemoryWrite = memory.words.get(&(base, start))?;
+    if selector_write.valid_bytes < 4 {
+        return None;
+    }
+    // the selector is written alone in its word, the data that follows overwrites the rest
+    let selector_word: U256 = selector_write.value.compute_value()?;
+    if selector_word.is_zero() || !(selector_word << 32).is_zero() {
+        return None;
+    }
+    let selector: u32 = (selector_word >> 224).as_u32();
+
+    let n_words: usize = (size - 4) / 0x20;
+    let (words, mut decoded_writes) = read_words(
+        memory,
+        (base, start.overflowing_add(U256::from(4)).0),
+        n_words,
+    )?;
+    decoded_writes.insert(0, selector_write.line_id);
+
+    match selector {
+        ERROR_SELECTOR => {
+            // offset of the message, its length and its content
+            if n_words < 2 || words[0].compute_value()? != U256::from(0x20) {
+                return None;
+            }
+            let length: U256 = words[1].compute_value()?;
+            if length > U256::from(0x20 * (n_words - 2)) {
+                return None;
+            }
+            let mut bytes: Vec<u8> = Vec::new();
+            for word in &words[2..] {
+                let mut word_bytes: [u8; 32] = [0; 32];
+                word.compute_value()?.to_big_endian(&mut word_bytes);
+                bytes.extend(word_bytes);
+            }
+            bytes.truncate(length.as_usize());
+            let message: String = String::from_utf8(bytes).ok()?;
+            return Some((RevertError::Message(message), Vec::new(), decoded_writes));
+        }
+        PANIC_SELECTOR => {
+            if n_words != 1 {
+                return None;
+            }
+            let code: U256 = words[0].compute_value()?;
+            return Some((RevertError::Panic(code), Vec::new(), decoded_writes));
+        }
+        _ => {
+            return Some((
+                RevertError::Custom {
+                    selector,
+                    name: None,
+                },
+                words,
+                decoded_writes,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompiler::Decompiler;
+    use crate::tools::utils::read_file;
+
+    fn calculation(opcode: Opcode, args: &[Value]) -> Value {
+        return Value::Calculation {
+            opcode,
+            args: args.to_vec(),
+        };
+    }
+
+    // The decoded reverts, and the words still written in memory.
+    fn decode(bytecode: &str) -> (Vec<Value>, Vec<Value>) {
+        let decompiler: Decompiler = Decompiler::from_hex(bytecode).unwrap();
+        let flow: ExecutionFlowWithVars = decompiler.decompile().unwrap().execution_flow_with_vars;
+        let mut reverts: Vec<Value> = Vec::new();
+        let mut written_words: Vec<Value> = Vec::new();
+        for function in flow.functions.values() {
+            apply_on_values(&function.content, &mut |value: &Value| match value {
+                Value::Revert {
+                    error: _,
+                    arguments: _,
+                } => reverts.push(value.clone()),
+                Value::Calculation {
+                    opcode: Opcode::MSTORE,
+                    args,
+                } => written_words.push(args[1].clone()),
+                _ => (),
+            });
+        }
+        return (reverts, written_words);
+    }
+
+    #[test]
+    fn test_decode_reverts() {
+        // revert Error("hello") through the free memory pointer
+        let (reverts, written_words) = decode(
+            "604051806308c379a060e01b815260208160040152600581602401527f68656c6c6f0000000000000000000000000000000000000000000000000000008160440152606490fd",
+        );
+        assert_eq!(
+            reverts,
+            vec![Value::Revert {
+                error: RevertError::Message(String::from("hello")),
+                arguments: Vec::new(),
+            }]
+        );
+        assert!(written_words.is_empty());
+
+        // revert Panic(0x11)
+        let (reverts, _) = decode("634e487b7160e01b600052601160045260246000fd");
+        assert_eq!(
+            reverts,
+            vec![Value::Revert {
+                error: RevertError::Panic(U256::from(0x11)),
+                arguments: Vec::new(),
+            }]
+        );
+
+        // revert with the custom error 0xdeadbeef and calldata[4] as argument
+        let (reverts, written_words) = decode("63deadbeef60e01b60005260043560045260246000fd");
+        assert_eq!(
+            reverts,
+            vec![Value::Revert {
+                error: RevertError::Custom {
+                    selector: 0xdeadbeef,
+                    name: None
+                },
+                arguments: vec![calculation(
+                    Opcode::CALLDATALOAD,
+                    &[Value::Bytes(U256::from(4))]
+                )],
+            }]
+        );
+        assert!(written_words.is_empty());
+
+        // the first word is not shifted, its first 4 bytes are not a selector
+        let (reverts, _) = decode("63deadbeef60005260043560045260246000fd");
+        assert!(reverts.is_empty());
+    }
+
+    #[test]
+    fn test_decode_reverts_of_helpers() {
+        // the selector is written by the reverting function, the message by a helper
+        let (reverts, written_words) = decode(&read_file("./contracts/complex/bytecode.txt"));
+        let error_selector: U256 = U256::from(ERROR_SELECTOR) << 224;
+        assert!(!written_words
+            .iter()
+            .any(|word| word.compute_value() == Some(error_selector)));
+        let messages: HashSet<String> = reverts
+            .into_iter()
+            .filter_map(|revert| match revert {
+                Value::Revert {
+                    error: RevertError::Message(message),
+                    arguments: _,
+                } => Some(message),
+                _ => None,
+            })
+            .collect();
+        for message in [
+            "Ownable: caller is not the owner",
+            "Ownable: new owner is the zero address",
+            "ReentrancyGuard: reentrant call",
+            "Initializable: contract is already initialized",
+        ] {
+            assert!(messages.contains(message));
+        }
+    }
+}
@@ -1,4 +1,9 @@
-use super::incorporate_variables::{Line, Value, Variable};
+use std::collections::HashMap;
+
+use super::{
+    incorporate_variables::{Line, Value, Variable},
+    type_inference::VarType,
+};
 use crate::execution_flow::execution_flow::{
     AccessContent, FunctionLabel, GetFunctionLabel, Length, Scope,
 };
@@ -22,9 +27,24 @@ impl Length for InstructionsWithVars {
     }
 }
 
+// Entry points come first when displayed, in this order.
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum FunctionKind {
+    Main, // the whole contract, when no dispatcher is recognised
+    External { selector: u32 },
+    Receive,
+    Fallback, // what remains of `main` once the external functions are extracted
+    JumpTarget { pc: usize }, // a JUMPDEST only reached by jumps whose destination is unknown
+    Internal,
+}
+
 #[derive(Clone, Eq, Debug)]
 pub struct FunctionWithVars {
     pub label: FunctionLabel,
+    pub kind: FunctionKind,
+    pub signature: Option<String>, // from the signature database, for external functions
+    pub parameters: Vec<String>,   // ABI types of the arguments of external functions
+    pub var_types: HashMap<Variable, VarType>, // only the inferred ones
     pub input_vars: Vec<Variable>,
     pub n_outputs: usize,
     pub returns: bool, // true
@@ -46,6 +66,10 @@ impl FunctionWithVars {
     pub fn n_parameters(&self) -> usize {
         return self.input_vars.len();
     }
+
+    pub fn is_entry_point(&self) -> bool {
+        return self.kind != FunctionKind::Internal;
+    }
 }
 
 impl AccessContent<InstructionsWithVars, FunctionCallWithVars, FunctionReturnWithVars>
@@ -0,0 +1,432 @@
+use std::collections::{HashMap, HashSet};
+
+use primitive_types::U256;
+
+use super::{
+    abi_parameters::{apply_on_values, apply_on_values_mut},
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::{Line, ParameterField, RevertError, Value},
+    scopes_with_vars::{FunctionKind, FunctionWithVars, VarScope},
+};
+use crate::{
+    bytecode_reader::opcode::Opcode,
+    execution_flow::execution_flow::Scope,
+    signatures::signature_database::{split_signature, SignatureDatabase},
+};
+
+impl ExecutionFlowWithVars {
+    // Names external functions, event topics, custom errors and selectors (calls to other
+    // contracts) that are found in the database. Names that are already set are kept, so that
+    // several databases can be applied one after the other.
+    pub fn apply_signatures(&mut self, signatures: &SignatureDatabase) {
+        for function in self.functions.values_mut() {
+            if let FunctionKind::External { selector } = function.kind {
+                // the parameters are mapped onto the signature only once
+                if function.signature.is_none() {
+                    if let Some(signature) = signatures.get_selector_signature(selector) {
+                        function.signature = Some(signature.clone());
+                        // the types of the signature are more precise than the inferred ones
+                        let (_, types) = split_signature(signature);
+                        apply_parameter_types(function, &types);
+                    }
+                }
+            }
+            apply_signatures_in_scopes(&mut function.content, signatures);
+            name_call_selectors(&mut function.content, signatures);
+        }
+    }
+}
+
+// A static array or tuple takes one head per word in calldata, so the decoded heads are mapped
+// onto the parameters of the signature before taking its types.
+fn apply_parameter_types(function: &mut FunctionWithVars, types: &[&str]) {
+    let sizes: Vec<Option<usize>> = types.iter().map(|type_| get_static_size(type_)).collect();
+    // head index => (parameter index, word in the parameter)
+    let mut heads: Vec<(usize, usize)> = Vec::new();
+    for (index, size) in sizes.iter().enumerate() {
+        heads.extend((0..size.unwrap_or(1)).map(|word| (index, word)));
+    }
+    if heads.len() < function.parameters.len() {
+        return; // the signature does not match the calldata that is read
+    }
+    if heads.len() != types.len() {
+        apply_on_values_mut(&mut function.content, &mut |value: &mut Value| {
+            if let Value::Parameter { index, field } = value {
+                let (parameter_index, word): (usize, usize) = heads[*index];
+                *index = parameter_index;
+                if *field == ParameterField::Word && sizes[parameter_index].unwrap_or(1) > 1 {
+                    *field = ParameterField::Element(word);
+                }
+            }
+        });
+    }
+    function.parameters = types.iter().map(|type_| type_.to_string()).collect();
+}
+
+// Number of words of a static ABI type, `None` for dynamic types that only have an offset in the
+// head.
+fn get_static_size(type_: &str) -> Option<usize> {
+    if let Some(array) = type_.strip_suffix(']') {
+        let bracket: usize = array.rfind('[')?;
+        let length: usize = array[bracket + 1..].parse().ok()?;
+        return Some(length * get_static_size(&array[..bracket])?);
+    }
+    if type_.starts_with('(') {
+        let (_, components) = split_signature(type_);
+        let mut size: usize = 0;
+        for component in components {
+            size += get_static_size(component)?;
+        }
+        return Some(size);
+    }
+    if type_ == "bytes" || type_ == "string" {
+        return None;
+    }
+    return Some(1);
+}
+
+fn apply_signatures_in_scopes(scopes: &mut Vec<VarScope>, signatures: &SignatureDatabase) {
+    for scope in scopes {
+        match scope {
+            Scope::Instructions(instructions) => {
+                for line in &mut instructions.lines {
+                    match line {
+                        Line::Assignement {
+                            receiving_var: _,
+                            assigned_value,
+                        } => apply_signatures_in_value(assigned_value, signatures),
+                        Line::If { condition } => apply_signatures_in_value(condition, signatures),
+                        Line::DynamicJump {
+                            condition,
+                            destination,
+                        } => {
+                            if let Some(jump_condition) = condition {
+                                apply_signatures_in_value(jump_condition, signatures);
+                            }
+                            apply_signatures_in_value(destination, signatures);
+                        }
+                        Line::Require { condition, error } => {
+                            apply_signatures_in_value(condition, signatures);
+                            if let Some(revert_error) = error {
+                                apply_signatures_in_value(revert_error, signatures);
+                            }
+                        }
+                        Line::Empty => (),
+                    }
+                }
+            }
+            Scope::FunctionCall(function_call) => {
+                for argument in &mut function_call.arguments {
+                    apply_signatures_in_value(argument, signatures);
+                }
+            }
+            Scope::FunctionReturn(function_return) => {
+                for returned_value in &mut function_return.returned_values {
+                    apply_signatures_in_value(returned_value, signatures);
+                }
+            }
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => {
+                apply_signatures_in_scopes(instructions_if_true, signatures);
+                apply_signatures_in_scopes(instructions_if_false, signatures);
+            }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                apply_signatures_in_scopes(header, signatures);
+                apply_signatures_in_scopes(body, signatures);
+            }
+            _ => (),
+        }
+    }
+}
+
+fn apply_signatures_in_value(value: &mut Value, signatures: &SignatureDatabase) {
+    match value {
+        Value::Calculation { opcode, args } => {
+            // the first topic of a log is the hash of the event signature
+            if let Opcode::LOG { topic_count: 1.. } = opcode {
+                if let Value::Bytes(topic) = args[2] {
+                    if let Some(event) = signatures.get_event_signature(topic) {
+                        args[2] = Value::Named {
+                            bytes: topic,
+                            name: event.clone(),
+                        };
+                    }
+                }
+            }
+            for arg in args {
+                apply_signatures_in_value(arg, signatures);
+            }
+        }
+        Value::FunctionReturnedValue {
+            label: _,
+            arguments,
+            return_index: _,
+        } => {
+            for argument in arguments {
+                apply_signatures_in_value(argument, signatures);
+            }
+        }
+        Value::StorageSlot { slot: _, keys } => {
+            for (_, key) in keys {
+                apply_signatures_in_value(key, signatures);
+            }
+        }
+        Value::Revert { error, arguments } => {
+            if let RevertError::Custom { selector, name } = error {
+                if let Some(signature) = signatures.get_selector_signature(*selector) {
+                    *name = Some(split_signature(signature).0.to_owned());
+                }
+            }
+            for argument in arguments {
+                apply_signatures_in_value(argument, signatures);
+            }
+        }
+        Value::Event { topics, data } => {
+            if let Some(Value::Bytes(topic)) = topics.first() {
+                if let Some(event) = signatures.get_event_signature(*topic) {
+                    topics[0] = Value::Named {
+                        bytes: *topic,
+                        name: event.clone(),
+                    };
+                }
+            }
+            for argument in topics.iter_mut().chain(data) {
+                apply_signatures_in_value(argument, signatures);
+            }
+        }
+        Value::Existing(_)
+        | Value::Bytes(_)
+        | Value::Named { bytes: _, name: _ }
+        | Value::Parameter { index: _, field: _ } => (),
+    }
+}
+
+// Selectors are stored in memory as the first 4 bytes of a word.
+fn is_selector_word(bytes: U256) -> bool {
+    return !bytes.is_zero() && (bytes << 32).is_zero();
+}
+
+// The selector of a call to another contract is the first word of its calldata, written in memory
+// where the call reads its arguments. Other words with empty low bytes (masks, `1 << 224`...) keep
+// their value.
+fn name_call_selectors(scopes: &mut Vec<VarScope>, signatures: &SignatureDatabase) {
+    let mut written_words: HashMap<Value, U256> = HashMap::new();
+    let mut selector_words: HashSet<U256> = HashSet::new();
+    apply_on_values(scopes, &mut |value: &Value| {
+        let Value::Calculation { opcode, args } = value else {
+            return;
+        };
+        let arguments_offset: &Value = match opcode {
+            Opcode::MSTORE => {
+                if let Value::Bytes(word) = args[1] {
+                    if is_selector_word(word) {
+                        written_words.insert(args[0].clone(), word);
+                    }
+                }
+                return;
+            }
+            Opcode::CALL | Opcode::CALLCODE => &args[3],
+            Opcode::DELEGATECALL | Opcode::STATICCALL => &args[2],
+            Opcode::EXTCALL | Opcode::EXTDELEGATECALL | Opcode::EXTSTATICCALL => &args[1],
+            _ => return,
+        };
+        if let Some(word) = written_words.get(arguments_offset) {
+            selector_words.insert(*word);
+        }
+    });
+
+    apply_on_values_mut(scopes, &mut |value: &mut Value| {
+        let Value::Calculation {
+            opcode: Opcode::MSTORE,
+            args,
+        } = value
+        else {
+            return;
+        };
+        let Value::Bytes(word) = args[1] else {
+            return;
+        };
+        if !selector_words.contains(&word) {
+            return;
+        }
+        if let Some(signature) = signatures.get_selector_signature((word >> 224).as_u32()) {
+            args[1] = Value::Named {
+                bytes: word,
+                name: format!("{}.selector", signature),
+            };
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompiler::Decompiler;
+    use crate::execution_flow::with_variables::{
+        incorporate_variables::Variable, scopes_with_vars::InstructionsWithVars,
+    };
+
+    fn calculation(opcode: Opcode, args: &[Value]) -> Value {
+        return Value::Calculation {
+            opcode,
+            args: args.to_vec(),
+        };
+    }
+
+    fn instructions_scope(assigned_values: Vec<Value>) -> Vec<VarScope> {
+        let lines: Vec<Line> = assigned_values
+            .into_iter()
+            .map(|assigned_value| Line::Assignement {
+                receiving_var: None,
+                assigned_value,
+            })
+            .collect();
+        return vec![Scope::Instructions(InstructionsWithVars { lines })];
+    }
+
+    #[test]
+    fn test_apply_signatures_twice() {
+        // storage[0] = calldata[0x24], storage[1] = calldata[0x44]
+        let decompiler: Decompiler =
+            Decompiler::from_hex("60003560e01c80631111111114601157005b60243560005560443560015500")
+                .unwrap();
+        let mut flow: ExecutionFlowWithVars =
+            decompiler.decompile().unwrap().execution_flow_with_vars;
+        let mut signatures: SignatureDatabase = SignatureDatabase::new();
+        signatures
+            .add_hashed_signature("0x11111111", "f(uint256[2],uint256)")
+            .unwrap();
+        flow.apply_signatures(&signatures);
+        flow.apply_signatures(&signatures);
+        flow.apply_signatures(&SignatureDatabase::new());
+
+        let function: &FunctionWithVars = flow
+            .functions
+            .values()
+            .find(|function| {
+                function.kind
+                    == FunctionKind::External {
+                        selector: 0x11111111,
+                    }
+            })
+            .unwrap();
+        assert_eq!(
+            function.signature,
+            Some(String::from("f(uint256[2],uint256)"))
+        );
+        assert_eq!(function.parameters, vec!["uint256[2]", "uint256"]);
+        let mut stored_values: Vec<Value> = Vec::new();
+        apply_on_values(&function.content, &mut |value: &Value| {
+            if let Value::Calculation {
+                opcode: Opcode::SSTORE,
+                args,
+            } = value
+            {
+                stored_values.push(args[1].clone());
+            }
+        });
+        assert_eq!(
+            stored_values,
+            vec![
+                Value::Parameter {
+                    index: 0,
+                    field: ParameterField::Element(1)
+                },
+                Value::Parameter {
+                    index: 1,
+                    field: ParameterField::Word
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_name_call_selectors() {
+        let mut signatures: SignatureDatabase = SignatureDatabase::new();
+        for (selector, signature) in [
+            ("0xa9059cbb", "transfer(address,uint256)"),
+            ("0xffffffff", "f()"),
+            ("0x00000001", "g()"),
+        ] {
+            signatures
+                .add_hashed_signature(selector, signature)
+                .unwrap();
+        }
+        let pointer: Value = Value::Existing(Variable { alias: 0 });
+        let transfer_word: U256 = U256::from(0xa9059cbbu32) << 224;
+        let mask: U256 = U256::from(u32::MAX) << 224;
+        let bytes = |value: u64| -> Value { Value::Bytes(U256::from(value)) };
+        let call: Value = calculation(
+            Opcode::CALL,
+            &[
+                calculation(Opcode::GAS, &[]),
+                calculation(Opcode::CALLER, &[]),
+                bytes(0),
+                pointer.clone(),
+                bytes(0x44),
+                bytes(0),
+                bytes(0x20),
+            ],
+        );
+        let mut scopes: Vec<VarScope> = instructions_scope(vec![
+            calculation(
+                Opcode::MSTORE,
+                &[pointer.clone(), Value::Bytes(transfer_word)],
+            ),
+            calculation(Opcode::MSTORE, &[bytes(0), Value::Bytes(mask)]),
+            calculation(
+                Opcode::SSTORE,
+                &[bytes(0), Value::Bytes(U256::one() << 224)],
+            ),
+            call,
+        ]);
+        name_call_selectors(&mut scopes, &signatures);
+
+        let Scope::Instructions(instructions) = &scopes[0] else {
+            panic!("The lines should stay in one scope.");
+        };
+        let assigned_values: Vec<&Value> = instructions
+            .lines
+            .iter()
+            .map(|line| match line {
+                Line::Assignement {
+                    receiving_var: _,
+                    assigned_value,
+                } => assigned_value,
+                _ => panic!("Only assignments are written."),
+            })
+            .collect();
+        assert_eq!(
+            assigned_values[0],
+            &calculation(
+                Opcode::MSTORE,
+                &[
+                    pointer,
+                    Value::Named {
+                        bytes: transfer_word,
+                        name: String::from("transfer(address,uint256).selector")
+                    }
+                ]
+            )
+        );
+        // the mask is written in memory, but not read by the call
+        assert_eq!(
+            assigned_values[1],
+            &calculation(Opcode::MSTORE, &[bytes(0), Value::Bytes(mask)])
+        );
+        assert_eq!(
+            assigned_values[2],
+            &calculation(
+                Opcode::SSTORE,
+                &[bytes(0), Value::Bytes(U256::one() << 224)]
+            )
+        );
+    }
+}
@@ -19,6 +19,21 @@ pub fn count_uses_per_var_in_line(line: &Line, uses_per_var: &mut HashMap<Variab
             count_uses_per_var_in_value(assigned_value, uses_per_var);
         }
         Line::If { condition } => count_uses_per_var_in_value(condition, uses_per_var),
+        Line::DynamicJump {
+            condition,
+            destination,
+        } => {
+            if let Some(_condition) = condition {
+                count_uses_per_var_in_value(_condition, uses_per_var);
+            }
+            count_uses_per_var_in_value(destination, uses_per_var);
+        }
+        Line::Require { condition, error } => {
+            count_uses_per_var_in_value(condition, uses_per_var);
+            if let Some(_error) = error {
+                count_uses_per_var_in_value(_error, uses_per_var);
+            }
+        }
         Line::Empty => (),
     }
 }
@@ -33,16 +48,32 @@ pub fn count_uses_per_var_in_value(value: &Value, uses_per_var: &mut HashMap<Var
         Value::Existing(var) => {
             *uses_per_var.entry(*var).or_insert(0) += 1;
         }
-        Value::Bytes(_) => (),
+        Value::Bytes(_)
+        | Value::Named { bytes: _, name: _ }
+        | Value::Parameter { index: _, field: _ } => (),
         Value::FunctionReturnedValue {
             label: _,
             arguments,
             return_index: _,
+        }
+        | Value::Revert {
+            error: _,
+            arguments,
         } => {
             for arg_value in arguments {
                 count_uses_per_var_in_value(arg_value, uses_per_var);
             }
         }
+        Value::Event { topics, data } => {
+            for arg_value in topics.iter().chain(data) {
+                count_uses_per_var_in_value(arg_value, uses_per_var);
+            }
+        }
+        Value::StorageSlot { slot: _, keys } => {
+            for (_, key) in keys {
+                count_uses_per_var_in_value(key, uses_per_var);
+            }
+        }
     }
 }
 
@@ -86,6 +117,15 @@ fn find_variable_depth(
                 current_depth =
                     find_variable_depth(instructions_if_false, depth_per_variable, current_depth);
             }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                current_depth = find_variable_depth(header, depth_per_variable, current_depth);
+                current_depth = find_variable_depth(body, depth_per_variable, current_depth);
+            }
             _ => (),
         }
     }
@@ -28,6 +28,7 @@ fn _enumerate_var_initializations(
         match scope {
             Scope::Loop { label: _ } => (),
             Scope::LoopContinue { label: _ } => (),
+            Scope::LoopBreak { label: _ } => (),
             Scope::Panic => (),
             Scope::Instructions(var_instructions) => {
                 for line in &var_instructions.lines {
@@ -53,6 +54,15 @@ fn _enumerate_var_initializations(
                 _enumerate_var_initializations(instructions_if_true, n_initializations_per_var);
                 _enumerate_var_initializations(instructions_if_false, n_initializations_per_var);
             }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                _enumerate_var_initializations(header, n_initializations_per_var);
+                _enumerate_var_initializations(body, n_initializations_per_var);
+            }
             Scope::Empty => (),
         }
     }
@@ -74,6 +84,21 @@ pub fn find_vars_used_in_scopes(scopes: &Vec<VarScope>) -> HashSet<Variable> {
                         used_vars.extend(find_vars_used_in_value(assigned_value))
                     }
                     Line::If { condition } => used_vars.extend(find_vars_used_in_value(condition)),
+                    Line::DynamicJump {
+                        condition,
+                        destination,
+                    } => {
+                        if let Some(_condition) = condition {
+                            used_vars.extend(find_vars_used_in_value(_condition));
+                        }
+                        used_vars.extend(find_vars_used_in_value(destination));
+                    }
+                    Line::Require { condition, error } => {
+                        used_vars.extend(find_vars_used_in_value(condition));
+                        if let Some(_error) = error {
+                            used_vars.extend(find_vars_used_in_value(_error));
+                        }
+                    }
                     Line::Empty => (),
                 }
             }
@@ -109,8 +134,23 @@ fn find_vars_used_in_value(value: &Value) -> HashSet<Variable> {
             label: _,
             arguments,
             return_index: _,
+        }
+        | Value::Revert {
+            error: _,
+            arguments,
         } => used_vars.extend(find_vars_used_in_values(arguments)),
-        Value::Bytes(_) => (),
+        Value::Event { topics, data } => {
+            used_vars.extend(find_vars_used_in_values(topics));
+            used_vars.extend(find_vars_used_in_values(data));
+        }
+        Value::StorageSlot { slot: _, keys } => {
+            for (_, key) in keys {
+                used_vars.extend(find_vars_used_in_value(key));
+            }
+        }
+        Value::Bytes(_)
+        | Value::Named { bytes: _, name: _ }
+        | Value::Parameter { index: _, field: _ } => (),
     }
     return used_vars;
 }
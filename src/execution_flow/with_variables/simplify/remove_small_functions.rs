@@ -10,7 +10,7 @@ use crate::execution_flow::{
     },
 };
 
-fn replace_function_call_by_content_in_scopes(
+pub fn replace_function_call_by_content_in_scopes(
     scopes: &mut Vec<VarScope>,
     label_to_replace: FunctionLabel,
     function_content: &FunctionWithVars,
@@ -109,7 +109,7 @@ fn replace_function_returns_by_var_assignment(
 }
 
 fn should_function_with_vars_exist(function: &FunctionWithVars, n_uses: usize) -> bool {
-    if function.is_main() {
+    if function.is_entry_point() || ExecutionFlowWithVars::is_recursive(function) {
         return true;
     }
     if n_uses <= 1 {
@@ -123,8 +123,6 @@ fn should_function_with_vars_exist(function: &FunctionWithVars, n_uses: usize) -
         return false;
     }
     return n_uses * length >= 6;
-
-    // TODO do not duplicate recursivefunctions
 }
 
 impl ExecutionFlowWithVars {
@@ -1,12 +1,10 @@
 use crate::{
     execution_flow::{
-        execution_flow::{
-            AccessContent, FunctionLabel, GetFunctionLabel, Scope, MAIN_FUNCTION_LABEL,
-        },
+        execution_flow::{AccessContent, FunctionLabel, GetFunctionLabel, Scope},
         with_variables::{
             flow_with_vars::ExecutionFlowWithVars,
             incorporate_variables::{Line, Value, Variable},
-            scopes_with_vars::VarScope,
+            scopes_with_vars::{FunctionWithVars, VarScope},
         },
     },
     tools::utils::{dedup_all, rename_keys},
@@ -47,17 +45,45 @@ impl ExecutionFlowWithVars {
         for (index, label) in function_labels_ordered_by_depth.iter().enumerate() {
             label_mapping.insert(*label, index as u64);
         }
-        label_mapping.insert(MAIN_FUNCTION_LABEL, MAIN_FUNCTION_LABEL);
+        for function in self.functions.values() {
+            if function.is_entry_point() {
+                label_mapping.insert(function.label, function.label);
+            }
+        }
         self.rename_function_labels(&label_mapping);
     }
 
     fn get_functions_ordered_by_depth(&mut self) -> Vec<FunctionLabel> {
-        let mut labels: Vec<FunctionLabel> = Vec::new();
-        labels.extend(get_functions_ordered_by_depth_in_scopes(
-            self.get_main_function().get_content(),
-        ));
+        let mut entry_points: Vec<&FunctionWithVars> = self
+            .functions
+            .values()
+            .filter(|function| function.is_entry_point())
+            .collect();
+        entry_points.sort_by_key(|function| function.kind);
+        let entry_point_labels: Vec<FunctionLabel> =
+            entry_points.iter().map(|function| function.label).collect();
 
+        let mut labels: Vec<FunctionLabel> = Vec::new();
+        for entry_point in entry_points {
+            labels.extend(get_functions_ordered_by_depth_in_scopes(
+                entry_point.get_content(),
+            ));
+        }
+        labels.retain(|label: &FunctionLabel| !entry_point_labels.contains(label));
         dedup_all(&mut labels);
+
+        // functions can also be called from other functions (always the case with EOF code sections)
+        let mut index: usize = 0;
+        while index < labels.len() {
+            if let Some(function) = self.functions.get(&labels[index]) {
+                labels.extend(get_functions_ordered_by_depth_in_scopes(
+                    function.get_content(),
+                ));
+                labels.retain(|label: &FunctionLabel| !entry_point_labels.contains(label));
+                dedup_all(&mut labels);
+            }
+            index += 1;
+        }
         return labels;
     }
 }
@@ -89,6 +115,21 @@ fn rename_variables_in_line(line: &mut Line, var_mapping: &HashMap<Variable, Var
             rename_variables_in_value(assigned_value, var_mapping);
         }
         Line::If { condition } => rename_variables_in_value(condition, var_mapping),
+        Line::DynamicJump {
+            condition,
+            destination,
+        } => {
+            if let Some(_condition) = condition {
+                rename_variables_in_value(_condition, var_mapping);
+            }
+            rename_variables_in_value(destination, var_mapping);
+        }
+        Line::Require { condition, error } => {
+            rename_variables_in_value(condition, var_mapping);
+            if let Some(_error) = error {
+                rename_variables_in_value(_error, var_mapping);
+            }
+        }
         Line::Empty => (),
     }
 }
@@ -101,16 +142,32 @@ fn rename_variables_in_value(value: &mut Value, var_mapping: &HashMap<Variable,
             }
         }
         Value::Existing(var) => rename_variable(var, var_mapping),
-        Value::Bytes(_) => (),
+        Value::Bytes(_)
+        | Value::Named { bytes: _, name: _ }
+        | Value::Parameter { index: _, field: _ } => (),
         Value::FunctionReturnedValue {
             label: _,
             arguments,
             return_index: _,
+        }
+        | Value::Revert {
+            error: _,
+            arguments,
         } => {
             for arg_value in arguments {
                 rename_variables_in_value(arg_value, var_mapping);
             }
         }
+        Value::Event { topics, data } => {
+            for arg_value in topics.iter_mut().chain(data) {
+                rename_variables_in_value(arg_value, var_mapping);
+            }
+        }
+        Value::StorageSlot { slot: _, keys } => {
+            for (_, key) in keys {
+                rename_variables_in_value(key, var_mapping);
+            }
+        }
     }
 }
 
@@ -143,8 +200,18 @@ pub fn rename_variables_in_scopes(
                 rename_variables_in_scopes(instructions_if_true, var_mapping);
                 rename_variables_in_scopes(instructions_if_false, var_mapping);
             }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                rename_variables_in_scopes(header, var_mapping);
+                rename_variables_in_scopes(body, var_mapping);
+            }
             Scope::Loop { label: _ }
             | Scope::LoopContinue { label: _ }
+            | Scope::LoopBreak { label: _ }
             | Scope::Panic
             | Scope::Empty => (),
         }
@@ -174,6 +241,15 @@ fn rename_function_labels_in_scopes(
                 rename_function_labels_in_scopes(instructions_if_true, label_mapping);
                 rename_function_labels_in_scopes(instructions_if_false, label_mapping);
             }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                rename_function_labels_in_scopes(header, label_mapping);
+                rename_function_labels_in_scopes(body, label_mapping);
+            }
             _ => (),
         }
     }
@@ -203,6 +279,15 @@ fn find_function_depth(
                 current_depth =
                     find_function_depth(instructions_if_false, depth_per_function, current_depth);
             }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                current_depth = find_function_depth(header, depth_per_function, current_depth);
+                current_depth = find_function_depth(body, depth_per_function, current_depth);
+            }
             _ => (),
         }
     }
@@ -50,6 +50,16 @@ fn replace_var_in_scope_second_assignment(
         }
         Scope::Loop { label: _ } => (),
         Scope::LoopContinue { label: _ } => (),
+        Scope::LoopBreak { label: _ } => (),
+        Scope::StructuredLoop {
+            label: _,
+            kind: _,
+            header,
+            body,
+        } => {
+            _replace_var_in_scopes_until_second_assignment(header, to_replace, init_counts);
+            _replace_var_in_scopes_until_second_assignment(body, to_replace, init_counts);
+        }
         Scope::Condition {
             instructions_if_true,
             instructions_if_false,
@@ -97,6 +107,21 @@ fn replace_var_in_line_until_second_assignment(
         Line::If { condition } => {
             replace_var_in_value_until_second_assignment(condition, to_replace, init_counts)
         }
+        Line::DynamicJump {
+            condition,
+            destination,
+        } => {
+            if let Some(_condition) = condition {
+                replace_var_in_value_until_second_assignment(_condition, to_replace, init_counts);
+            }
+            replace_var_in_value_until_second_assignment(destination, to_replace, init_counts)
+        }
+        Line::Require { condition, error } => {
+            replace_var_in_value_until_second_assignment(condition, to_replace, init_counts);
+            if let Some(_error) = error {
+                replace_var_in_value_until_second_assignment(_error, to_replace, init_counts);
+            }
+        }
         Line::Empty => (),
     }
 }
@@ -144,16 +169,32 @@ fn replace_var_in_value_until_second_assignment(
                 }
             }
         }
-        Value::Bytes(_) => (),
+        Value::Bytes(_)
+        | Value::Named { bytes: _, name: _ }
+        | Value::Parameter { index: _, field: _ } => (),
         Value::FunctionReturnedValue {
             label: _,
             arguments,
             return_index: _,
+        }
+        | Value::Revert {
+            error: _,
+            arguments,
         } => {
             for arg_value in arguments {
                 replace_var_in_value_until_second_assignment(arg_value, to_replace, init_counts);
             }
         }
+        Value::Event { topics, data } => {
+            for arg_value in topics.iter_mut().chain(data) {
+                replace_var_in_value_until_second_assignment(arg_value, to_replace, init_counts);
+            }
+        }
+        Value::StorageSlot { slot: _, keys } => {
+            for (_, key) in keys {
+                replace_var_in_value_until_second_assignment(key, to_replace, init_counts);
+            }
+        }
     }
 }
 
@@ -170,15 +211,31 @@ pub fn replace_vars_in_value(value: &mut Value, to_replace: &HashMap<Variable, V
                 *value = replacing_value.clone();
             }
         }
-        Value::Bytes(_) => (),
+        Value::Bytes(_)
+        | Value::Named { bytes: _, name: _ }
+        | Value::Parameter { index: _, field: _ } => (),
         Value::FunctionReturnedValue {
             label: _,
             arguments,
             return_index: _,
+        }
+        | Value::Revert {
+            error: _,
+            arguments,
         } => {
             for arg_value in arguments {
                 replace_vars_in_value(arg_value, to_replace);
             }
         }
+        Value::Event { topics, data } => {
+            for arg_value in topics.iter_mut().chain(data) {
+                replace_vars_in_value(arg_value, to_replace);
+            }
+        }
+        Value::StorageSlot { slot: _, keys } => {
+            for (_, key) in keys {
+                replace_vars_in_value(key, to_replace);
+            }
+        }
     }
 }
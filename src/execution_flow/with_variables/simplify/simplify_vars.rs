@@ -7,7 +7,7 @@ use crate::execution_flow::{
     with_variables::{
         flow_with_vars::ExecutionFlowWithVars,
         incorporate_variables::{Line, Value, Variable},
-        scopes_with_vars::VarScope,
+        scopes_with_vars::{FunctionWithVars, VarScope},
         simplify::rename::rename_variables_in_scopes,
     },
 };
@@ -21,7 +21,7 @@ impl ExecutionFlowWithVars {
     }
     pub fn rename_variables_starting_from_zero(&mut self) {
         for (_, function) in &mut self.functions {
-            rename_variables_starting_from_zero_in_scopes(function.get_content_mut());
+            rename_variables_starting_from_zero_in_function(function);
         }
     }
     pub fn simplify_vars(&mut self) {
@@ -71,6 +71,21 @@ fn _simplify_vars_in_scopes(
                         Line::If { condition } => {
                             replace_vars_in_value(condition, to_replace);
                         }
+                        Line::DynamicJump {
+                            condition,
+                            destination,
+                        } => {
+                            if let Some(_condition) = condition {
+                                replace_vars_in_value(_condition, to_replace);
+                            }
+                            replace_vars_in_value(destination, to_replace);
+                        }
+                        Line::Require { condition, error } => {
+                            replace_vars_in_value(condition, to_replace);
+                            if let Some(_error) = error {
+                                replace_vars_in_value(_error, to_replace);
+                            }
+                        }
                         Line::Empty => (),
                     }
                 }
@@ -92,8 +107,18 @@ fn _simplify_vars_in_scopes(
                 _simplify_vars_in_scopes(instructions_if_true, duplicable_vars, to_replace);
                 _simplify_vars_in_scopes(instructions_if_false, duplicable_vars, to_replace);
             }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                _simplify_vars_in_scopes(header, duplicable_vars, to_replace);
+                _simplify_vars_in_scopes(body, duplicable_vars, to_replace);
+            }
             Scope::LoopContinue { label: _ }
             | Scope::Loop { label: _ }
+            | Scope::LoopBreak { label: _ }
             | Scope::Empty
             | Scope::Panic => (),
         }
@@ -109,12 +134,22 @@ fn should_value_be_duplicated(value: &Value, duplicable_vars: &HashSet<Variable>
                     .all(|arg_value| should_value_be_duplicated(arg_value, duplicable_vars))
         }
         Value::Existing(variable) => duplicable_vars.contains(variable),
-        Value::Bytes(_) => true,
+        Value::Bytes(_)
+        | Value::Named { bytes: _, name: _ }
+        | Value::Parameter { index: _, field: _ } => true,
         Value::FunctionReturnedValue {
             label: _,
             arguments: _,
             return_index: _,
-        } => false,
+        }
+        | Value::Revert {
+            error: _,
+            arguments: _,
+        }
+        | Value::Event { topics: _, data: _ } => false,
+        Value::StorageSlot { slot: _, keys } => keys
+            .iter()
+            .all(|(_, key)| should_value_be_duplicated(key, duplicable_vars)),
     }
 }
 
@@ -131,17 +166,37 @@ fn remove_empty_elements_in_scopes(scopes: &mut Vec<VarScope>) {
                 remove_empty_elements_in_scopes(instructions_if_true);
                 remove_empty_elements_in_scopes(instructions_if_false);
             }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                remove_empty_elements_in_scopes(header);
+                remove_empty_elements_in_scopes(body);
+            }
             _ => (),
         }
     }
     scopes.retain(|scope: &VarScope| !scope.is_empty());
 }
 
-fn rename_variables_starting_from_zero_in_scopes(scopes: &mut Vec<VarScope>) {
-    let vars_ordered_by_depth: Vec<Variable> = get_vars_ordered_by_depth(scopes);
+fn rename_variables_starting_from_zero_in_function(function: &mut FunctionWithVars) {
+    // parameters come first, so that the variables of the body do not take their names
+    let mut ordered_vars: Vec<Variable> = function.input_vars.clone();
+    for var in get_vars_ordered_by_depth(&function.content) {
+        if !ordered_vars.contains(&var) {
+            ordered_vars.push(var);
+        }
+    }
     let mut var_mapping: HashMap<Variable, Variable> = HashMap::new();
-    for (index, var) in vars_ordered_by_depth.iter().enumerate() {
+    for (index, var) in ordered_vars.iter().enumerate() {
         var_mapping.insert(*var, Variable { alias: index });
     }
-    rename_variables_in_scopes(scopes, &var_mapping);
+    rename_variables_in_scopes(&mut function.content, &var_mapping);
+    function.input_vars = function
+        .input_vars
+        .iter()
+        .map(|input_var| var_mapping[input_var])
+        .collect();
 }
@@ -0,0 +1,714 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use itertools::Itertools;
+use primitive_types::U256;
+use serde::Serialize;
+
+use super::{
+    abi_parameters::apply_on_values,
+    display_flow_with_vars::{storage_variable_description, storage_variable_name},
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::{Line, ParameterField, StorageKind, Value, Variable},
+    scopes_with_vars::{FunctionWithVars, VarScope},
+    simplify::initialization_of_vars::enumerate_var_initializations,
+    type_inference::VarType,
+};
+use crate::{
+    bytecode_reader::opcode::Opcode,
+    execution_flow::execution_flow::{FunctionLabel, Scope},
+    tools::utils::u256_to_hex,
+};
+
+const WORD_BITS: i64 = 256;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlotKind {
+    Value,
+    Mapping,
+    Array,
+}
+
+// Part of a slot read on its own, Solidity packs small variables in the same slot.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize)]
+pub struct SlotField {
+    pub offset: usize, // in bytes, from the lowest one
+    pub size: usize,
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+// For mappings and arrays, the fields are the ones of their elements.
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct SlotLayout {
+    pub slot: U256,
+    pub kind: SlotKind,
+    pub n_keys: usize,
+    pub fields: Vec<SlotField>,
+    pub read_by: Vec<String>,
+    pub written_by: Vec<String>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct StorageLayout {
+    pub slots: Vec<SlotLayout>,
+}
+
+impl StorageLayout {
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string_pretty(self).unwrap();
+    }
+}
+
+impl ToString for StorageLayout {
+    fn to_string(&self) -> String {
+        let mut res: String = String::new();
+        for slot in &self.slots {
+            res += &format!("slot {}", u256_to_hex(slot.slot));
+            match slot.kind {
+                SlotKind::Value => res += "\n",
+                SlotKind::Mapping | SlotKind::Array => {
+                    let kind: StorageKind = match slot.kind {
+                        SlotKind::Mapping => StorageKind::Mapping,
+                        _ => StorageKind::Array,
+                    };
+                    res += &format!(
+                        ": {} ({})\n",
+                        storage_variable_name(slot.slot, kind),
+                        storage_variable_description(kind, slot.n_keys)
+                    );
+                }
+            }
+            for field in &slot.fields {
+                let size: String = match field.size {
+                    1 => "1 byte".to_owned(),
+                    size => format!("{} bytes", size),
+                };
+                res += &format!("    offset {}, {}: {}\n", field.offset, size, field.type_);
+            }
+            if !slot.read_by.is_empty() {
+                res += &format!("    read by: {}\n", slot.read_by.join(", "));
+            }
+            if !slot.written_by.is_empty() {
+                res += &format!("    written by: {}\n", slot.written_by.join(", "));
+            }
+        }
+        return res;
+    }
+}
+
+// Bits `[start, end)` of a storage word kept by a value, whose bit `i` is the bit `i + shift` of
+// the word.
+#[derive(Clone, Copy)]
+struct Window {
+    start: i64,
+    end: i64,
+    shift: i64,
+}
+
+#[derive(Default)]
+struct StorageAccesses {
+    read: HashSet<U256>,
+    written: HashSet<U256>,
+    fields: Vec<(U256, SlotField)>,
+}
+
+impl ExecutionFlowWithVars {
+    // Slots with a constant number (mappings and arrays included) read or written by the code.
+    // Packed variables are found from the masks and shifts applied to the loaded words, and the
+    // accesses of internal functions are attributed to the entry points calling them.
+    pub fn get_storage_layout(&self) -> StorageLayout {
+        let accesses: HashMap<FunctionLabel, StorageAccesses> = self
+            .functions
+            .iter()
+            .map(|(label, function)| (*label, get_storage_accesses(function)))
+            .collect();
+
+        let mut read_by: HashMap<U256, BTreeSet<String>> = HashMap::new();
+        let mut written_by: HashMap<U256, BTreeSet<String>> = HashMap::new();
+        for entry_point in self.functions.values() {
+            if !entry_point.is_entry_point() {
+                continue;
+            }
+            for label in self.get_reachable_functions(entry_point.label) {
+                for slot in &accesses[&label].read {
+                    read_by
+                        .entry(*slot)
+                        .or_default()
+                        .insert(entry_point.get_name());
+                }
+                for slot in &accesses[&label].written {
+                    written_by
+                        .entry(*slot)
+                        .or_default()
+                        .insert(entry_point.get_name());
+                }
+            }
+        }
+
+        let mut fields: HashMap<U256, BTreeSet<SlotField>> = HashMap::new();
+        for function_accesses in accesses.values() {
+            for (slot, field) in &function_accesses.fields {
+                fields.entry(*slot).or_default().insert(field.clone());
+            }
+        }
+
+        let storage_variables: BTreeMap<U256, (StorageKind, usize)> = self.get_storage_variables();
+        let slots: Vec<SlotLayout> = accesses
+            .values()
+            .flat_map(|function_accesses| function_accesses.read.union(&function_accesses.written))
+            .copied()
+            .unique()
+            .sorted()
+            .map(|slot| {
+                let (kind, n_keys): (SlotKind, usize) = match storage_variables.get(&slot) {
+                    Some((StorageKind::Mapping, n_keys)) => (SlotKind::Mapping, *n_keys),
+                    Some((StorageKind::Array, n_keys)) => (SlotKind::Array, *n_keys),
+                    None => (SlotKind::Value, 0),
+                };
+                let mut slot_fields: Vec<SlotField> = fields
+                    .remove(&slot)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect();
+                // the whole word is also loaded to update one of its packed variables
+                if slot_fields.len() > 1 {
+                    slot_fields.retain(|field| *field != whole_word());
+                }
+                remove_overlapping_fields(&mut slot_fields);
+                return SlotLayout {
+                    slot,
+                    kind,
+                    n_keys,
+                    fields: slot_fields,
+                    read_by: read_by
+                        .remove(&slot)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect(),
+                    written_by: written_by
+                        .remove(&slot)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .collect(),
+                };
+            })
+            .collect();
+        return StorageLayout { slots };
+    }
+
+    pub fn get_reachable_functions(&self, label: FunctionLabel) -> HashSet<FunctionLabel> {
+        let mut reachable_functions: HashSet<FunctionLabel> = HashSet::new();
+        let mut functions_to_visit: Vec<FunctionLabel> = vec![label];
+        while let Some(label) = functions_to_visit.pop() {
+            if !reachable_functions.insert(label) {
+                continue;
+            }
+            Self::apply_on_scopes(&self.functions[&label].content, &mut |scope: &VarScope| {
+                if let Scope::FunctionCall(function_call) = scope {
+                    functions_to_visit.push(function_call.label);
+                }
+            });
+            apply_on_values(&self.functions[&label].content, &mut |value: &Value| {
+                if let Value::FunctionReturnedValue {
+                    label,
+                    arguments: _,
+                    return_index: _,
+                } = value
+                {
+                    functions_to_visit.push(*label);
+                }
+            });
+        }
+        return reachable_functions;
+    }
+}
+
+fn get_storage_accesses(function: &FunctionWithVars) -> StorageAccesses {
+    let mut accesses: StorageAccesses = StorageAccesses::default();
+    apply_on_values(&function.content, &mut |value: &Value| {
+        if let Value::Calculation { opcode, args } = value {
+            let accessed_slots: &mut HashSet<U256> = match opcode {
+                Opcode::SLOAD => &mut accesses.read,
+                Opcode::SSTORE => &mut accesses.written,
+                _ => return,
+            };
+            let slot: U256 = match get_slot(&args[0]) {
+                Some(slot) => slot,
+                None => return,
+            };
+            accessed_slots.insert(slot);
+            if *opcode == Opcode::SSTORE {
+                if let Some(field) = get_written_field(&args[1], function) {
+                    accesses.fields.push((slot, field));
+                }
+            }
+        }
+    });
+
+    // variables holding a whole word, and the ones stored as a whole
+    let n_initializations_per_var: HashMap<Variable, usize> =
+        enumerate_var_initializations(&function.content);
+    let mut stored_vars: HashMap<Variable, U256> = HashMap::new();
+    apply_on_values(&function.content, &mut |value: &Value| {
+        if let Value::Calculation {
+            opcode: Opcode::SSTORE,
+            args,
+        } = value
+        {
+            if let (Some(slot), Value::Existing(var)) = (get_slot(&args[0]), &args[1]) {
+                if n_initializations_per_var.get(var) == Some(&1) {
+                    stored_vars.insert(*var, slot);
+                }
+            }
+        }
+    });
+    let mut loaded_vars: HashMap<Variable, U256> = HashMap::new();
+    ExecutionFlowWithVars::apply_on_scopes(&function.content, &mut |scope: &VarScope| {
+        if let Scope::Instructions(instructions) = scope {
+            for line in &instructions.lines {
+                if let Line::Assignement {
+                    receiving_var: Some(receiving_var),
+                    assigned_value,
+                } = line
+                {
+                    if n_initializations_per_var[receiving_var] != 1 {
+                        continue;
+                    }
+                    if let Some(slot) = get_loaded_slot(assigned_value) {
+                        loaded_vars.insert(*receiving_var, slot);
+                    }
+                }
+            }
+        }
+    });
+
+    ExecutionFlowWithVars::apply_on_scopes(&function.content, &mut |scope: &VarScope| {
+        if let Scope::Instructions(instructions) = scope {
+            for line in &instructions.lines {
+                match line {
+                    Line::Assignement {
+                        receiving_var,
+                        assigned_value,
+                    } => {
+                        let var_type: Option<VarType> =
+                            receiving_var.and_then(|var| function.var_types.get(&var).copied());
+                        match receiving_var.and_then(|var| stored_vars.get(&var)) {
+                            Some(slot) => collect_written_fields(
+                                assigned_value,
+                                var_type,
+                                Some(*slot),
+                                &loaded_vars,
+                                &mut accesses,
+                            ),
+                            None => collect_fields(
+                                assigned_value,
+                                var_type,
+                                &loaded_vars,
+                                &mut accesses,
+                            ),
+                        }
+                    }
+                    Line::If { condition }
+                    | Line::Require {
+                        condition,
+                        error: _,
+                    } => {
+                        collect_fields(condition, None, &loaded_vars, &mut accesses);
+                    }
+                    Line::DynamicJump {
+                        condition: _,
+                        destination: _,
+                    }
+                    | Line::Empty => (),
+                }
+            }
+        }
+    });
+    return accesses;
+}
+
+// Slot of a storage variable, mappings and arrays are identified by their root slot.
+fn get_slot(value: &Value) -> Option<U256> {
+    match value {
+        Value::StorageSlot { slot, keys: _ } => return Some(*slot),
+        _ => return value.compute_value(),
+    }
+}
+
+// The length of an array is not one of its elements.
+fn get_loaded_slot(value: &Value) -> Option<U256> {
+    if let Value::Calculation {
+        opcode: Opcode::SLOAD,
+        args,
+    } = value
+    {
+        if let Value::StorageSlot { slot: _, keys } = &args[0] {
+            if keys.is_empty() {
+                return None;
+            }
+        }
+        return get_slot(&args[0]);
+    }
+    return None;
+}
+
+// The largest parts of a value taken from a storage word.
+fn collect_fields(
+    value: &Value,
+    var_type: Option<VarType>,
+    loaded_vars: &HashMap<Variable, U256>,
+    accesses: &mut StorageAccesses,
+) {
+    if let Some((slot, window)) = get_window(value, loaded_vars) {
+        // a plain copy of a loaded variable is not a new read
+        if matches!(value, Value::Existing(_)) && var_type.is_none() {
+            return;
+        }
+        if let Some(field) = get_field(window, var_type) {
+            accesses.fields.push((slot, field));
+        }
+        return;
+    }
+    match value {
+        // the parts of a word kept when updating one of its packed variables are not read
+        Value::Calculation {
+            opcode: Opcode::SSTORE,
+            args,
+        } => collect_written_fields(&args[1], None, get_slot(&args[0]), loaded_vars, accesses),
+        Value::Calculation { opcode: _, args } => {
+            for arg in args {
+                collect_fields(arg, None, loaded_vars, accesses);
+            }
+        }
+        Value::FunctionReturnedValue {
+            label: _,
+            arguments,
+            return_index: _,
+        } => {
+            for argument in arguments {
+                collect_fields(argument, None, loaded_vars, accesses);
+            }
+        }
+        _ => (),
+    }
+}
+
+// The fields of a value stored in `written_slot` are only read from other slots.
+fn collect_written_fields(
+    value: &Value,
+    var_type: Option<VarType>,
+    written_slot: Option<U256>,
+    loaded_vars: &HashMap<Variable, U256>,
+    accesses: &mut StorageAccesses,
+) {
+    let mut written_accesses: StorageAccesses = StorageAccesses::default();
+    collect_fields(value, var_type, loaded_vars, &mut written_accesses);
+    accesses.fields.extend(
+        written_accesses
+            .fields
+            .into_iter()
+            .filter(|(slot, _)| Some(*slot) != written_slot),
+    );
+}
+
+fn get_window(value: &Value, loaded_vars: &HashMap<Variable, U256>) -> Option<(U256, Window)> {
+    let whole_word: Window = Window {
+        start: 0,
+        end: WORD_BITS,
+        shift: 0,
+    };
+    let (opcode, args) = match value {
+        Value::Existing(var) => return Some((*loaded_vars.get(var)?, whole_word)),
+        Value::Calculation { opcode, args } => (*opcode, args),
+        _ => return None,
+    };
+    let (slot, mut window): (U256, Window) = match opcode {
+        Opcode::SLOAD => return Some((get_loaded_slot(value)?, whole_word)),
+        Opcode::SHR | Opcode::SHL => get_window(&args[1], loaded_vars)?,
+        Opcode::AND | Opcode::MUL => match (args[0].compute_value(), args[1].compute_value()) {
+            (Some(_), None) => get_window(&args[1], loaded_vars)?,
+            (None, Some(_)) => get_window(&args[0], loaded_vars)?,
+            _ => return None,
+        },
+        Opcode::DIV => get_window(&args[0], loaded_vars)?,
+        _ => return None,
+    };
+    let constant: U256 = args.iter().find_map(|arg| arg.compute_value())?;
+    match opcode {
+        // `word / 2**n` and `word * 2**n` are older shifts
+        Opcode::SHR | Opcode::DIV => {
+            let shift: i64 = get_shift(opcode, constant)?;
+            window.shift += shift;
+            window.start = window.start.max(window.shift);
+        }
+        Opcode::SHL | Opcode::MUL => {
+            let shift: i64 = get_shift(opcode, constant)?;
+            window.shift -= shift;
+            window.end = window.end.min(window.shift + WORD_BITS);
+        }
+        Opcode::AND => {
+            let (mask_start, mask_end): (i64, i64) = get_mask_bits(constant)?;
+            window.start = window.start.max(mask_start + window.shift);
+            window.end = window.end.min(mask_end + window.shift);
+        }
+        _ => return None,
+    }
+    if window.start >= window.end {
+        return None;
+    }
+    return Some((slot, window));
+}
+
+fn get_shift(opcode: Opcode, constant: U256) -> Option<i64> {
+    let shift: U256 = match opcode {
+        Opcode::SHR | Opcode::SHL => constant,
+        // powers of two only
+        _ if !constant.is_zero() && (constant & (constant - 1)).is_zero() => {
+            U256::from(constant.trailing_zeros())
+        }
+        _ => return None,
+    };
+    if shift >= U256::from(WORD_BITS) {
+        return None;
+    }
+    return Some(shift.as_u64() as i64);
+}
+
+// Bits `[start, end)` of a mask made of contiguous ones.
+fn get_mask_bits(mask: U256) -> Option<(i64, i64)> {
+    if mask.is_zero() {
+        return None;
+    }
+    let start: u32 = mask.trailing_zeros();
+    let ones: U256 = mask >> start;
+    if !(ones & ones.overflowing_add(U256::one()).0).is_zero() {
+        return None;
+    }
+    return Some((start as i64, (start + ones.bits() as u32) as i64));
+}
+
+fn get_field(mut window: Window, var_type: Option<VarType>) -> Option<SlotField> {
+    // the cleanup of typed variables is removed from their declaration
+    if window.start == 0 && window.end == WORD_BITS {
+        match var_type {
+            Some(VarType::Address) => window.end = 160,
+            Some(VarType::Bool) => window.end = 8,
+            Some(VarType::Int { bits }) => window.end = bits as i64,
+            Some(VarType::FixedBytes { size }) => window.start = WORD_BITS - 8 * size as i64,
+            None => (),
+        }
+    }
+    if window.start % 8 != 0 || window.end % 8 != 0 {
+        return None;
+    }
+    let offset: usize = (window.start / 8) as usize;
+    let size: usize = ((window.end - window.start) / 8) as usize;
+    let type_: String = match var_type {
+        Some(var_type) => var_type.to_string(),
+        None if size == 20 => "address".to_owned(),
+        None => format!("uint{}", 8 * size),
+    };
+    return Some(SlotField {
+        offset,
+        size,
+        type_,
+    });
+}
+
+// A typed variable or argument written as a whole.
+fn get_written_field(value: &Value, function: &FunctionWithVars) -> Option<SlotField> {
+    let var_type: Option<VarType> = match value {
+        Value::Existing(var) => Some(*function.var_types.get(var)?),
+        Value::Parameter {
+            index,
+            field: ParameterField::Word,
+        } => match function.parameters.get(*index).map(String::as_str) {
+            Some("address") => Some(VarType::Address),
+            Some("uint256") | None => None, // a word without a known type
+            _ => return None,
+        },
+        Value::Parameter {
+            index: _,
+            field: ParameterField::Element(_),
+        } => None,
+        _ => return None,
+    };
+    return get_field(
+        Window {
+            start: 0,
+            end: WORD_BITS,
+            shift: 0,
+        },
+        var_type,
+    );
+}
+
+// Packed variables are also read together, so a field containing other ones is not a variable.
+// Fields that still overlap are dropped.
+fn remove_overlapping_fields(fields: &mut Vec<SlotField>) {
+    let get_bounds = |fields: &Vec<SlotField>| -> Vec<(usize, usize)> {
+        return fields
+            .iter()
+            .map(|field| (field.offset, field.offset + field.size))
+            .unique()
+            .collect();
+    };
+    let bounds: Vec<(usize, usize)> = get_bounds(fields);
+    fields.retain(|field| {
+        let (start, end): (usize, usize) = (field.offset, field.offset + field.size);
+        return !bounds.iter().any(|(other_start, other_end)| {
+            (*other_start, *other_end) != (start, end) && start <= *other_start && *other_end <= end
+        });
+    });
+    let bounds: Vec<(usize, usize)> = get_bounds(fields);
+    fields.retain(|field| {
+        let (start, end): (usize, usize) = (field.offset, field.offset + field.size);
+        return bounds.iter().all(|(other_start, other_end)| {
+            (*other_start, *other_end) == (start, end) || *other_end <= start || end <= *other_start
+        });
+    });
+}
+
+fn whole_word() -> SlotField {
+    return SlotField {
+        offset: 0,
+        size: 32,
+        type_: "uint256".to_owned(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompiler::Decompiler;
+    use crate::execution_flow::with_variables::scopes_with_vars::{
+        FunctionKind, InstructionsWithVars,
+    };
+    use crate::signatures::signature_database::SignatureDatabase;
+
+    fn calculation(opcode: Opcode, args: &[Value]) -> Value {
+        return Value::Calculation {
+            opcode,
+            args: args.to_vec(),
+        };
+    }
+
+    fn fields(fields: &[(usize, usize, &str)]) -> Vec<SlotField> {
+        return fields
+            .iter()
+            .map(|(offset, size, type_)| SlotField {
+                offset: *offset,
+                size: *size,
+                type_: type_.to_string(),
+            })
+            .collect();
+    }
+
+    #[test]
+    fn test_get_storage_layout() {
+        // 0x11111111: storage[5] = address(storage[0]), storage[6] = uint8(storage[0] >> 160)
+        // 0x22222222: storage[1] = arg0
+        let decompiler: Decompiler = Decompiler::from_hex(
+            "60003560e01c80631111111114601b5780632222222214604557005b60005473ffffffffffffffffffffffffffffffffffffffff1660055560005460a01c60ff16600655005b60043560015500",
+        )
+        .unwrap();
+        let mut flow: ExecutionFlowWithVars =
+            decompiler.decompile().unwrap().execution_flow_with_vars;
+        let storage_layout: StorageLayout = flow.get_storage_layout();
+        let slots: Vec<U256> = storage_layout.slots.iter().map(|slot| slot.slot).collect();
+        assert_eq!(slots, [0x00, 0x01, 0x05, 0x06].map(U256::from));
+        assert_eq!(
+            storage_layout.slots[0].fields,
+            fields(&[(0, 20, "address"), (20, 1, "uint8")])
+        );
+        assert_eq!(storage_layout.slots[0].read_by, ["selector_0x11111111"]);
+        assert!(storage_layout.slots[0].written_by.is_empty());
+        assert_eq!(storage_layout.slots[1].written_by, ["selector_0x22222222"]);
+
+        let json: serde_json::Value = serde_json::from_str(&storage_layout.to_json()).unwrap();
+        assert_eq!(json["slots"][0]["kind"], "value");
+        assert_eq!(json["slots"][0]["fields"][0]["type"], "address");
+        assert!(storage_layout
+            .to_string()
+            .starts_with("slot 0x00\n    offset 0, 20 bytes: address\n"));
+
+        // arg0 is now the first word of a static array
+        let mut signatures: SignatureDatabase = SignatureDatabase::new();
+        signatures
+            .add_hashed_signature("0x22222222", "g(uint256[2])")
+            .unwrap();
+        flow.apply_signatures(&signatures);
+        let storage_layout: StorageLayout = flow.get_storage_layout();
+        assert_eq!(storage_layout.slots[1].written_by, ["g"]);
+        assert_eq!(storage_layout.slots[1].fields[0].type_, "uint256");
+    }
+
+    #[test]
+    fn test_get_storage_accesses() {
+        // var_0 = storage[0x33], var_1 = msg.sender | (~((1 << 0xa0) - 1) & var_0),
+        // storage[0x33] = var_1
+        let slot: Value = Value::Bytes(U256::from(0x33));
+        let loaded: Variable = Variable { alias: 0 };
+        let updated: Variable = Variable { alias: 1 };
+        let kept_bits: Value = calculation(
+            Opcode::AND,
+            &[
+                calculation(Opcode::NOT, &[Value::Bytes(U256::MAX >> 96)]),
+                Value::Existing(loaded),
+            ],
+        );
+        let lines: Vec<Line> = vec![
+            Line::Assignement {
+                receiving_var: Some(loaded),
+                assigned_value: calculation(Opcode::SLOAD, &[slot.clone()]),
+            },
+            Line::Assignement {
+                receiving_var: Some(updated),
+                assigned_value: calculation(
+                    Opcode::OR,
+                    &[calculation(Opcode::CALLER, &[]), kept_bits],
+                ),
+            },
+            Line::Assignement {
+                receiving_var: None,
+                assigned_value: calculation(Opcode::SSTORE, &[slot, Value::Existing(updated)]),
+            },
+        ];
+        let function: FunctionWithVars = FunctionWithVars {
+            label: 0,
+            kind: FunctionKind::Main,
+            signature: None,
+            parameters: Vec::new(),
+            var_types: HashMap::new(),
+            input_vars: Vec::new(),
+            n_outputs: 0,
+            returns: false,
+            content: vec![Scope::Instructions(InstructionsWithVars { lines })],
+        };
+        let accesses: StorageAccesses = get_storage_accesses(&function);
+        // the upper bytes kept by the update are not a variable, only the whole word is loaded
+        assert_eq!(accesses.fields, vec![(U256::from(0x33), whole_word())]);
+        assert_eq!(accesses.written, HashSet::from([U256::from(0x33)]));
+    }
+
+    #[test]
+    fn test_remove_overlapping_fields() {
+        // two packed variables read together
+        let mut slot_fields: Vec<SlotField> = fields(&[
+            (0, 8, "uint64"),
+            (16, 8, "uint64"),
+            (16, 16, "uint128"),
+            (24, 8, "uint64"),
+        ]);
+        remove_overlapping_fields(&mut slot_fields);
+        assert_eq!(
+            slot_fields,
+            fields(&[(0, 8, "uint64"), (16, 8, "uint64"), (24, 8, "uint64")])
+        );
+
+        let mut slot_fields: Vec<SlotField> = fields(&[(0, 2, "uint16"), (1, 2, "uint16")]);
+        remove_overlapping_fields(&mut slot_fields);
+        assert!(slot_fields.is_empty());
+    }
+}
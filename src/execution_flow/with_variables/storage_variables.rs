@@ -0,0 +1,685 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use primitive_types::U256;
+
+use super::{
+    abi_parameters::{apply_on_value_mut, apply_on_values, apply_on_values_mut},
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::{Line, StorageKind, Value, Variable},
+    scopes_with_vars::VarScope,
+    simplify::initialization_of_vars::enumerate_var_initializations,
+};
+use crate::{bytecode_reader::opcode::Opcode, execution_flow::execution_flow::Scope};
+
+// Solidity hashes mapping keys and slots in the scratch space, the first two words of memory.
+const SCRATCH_SPACE_SIZE: u64 = 0x40;
+
+// Lines are numbered in the order they are visited.
+pub type LineId = usize;
+
+struct ScratchSpaceUses {
+    n_initializations_per_var: HashMap<Variable, usize>,
+    slot_vars: HashMap<Variable, Value>,
+    slot_var_lines: HashSet<LineId>,
+    hashed_writes: HashSet<LineId>,
+    read_writes: HashSet<LineId>, // read for something else than a storage slot
+}
+
+impl ExecutionFlowWithVars {
+    // A mapping stores the value of `key` at `keccak(key . slot)`, a dynamic array stores its
+    // length at `slot` and its elements from `keccak(slot)`. Nested variables hash the slot of
+    // their parent instead of their own.
+    pub fn recognise_storage_variables(&mut self) {
+        for function in self.functions.values_mut() {
+            recognise_storage_variables_in_function(&mut function.content);
+        }
+
+        let array_slots: HashSet<U256> = self
+            .get_storage_variables()
+            .into_iter()
+            .filter(|(_, (kind, _))| *kind == StorageKind::Array)
+            .map(|(slot, _)| slot)
+            .collect();
+        for function in self.functions.values_mut() {
+            apply_on_values_mut(&mut function.content, &mut |value: &mut Value| {
+                if let Value::Calculation {
+                    opcode: Opcode::SLOAD | Opcode::SSTORE,
+                    args,
+                } = value
+                {
+                    if let Value::Bytes(slot) = args[0] {
+                        if array_slots.contains(&slot) {
+                            args[0] = Value::StorageSlot {
+                                slot,
+                                keys: Vec::new(),
+                            };
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    // Slot of each recognised storage variable, with its kind and its maximum number of keys.
+    pub fn get_storage_variables(&self) -> BTreeMap<U256, (StorageKind, usize)> {
+        let mut storage_variables: BTreeMap<U256, (StorageKind, usize)> = BTreeMap::new();
+        for function in self.functions.values() {
+            apply_on_values(&function.content, &mut |value: &Value| {
+                if let Value::StorageSlot { slot, keys } = value {
+                    if let Some((kind, _)) = keys.first() {
+                        let (_, n_keys) = storage_variables.entry(*slot).or_insert((*kind, 0));
+                        *n_keys = (*n_keys).max(keys.len());
+                    }
+                }
+            });
+        }
+        return storage_variables;
+    }
+}
+
+fn recognise_storage_variables_in_function(scopes: &mut Vec<VarScope>) {
+    let mut uses: ScratchSpaceUses = ScratchSpaceUses {
+        n_initializations_per_var: enumerate_var_initializations(scopes),
+        slot_vars: HashMap::new(),
+        slot_var_lines: HashSet::new(),
+        hashed_writes: HashSet::new(),
+        read_writes: HashSet::new(),
+    };
+    track_memory_in_scopes(scopes, &mut HashMap::new(), &mut uses, &mut 0);
+    if uses.hashed_writes.is_empty() {
+        return;
+    }
+
+    // the writes to the scratch space and the variables holding slots are not needed anymore
+    let mut lines_to_remove: HashSet<LineId> = uses
+        .hashed_writes
+        .difference(&uses.read_writes)
+        .copied()
+        .collect();
+    lines_to_remove.extend(&uses.slot_var_lines);
+    remove_lines_in_scopes(scopes, &lines_to_remove, &mut 0);
+    apply_on_values_mut(scopes, &mut |value: &mut Value| {
+        if let Value::Existing(var) = value {
+            if let Some(slot) = uses.slot_vars.get(var) {
+                *value = slot.clone();
+            }
+        }
+        index_array_element(value);
+    });
+}
+
+impl MemoryTracker for ScratchSpaceUses {
+    type Memory = HashMap<U256, (Value, LineId)>;
+
+    fn visit_value(&mut self, value: &mut Value, scratch_space: &mut Self::Memory) {
+        hash_scratch_space(value, scratch_space, self);
+    }
+
+    fn visit_assignment(
+        &mut self,
+        receiving_var: Option<Variable>,
+        assigned_value: &Value,
+        line_id: LineId,
+        scratch_space: &mut Self::Memory,
+    ) {
+        match (receiving_var, assigned_value) {
+            // the returned data is written in memory
+            (
+                _,
+                Value::Calculation {
+                    opcode:
+                        Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL | Opcode::STATICCALL,
+                    args: _,
+                },
+            ) => scratch_space.clear(),
+            (
+                None,
+                Value::Calculation {
+                    opcode: Opcode::MSTORE,
+                    args,
+                },
+            ) => match args[0].compute_value() {
+                Some(offset) => {
+                    write_scratch_space(scratch_space, offset, U256::from(0x20));
+                    if offset % 0x20 == U256::zero() && offset < U256::from(SCRATCH_SPACE_SIZE) {
+                        scratch_space.insert(offset, (args[1].clone(), line_id));
+                    }
+                }
+                None => scratch_space.clear(),
+            },
+            (
+                None,
+                Value::Calculation {
+                    opcode: Opcode::MSTORE8,
+                    args,
+                },
+            ) => match args[0].compute_value() {
+                Some(offset) => write_scratch_space(scratch_space, offset, U256::one()),
+                None => scratch_space.clear(),
+            },
+            (
+                None,
+                Value::Calculation {
+                    opcode:
+                        Opcode::CALLDATACOPY
+                        | Opcode::CODECOPY
+                        | Opcode::RETURNDATACOPY
+                        | Opcode::MCOPY
+                        | Opcode::DATACOPY,
+                    args,
+                },
+            ) => match (args[0].compute_value(), args[2].compute_value()) {
+                (Some(offset), Some(size)) => write_scratch_space(scratch_space, offset, size),
+                _ => scratch_space.clear(),
+            },
+            (
+                None,
+                Value::Calculation {
+                    opcode: Opcode::EXTCODECOPY,
+                    args,
+                },
+            ) => match (args[1].compute_value(), args[3].compute_value()) {
+                (Some(offset), Some(size)) => write_scratch_space(scratch_space, offset, size),
+                _ => scratch_space.clear(),
+            },
+            (Some(var), slot @ Value::StorageSlot { slot: _, keys: _ })
+                if self.n_initializations_per_var[&var] == 1 =>
+            {
+                self.slot_vars.insert(var, slot.clone());
+                self.slot_var_lines.insert(line_id);
+            }
+            _ => (),
+        }
+    }
+
+    fn read_memory(&mut self, scratch_space: &Self::Memory) {
+        self.read_writes
+            .extend(scratch_space.values().map(|(_, line_id)| *line_id));
+    }
+
+    fn forget_memory(&mut self, scratch_space: &mut Self::Memory) {
+        scratch_space.clear();
+    }
+}
+
+impl ScratchSpaceUses {
+    // A read of `size` bytes at `offset` only reads the scratch space words it overlaps.
+    fn read_scratch_space(
+        &mut self,
+        scratch_space: &HashMap<U256, (Value, LineId)>,
+        offset: U256,
+        size: U256,
+    ) {
+        self.read_writes.extend(
+            scratch_space
+                .iter()
+                .filter(|(word_offset, _)| {
+                    !size.is_zero()
+                        && **word_offset < offset.saturating_add(size)
+                        && offset < **word_offset + 0x20
+                })
+                .map(|(_, (_, line_id))| *line_id),
+        );
+    }
+}
+
+// Replaces `keccak` of the scratch space by the storage slot it computes.
+fn hash_scratch_space(
+    value: &mut Value,
+    scratch_space: &HashMap<U256, (Value, LineId)>,
+    uses: &mut ScratchSpaceUses,
+) {
+    let (opcode, args) = match value {
+        Value::Calculation { opcode, args } => (*opcode, args),
+        _ => return,
+    };
+    if opcode == Opcode::SHA3 {
+        if let Some(slot) = get_hashed_slot(args, scratch_space, uses) {
+            *value = slot;
+            return;
+        }
+    }
+    if reads_memory(opcode) {
+        match get_read_memory(opcode, args) {
+            Some((offset, size)) => uses.read_scratch_space(scratch_space, offset, size),
+            None => uses.read_memory(scratch_space),
+        }
+    }
+}
+
+fn get_hashed_slot(
+    args: &[Value],
+    scratch_space: &HashMap<U256, (Value, LineId)>,
+    uses: &mut ScratchSpaceUses,
+) -> Option<Value> {
+    if args[0].compute_value()? != U256::zero() {
+        return None;
+    }
+    let size: U256 = args[1].compute_value()?;
+    let (kind, key, hashed_words): (StorageKind, Value, Vec<U256>) = if size == U256::from(0x40) {
+        // keccak(key . slot)
+        (
+            StorageKind::Mapping,
+            scratch_space.get(&U256::zero())?.0.clone(),
+            vec![U256::zero(), U256::from(0x20)],
+        )
+    } else if size == U256::from(0x20) {
+        // keccak(slot), the first element of an array
+        (
+            StorageKind::Array,
+            Value::Bytes(U256::zero()),
+            vec![U256::zero()],
+        )
+    } else {
+        return None;
+    };
+    let (parent, _) = scratch_space.get(hashed_words.last().unwrap())?;
+    let parent: &Value = match parent {
+        Value::Existing(var) => uses.slot_vars.get(var)?,
+        _ => parent,
+    };
+    let slot: Value = match parent {
+        Value::Bytes(slot) => Value::StorageSlot {
+            slot: *slot,
+            keys: vec![(kind, key)],
+        },
+        Value::StorageSlot { slot, keys } if !keys.is_empty() => {
+            let mut keys: Vec<(StorageKind, Value)> = keys.clone();
+            keys.push((kind, key));
+            Value::StorageSlot { slot: *slot, keys }
+        }
+        _ => return None,
+    };
+    for word in hashed_words {
+        uses.hashed_writes.insert(scratch_space[&word].1);
+    }
+    return Some(slot);
+}
+
+// A write of `size` bytes at `offset` overwrites the scratch space words it overlaps.
+fn write_scratch_space(
+    scratch_space: &mut HashMap<U256, (Value, LineId)>,
+    offset: U256,
+    size: U256,
+) {
+    scratch_space.retain(|word_offset, _| {
+        size.is_zero()
+            || offset.saturating_add(size) <= *word_offset
+            || *word_offset + 0x20 <= offset
+    });
+}
+
+// `keccak(slot) + index` is the element `index` of an array.
+fn index_array_element(value: &mut Value) {
+    if let Value::Calculation {
+        opcode: Opcode::ADD,
+        args,
+    } = value
+    {
+        for (element, index) in [(0, 1), (1, 0)] {
+            if let Value::StorageSlot { slot, keys } = &args[element] {
+                if keys.last() == Some(&(StorageKind::Array, Value::Bytes(U256::zero()))) {
+                    let mut keys: Vec<(StorageKind, Value)> = keys.clone();
+                    keys.last_mut().unwrap().1 = args[index].clone();
+                    *value = Value::StorageSlot { slot: *slot, keys };
+                    return;
+                }
+            }
+        }
+    }
+}
+
+// What a pass follows of the memory, with the lines writing it.
+pub trait MemoryTracker {
+    type Memory: Clone + PartialEq;
+
+    // Nested values are visited before the ones containing them, and before the line is.
+    fn visit_value(&mut self, value: &mut Value, memory: &mut Self::Memory);
+
+    fn visit_assignment(
+        &mut self,
+        receiving_var: Option<Variable>,
+        assigned_value: &Value,
+        line_id: LineId,
+        memory: &mut Self::Memory,
+    );
+
+    // Every write still in memory may be read.
+    fn read_memory(&mut self, memory: &Self::Memory);
+
+    fn forget_memory(&mut self, memory: &mut Self::Memory);
+}
+
+// Visits the lines in execution order. The memory is only kept after a condition when both
+// branches leave it the same, and is forgotten around loops and after function calls.
+pub fn track_memory_in_scopes<T: MemoryTracker>(
+    scopes: &mut Vec<VarScope>,
+    memory: &mut T::Memory,
+    tracker: &mut T,
+    next_line_id: &mut LineId,
+) {
+    for scope in scopes {
+        match scope {
+            Scope::Instructions(instructions) => {
+                for line in &mut instructions.lines {
+                    let line_id: LineId = *next_line_id;
+                    *next_line_id += 1;
+                    let values: Vec<&mut Value> = match line {
+                        Line::Assignement {
+                            receiving_var: _,
+                            assigned_value,
+                        } => vec![assigned_value],
+                        Line::If { condition } => vec![condition],
+                        Line::DynamicJump {
+                            condition,
+                            destination,
+                        } => condition.iter_mut().chain([destination]).collect(),
+                        Line::Require { condition, error } => {
+                            [condition].into_iter().chain(error).collect()
+                        }
+                        Line::Empty => Vec::new(),
+                    };
+                    for value in values {
+                        apply_on_value_mut(value, &mut |value: &mut Value| {
+                            tracker.visit_value(value, memory);
+                        });
+                    }
+                    if let Line::Assignement {
+                        receiving_var,
+                        assigned_value,
+                    } = line
+                    {
+                        tracker.visit_assignment(*receiving_var, assigned_value, line_id, memory);
+                    }
+                }
+            }
+            Scope::FunctionCall(function_call) => {
+                for argument in &mut function_call.arguments {
+                    apply_on_value_mut(argument, &mut |value: &mut Value| {
+                        tracker.visit_value(value, memory);
+                    });
+                }
+                // the called function may read and write anything
+                tracker.read_memory(memory);
+                tracker.forget_memory(memory);
+            }
+            Scope::FunctionReturn(function_return) => {
+                for returned_value in &mut function_return.returned_values {
+                    apply_on_value_mut(returned_value, &mut |value: &mut Value| {
+                        tracker.visit_value(value, memory);
+                    });
+                }
+            }
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => {
+                let mut memory_if_true: T::Memory = memory.clone();
+                track_memory_in_scopes(
+                    instructions_if_true,
+                    &mut memory_if_true,
+                    tracker,
+                    next_line_id,
+                );
+                track_memory_in_scopes(instructions_if_false, memory, tracker, next_line_id);
+                if memory_if_true != *memory {
+                    tracker.forget_memory(memory);
+                }
+            }
+            Scope::Loop { label: _ }
+            | Scope::LoopContinue { label: _ }
+            | Scope::LoopBreak { label: _ } => tracker.forget_memory(memory),
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                tracker.forget_memory(memory);
+                track_memory_in_scopes(header, memory, tracker, next_line_id);
+                track_memory_in_scopes(body, memory, tracker, next_line_id);
+                tracker.forget_memory(memory);
+            }
+            Scope::Panic | Scope::Empty => (),
+        }
+    }
+}
+
+pub fn reads_memory(opcode: Opcode) -> bool {
+    return matches!(
+        opcode,
+        Opcode::MLOAD
+            | Opcode::SHA3
+            | Opcode::MCOPY
+            | Opcode::CALL
+            | Opcode::CALLCODE
+            | Opcode::DELEGATECALL
+            | Opcode::STATICCALL
+            | Opcode::EXTCALL
+            | Opcode::EXTDELEGATECALL
+            | Opcode::EXTSTATICCALL
+            | Opcode::CREATE
+            | Opcode::CREATE2
+            | Opcode::EOFCREATE { container: _ }
+            | Opcode::RETURN
+            | Opcode::RETURNCONTRACT { container: _ }
+            | Opcode::REVERT
+            | Opcode::LOG { topic_count: _ }
+    );
+}
+
+// Constant offset and size of the memory read by an opcode.
+fn get_read_memory(opcode: Opcode, args: &[Value]) -> Option<(U256, U256)> {
+    let (offset, size): (&Value, &Value) = match opcode {
+        Opcode::MLOAD => return Some((args[0].compute_value()?, U256::from(0x20))),
+        Opcode::SHA3
+        | Opcode::RETURN
+        | Opcode::REVERT
+        | Opcode::LOG { topic_count: _ }
+        | Opcode::RETURNCONTRACT { container: _ } => (&args[0], &args[1]),
+        Opcode::MCOPY
+        | Opcode::CREATE
+        | Opcode::CREATE2
+        | Opcode::EXTCALL
+        | Opcode::EXTDELEGATECALL
+        | Opcode::EXTSTATICCALL => (&args[1], &args[2]),
+        Opcode::DELEGATECALL | Opcode::STATICCALL | Opcode::EOFCREATE { container: _ } => {
+            (&args[2], &args[3])
+        }
+        Opcode::CALL | Opcode::CALLCODE => (&args[3], &args[4]),
+        _ => return None,
+    };
+    return Some((offset.compute_value()?, size.compute_value()?));
+}
+
+pub fn remove_lines_in_scopes(
+    scopes: &mut Vec<VarScope>,
+    lines_to_remove: &HashSet<LineId>,
+    next_line_id: &mut LineId,
+) {
+    for scope in scopes {
+        match scope {
+            Scope::Instructions(instructions) => {
+                for line in &mut instructions.lines {
+                    if lines_to_remove.contains(next_line_id) {
+                        *line = Line::Empty;
+                    }
+                    *next_line_id += 1;
+                }
+            }
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => {
+                remove_lines_in_scopes(instructions_if_true, lines_to_remove, next_line_id);
+                remove_lines_in_scopes(instructions_if_false, lines_to_remove, next_line_id);
+            }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                remove_lines_in_scopes(header, lines_to_remove, next_line_id);
+                remove_lines_in_scopes(body, lines_to_remove, next_line_id);
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompiler::Decompiler;
+    use crate::execution_flow::with_variables::scopes_with_vars::InstructionsWithVars;
+
+    fn calculation(opcode: Opcode, args: &[Value]) -> Value {
+        return Value::Calculation {
+            opcode,
+            args: args.to_vec(),
+        };
+    }
+
+    fn bytes(value: u64) -> Value {
+        return Value::Bytes(U256::from(value));
+    }
+
+    fn line(value: Value) -> Line {
+        return Line::Assignement {
+            receiving_var: None,
+            assigned_value: value,
+        };
+    }
+
+    // first argument of each use of `opcode`, the address for memory and storage accesses
+    fn get_addresses(scopes: &Vec<VarScope>, opcode: Opcode) -> Vec<Value> {
+        let mut addresses: Vec<Value> = Vec::new();
+        apply_on_values(scopes, &mut |value: &Value| {
+            if let Value::Calculation {
+                opcode: value_opcode,
+                args,
+            } = value
+            {
+                if *value_opcode == opcode {
+                    addresses.push(args[0].clone());
+                }
+            }
+        });
+        return addresses;
+    }
+
+    #[test]
+    fn test_recognise_storage_variables() {
+        // balance[msg.sender] += 1, then read list[calldata[4]] and list.length
+        let decompiler: Decompiler = Decompiler::from_hex(
+            "33600052600160205260406000205460010133600052600160205260406000205560036000526020600020600435015460055560035460065500",
+        )
+        .unwrap();
+        let flow: ExecutionFlowWithVars = decompiler.decompile().unwrap().execution_flow_with_vars;
+        assert_eq!(
+            flow.get_storage_variables(),
+            BTreeMap::from([
+                (U256::one(), (StorageKind::Mapping, 1)),
+                (U256::from(3), (StorageKind::Array, 1)),
+            ])
+        );
+        let balance: Value = Value::StorageSlot {
+            slot: U256::one(),
+            keys: vec![(StorageKind::Mapping, calculation(Opcode::CALLER, &[]))],
+        };
+        let element: Value = Value::StorageSlot {
+            slot: U256::from(3),
+            keys: vec![(
+                StorageKind::Array,
+                calculation(Opcode::CALLDATALOAD, &[bytes(4)]),
+            )],
+        };
+        let length: Value = Value::StorageSlot {
+            slot: U256::from(3),
+            keys: Vec::new(),
+        };
+        for function in flow.functions.values() {
+            assert_eq!(
+                get_addresses(&function.content, Opcode::SLOAD),
+                vec![balance.clone(), element.clone(), length.clone()]
+            );
+            assert_eq!(
+                get_addresses(&function.content, Opcode::SSTORE),
+                vec![balance.clone(), bytes(5), bytes(6)]
+            );
+            assert!(get_addresses(&function.content, Opcode::MSTORE).is_empty());
+            assert!(get_addresses(&function.content, Opcode::SHA3).is_empty());
+        }
+
+        // storage[0] = keccak of 2^64 bytes
+        let decompiler: Decompiler =
+            Decompiler::from_hex("6801000000000000000060002060005500").unwrap();
+        let flow: ExecutionFlowWithVars = decompiler.decompile().unwrap().execution_flow_with_vars;
+        assert!(flow.get_storage_variables().is_empty());
+    }
+
+    #[test]
+    fn test_hash_scratch_space() {
+        let pointer: Value = Value::Existing(Variable { alias: 0 });
+        let key: Value = calculation(Opcode::CALLER, &[]);
+        let mapping_element: Value = calculation(
+            Opcode::SSTORE,
+            &[
+                calculation(Opcode::SHA3, &[bytes(0), bytes(0x40)]),
+                bytes(1),
+            ],
+        );
+        let get_scopes = |line_before_hash: Line| -> Vec<VarScope> {
+            let lines: Vec<Line> = vec![
+                line(calculation(Opcode::MSTORE, &[bytes(0), key.clone()])),
+                line(calculation(Opcode::MSTORE, &[bytes(0x20), bytes(1)])),
+                line_before_hash,
+                line(mapping_element.clone()),
+            ];
+            return vec![Scope::Instructions(InstructionsWithVars { lines })];
+        };
+
+        // loading the free memory pointer does not read the scratch space
+        let mut scopes: Vec<VarScope> = get_scopes(Line::Assignement {
+            receiving_var: Some(Variable { alias: 0 }),
+            assigned_value: calculation(Opcode::MLOAD, &[bytes(0x40)]),
+        });
+        recognise_storage_variables_in_function(&mut scopes);
+        assert_eq!(
+            get_addresses(&scopes, Opcode::SSTORE),
+            vec![Value::StorageSlot {
+                slot: U256::one(),
+                keys: vec![(StorageKind::Mapping, key.clone())],
+            }]
+        );
+        assert!(get_addresses(&scopes, Opcode::MSTORE).is_empty());
+
+        // the scratch space may be overwritten
+        for line_before_hash in [
+            line(calculation(Opcode::MSTORE, &[pointer.clone(), bytes(2)])),
+            line(calculation(
+                Opcode::RETURNDATACOPY,
+                &[pointer.clone(), bytes(0), bytes(0x20)],
+            )),
+            Line::Assignement {
+                receiving_var: Some(Variable { alias: 1 }),
+                assigned_value: calculation(
+                    Opcode::STATICCALL,
+                    &[
+                        calculation(Opcode::GAS, &[]),
+                        pointer.clone(),
+                        bytes(0x80),
+                        bytes(0x04),
+                        bytes(0),
+                        bytes(0x20),
+                    ],
+                ),
+            },
+        ] {
+            let mut scopes: Vec<VarScope> = get_scopes(line_before_hash.clone());
+            recognise_storage_variables_in_function(&mut scopes);
+            assert_eq!(scopes, get_scopes(line_before_hash));
+        }
+    }
+}
@@ -0,0 +1,537 @@
+use std::collections::{HashMap, HashSet};
+
+use primitive_types::U256;
+
+use super::{
+    abi_parameters::{apply_on_values, apply_on_values_mut},
+    flow_with_vars::ExecutionFlowWithVars,
+    incorporate_variables::{Line, ParameterField, Value, Variable},
+    scopes_with_vars::{FunctionWithVars, VarScope},
+};
+use crate::{bytecode_reader::opcode::Opcode, execution_flow::execution_flow::Scope};
+
+// Variables without a known type are 256 bits words.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum VarType {
+    Address,
+    Bool,
+    Int { bits: usize },
+    FixedBytes { size: usize },
+}
+
+impl ToString for VarType {
+    fn to_string(&self) -> String {
+        match self {
+            VarType::Address => return "address".to_owned(),
+            VarType::Bool => return "bool".to_owned(),
+            VarType::Int { bits } => return format!("int{}", bits),
+            VarType::FixedBytes { size } => return format!("bytes{}", size),
+        }
+    }
+}
+
+impl ExecutionFlowWithVars {
+    // Types come from the way values are built (masks, comparisons, sign extensions, shifts) and
+    // from the way variables are used (call targets, account queries). The cleanups made useless by
+    // the types are then removed.
+    pub fn infer_types(&mut self) {
+        for function in self.functions.values_mut() {
+            infer_types_of_function(function);
+        }
+    }
+}
+
+fn infer_types_of_function(function: &mut FunctionWithVars) {
+    let mut var_types: HashMap<Variable, VarType> = HashMap::new();
+    loop {
+        let mut types_per_var: HashMap<Variable, Vec<Option<VarType>>> = HashMap::new();
+        ExecutionFlowWithVars::apply_on_scopes(&function.content, &mut |scope: &VarScope| {
+            if let Scope::Instructions(instructions) = scope {
+                for line in &instructions.lines {
+                    if let Line::Assignement {
+                        receiving_var: Some(receiving_var),
+                        assigned_value,
+                    } = line
+                    {
+                        types_per_var
+                            .entry(*receiving_var)
+                            .or_default()
+                            .push(get_value_type(
+                                assigned_value,
+                                &var_types,
+                                &function.parameters,
+                            ));
+                    }
+                }
+            }
+        });
+        // every assignment must agree
+        let new_var_types: HashMap<Variable, VarType> = types_per_var
+            .into_iter()
+            .filter_map(|(var, types)| match types[0] {
+                Some(var_type) if types.iter().all(|other| *other == Some(var_type)) => {
+                    Some((var, var_type))
+                }
+                _ => None,
+            })
+            .collect();
+        if new_var_types == var_types {
+            break;
+        }
+        var_types = new_var_types;
+    }
+
+    // `var == var & mask` checks that `var` fits in the mask, it tells nothing about `var`
+    let compared_cleanups: HashSet<Value> = get_compared_cleanups(&function.content);
+    let mut parameters: Vec<String> = function.parameters.clone();
+    let mut used_types: HashMap<Variable, VarType> = HashMap::new();
+    apply_on_values(&function.content, &mut |value: &Value| {
+        if compared_cleanups.contains(value) {
+            return;
+        }
+        for (used_value, used_type) in get_used_types(value) {
+            match used_value {
+                Value::Existing(var) => {
+                    used_types.entry(*var).or_insert(used_type);
+                }
+                Value::Parameter {
+                    index,
+                    field: ParameterField::Word,
+                } if used_type == VarType::Address => {
+                    if let Some(parameter) = parameters.get_mut(*index) {
+                        *parameter = "address".to_owned();
+                    }
+                }
+                _ => (),
+            }
+        }
+    });
+    for (var, used_type) in used_types {
+        var_types.entry(var).or_insert(used_type);
+    }
+    function.parameters = parameters;
+
+    apply_on_values_mut(&mut function.content, &mut |value: &mut Value| {
+        if !compared_cleanups.contains(value) {
+            remove_redundant_cleanup(value, &var_types, &function.parameters);
+        }
+    });
+    remove_cleanups_of_declarations(&mut function.content, &var_types);
+    function.var_types = var_types;
+}
+
+fn get_value_type(
+    value: &Value,
+    var_types: &HashMap<Variable, VarType>,
+    parameters: &[String],
+) -> Option<VarType> {
+    match value {
+        Value::Existing(var) => return var_types.get(var).copied(),
+        Value::Parameter {
+            index,
+            field: ParameterField::Word,
+        } if parameters.get(*index).map(String::as_str) == Some("address") => {
+            return Some(VarType::Address);
+        }
+        Value::Calculation { opcode, args } => match opcode {
+            Opcode::AND => {
+                let masks: Vec<U256> = args.iter().filter_map(|arg| arg.compute_value()).collect();
+                if masks.len() != 1 {
+                    return None;
+                }
+                return get_mask_type(masks[0]);
+            }
+            Opcode::ISZERO | Opcode::EQ | Opcode::LT | Opcode::GT | Opcode::SLT | Opcode::SGT => {
+                return Some(VarType::Bool);
+            }
+            Opcode::SIGNEXTEND => {
+                let byte_index: U256 = args[0].compute_value()?;
+                if byte_index >= U256::from(31) {
+                    return None;
+                }
+                return Some(VarType::Int {
+                    bits: 8 * (byte_index.as_usize() + 1),
+                });
+            }
+            Opcode::SDIV | Opcode::SMOD | Opcode::SAR => return Some(VarType::Int { bits: 256 }),
+            Opcode::BYTE => return Some(VarType::FixedBytes { size: 1 }),
+            Opcode::SHL => {
+                let shift: U256 = args[0].compute_value()?;
+                if shift.is_zero() || shift >= U256::from(256) || shift % 8 != U256::zero() {
+                    return None;
+                }
+                return Some(VarType::FixedBytes {
+                    size: 32 - shift.as_usize() / 8,
+                });
+            }
+            Opcode::SHA3 => return Some(VarType::FixedBytes { size: 32 }),
+            Opcode::CALLER | Opcode::ORIGIN | Opcode::ADDRESS | Opcode::COINBASE => {
+                return Some(VarType::Address);
+            }
+            _ => return None,
+        },
+        _ => return None,
+    }
+}
+
+// Arguments whose type is given by the opcode using them.
+fn get_used_types(value: &Value) -> Vec<(&Value, VarType)> {
+    match value {
+        Value::Calculation { opcode, args } => match opcode {
+            Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL | Opcode::STATICCALL => {
+                return vec![(&args[1], VarType::Address)];
+            }
+            Opcode::EXTCALL
+            | Opcode::EXTDELEGATECALL
+            | Opcode::EXTSTATICCALL
+            | Opcode::BALANCE
+            | Opcode::EXTCODESIZE
+            | Opcode::EXTCODECOPY
+            | Opcode::EXTCODEHASH => {
+                return vec![(&args[0], VarType::Address)];
+            }
+            // an address is cleaned before being used
+            Opcode::AND => {
+                if args[0].compute_value() == Some(address_mask()) {
+                    return vec![(&args[1], VarType::Address)];
+                }
+                if args[1].compute_value() == Some(address_mask()) {
+                    return vec![(&args[0], VarType::Address)];
+                }
+                return Vec::new();
+            }
+            _ => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    }
+}
+
+// Masked values compared to the value before the mask.
+fn get_compared_cleanups(scopes: &Vec<VarScope>) -> HashSet<Value> {
+    let mut compared_cleanups: HashSet<Value> = HashSet::new();
+    apply_on_values(scopes, &mut |value: &Value| {
+        if let Value::Calculation {
+            opcode: Opcode::EQ,
+            args,
+        } = value
+        {
+            for (cleaned, other) in [(&args[0], &args[1]), (&args[1], &args[0])] {
+                if let Value::Calculation {
+                    opcode: Opcode::AND,
+                    args: masked_args,
+                } = cleaned
+                {
+                    if masked_args.contains(other) {
+                        compared_cleanups.insert(cleaned.clone());
+                    }
+                }
+            }
+        }
+    });
+    return compared_cleanups;
+}
+
+fn address_mask() -> U256 {
+    return U256::MAX >> 96;
+}
+
+// Addresses are the 20 lowest bytes, bytesN the N highest ones.
+fn get_mask_type(mask: U256) -> Option<VarType> {
+    if mask == address_mask() {
+        return Some(VarType::Address);
+    }
+    let size: usize = (!mask).leading_zeros() as usize / 8;
+    if size > 0 && size < 32 && mask == !(U256::MAX >> (8 * size)) {
+        return Some(VarType::FixedBytes { size });
+    }
+    return None;
+}
+
+// `var & 0xff..ff` with an address `var`, `!(!var)` with a boolean `var`.
+fn remove_redundant_cleanup(
+    value: &mut Value,
+    var_types: &HashMap<Variable, VarType>,
+    parameters: &[String],
+) {
+    let cleaned: Option<Value> = match value {
+        Value::Calculation {
+            opcode: Opcode::AND,
+            args,
+        } => {
+            if args[0].compute_value() == Some(address_mask())
+                && get_value_type(&args[1], var_types, parameters) == Some(VarType::Address)
+            {
+                Some(args[1].clone())
+            } else if args[1].compute_value() == Some(address_mask())
+                && get_value_type(&args[0], var_types, parameters) == Some(VarType::Address)
+            {
+                Some(args[0].clone())
+            } else {
+                None
+            }
+        }
+        Value::Calculation {
+            opcode: Opcode::ISZERO,
+            args,
+        } => match &args[0] {
+            Value::Calculation {
+                opcode: Opcode::ISZERO,
+                args: negated_args,
+            } if get_value_type(&negated_args[0], var_types, parameters) == Some(VarType::Bool) => {
+                Some(negated_args[0].clone())
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    if let Some(cleaned) = cleaned {
+        *value = cleaned;
+    }
+}
+
+// The declared type replaces the cleanup of the assigned value.
+fn remove_cleanups_of_declarations(
+    scopes: &mut Vec<VarScope>,
+    var_types: &HashMap<Variable, VarType>,
+) {
+    for scope in scopes {
+        match scope {
+            Scope::Instructions(instructions) => {
+                for line in &mut instructions.lines {
+                    if let Line::Assignement {
+                        receiving_var: Some(receiving_var),
+                        assigned_value,
+                    } = line
+                    {
+                        if let Some(var_type) = var_types.get(receiving_var) {
+                            remove_cleanup_of_declaration(assigned_value, *var_type);
+                        }
+                    }
+                }
+            }
+            Scope::Condition {
+                instructions_if_true,
+                instructions_if_false,
+            } => {
+                remove_cleanups_of_declarations(instructions_if_true, var_types);
+                remove_cleanups_of_declarations(instructions_if_false, var_types);
+            }
+            Scope::StructuredLoop {
+                label: _,
+                kind: _,
+                header,
+                body,
+            } => {
+                remove_cleanups_of_declarations(header, var_types);
+                remove_cleanups_of_declarations(body, var_types);
+            }
+            _ => (),
+        }
+    }
+}
+
+fn remove_cleanup_of_declaration(value: &mut Value, var_type: VarType) {
+    let cleaned: Option<Value> = match (var_type, &value) {
+        (
+            VarType::Address | VarType::FixedBytes { size: _ },
+            Value::Calculation {
+                opcode: Opcode::AND,
+                args,
+            },
+        ) => {
+            if args[0].compute_value().and_then(get_mask_type) == Some(var_type) {
+                Some(args[1].clone())
+            } else if args[1].compute_value().and_then(get_mask_type) == Some(var_type) {
+                Some(args[0].clone())
+            } else {
+                None
+            }
+        }
+        (
+            VarType::Bool,
+            Value::Calculation {
+                opcode: Opcode::ISZERO,
+                args,
+            },
+        ) => match &args[0] {
+            Value::Calculation {
+                opcode: Opcode::ISZERO,
+                args: negated_args,
+            } => Some(negated_args[0].clone()),
+            _ => None,
+        },
+        (
+            VarType::Int { bits: _ },
+            Value::Calculation {
+                opcode: Opcode::SIGNEXTEND,
+                args,
+            },
+        ) => Some(args[1].clone()),
+        _ => None,
+    };
+    if let Some(cleaned) = cleaned {
+        *value = cleaned;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decompiler::Decompiler;
+    use crate::execution_flow::with_variables::scopes_with_vars::{
+        FunctionKind, InstructionsWithVars,
+    };
+    use crate::tools::utils::read_file;
+
+    fn calculation(opcode: Opcode, args: &[Value]) -> Value {
+        return Value::Calculation {
+            opcode,
+            args: args.to_vec(),
+        };
+    }
+
+    fn decompile(bytecode: &str) -> Vec<FunctionWithVars> {
+        let decompiler: Decompiler = Decompiler::from_hex(bytecode).unwrap();
+        return decompiler
+            .decompile()
+            .unwrap()
+            .execution_flow_with_vars
+            .functions
+            .into_values()
+            .collect();
+    }
+
+    fn get_assignments(function: &FunctionWithVars) -> Vec<(Variable, Value)> {
+        let mut assignments: Vec<(Variable, Value)> = Vec::new();
+        ExecutionFlowWithVars::apply_on_scopes(&function.content, &mut |scope: &VarScope| {
+            if let Scope::Instructions(instructions) = scope {
+                for line in &instructions.lines {
+                    if let Line::Assignement {
+                        receiving_var: Some(receiving_var),
+                        assigned_value,
+                    } = line
+                    {
+                        assignments.push((*receiving_var, assigned_value.clone()));
+                    }
+                }
+            }
+        });
+        return assignments;
+    }
+
+    #[test]
+    fn test_infer_types() {
+        let mut n_comparisons: usize = 0;
+        for function in decompile(&read_file("./contracts/simple/contract_0/bytecode.txt")) {
+            for (var, assigned_value) in get_assignments(&function) {
+                if let Value::Calculation {
+                    opcode: Opcode::GT,
+                    args: _,
+                } = assigned_value
+                {
+                    assert_eq!(function.var_types.get(&var), Some(&VarType::Bool));
+                    n_comparisons += 1;
+                }
+            }
+            // `!(!var)` with a boolean `var`
+            apply_on_values(&function.content, &mut |value: &Value| {
+                if let Value::Calculation {
+                    opcode: Opcode::ISZERO,
+                    args,
+                } = value
+                {
+                    assert!(!matches!(
+                        args[0],
+                        Value::Calculation {
+                            opcode: Opcode::ISZERO,
+                            args: _
+                        }
+                    ));
+                }
+            });
+        }
+        assert!(n_comparisons > 0);
+
+        // a storage key can hold any word
+        for function in decompile("60005480600190556002905500") {
+            assert!(function.var_types.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_remove_redundant_cleanup() {
+        // 0x11111111: storage[0] = arg0 & 0xff..ff, then call arg0 & 0xff..ff
+        let functions: Vec<FunctionWithVars> = decompile(
+            "60003560e01c80631111111114601157005b60043573ffffffffffffffffffffffffffffffffffffffff168060005560006000600060006000855af15000",
+        );
+        let function: &FunctionWithVars = functions
+            .iter()
+            .find(|function| {
+                function.kind
+                    == FunctionKind::External {
+                        selector: 0x11111111,
+                    }
+            })
+            .unwrap();
+        assert_eq!(function.parameters, vec!["address"]);
+        let address: Value = Value::Parameter {
+            index: 0,
+            field: ParameterField::Word,
+        };
+        let mut used_values: Vec<Value> = Vec::new();
+        apply_on_values(&function.content, &mut |value: &Value| match value {
+            Value::Calculation {
+                opcode: Opcode::SSTORE,
+                args,
+            } => used_values.push(args[1].clone()),
+            Value::Calculation {
+                opcode: Opcode::CALL,
+                args,
+            } => used_values.push(args[1].clone()),
+            _ => (),
+        });
+        assert_eq!(used_values, vec![address.clone(), address]);
+    }
+
+    #[test]
+    fn test_compared_cleanup() {
+        // `if var == (var & ((1 << 0xa0) - 1))` checks that `var` is an address
+        let var: Value = Value::Existing(Variable { alias: 0 });
+        let mask: Value = calculation(
+            Opcode::SUB,
+            &[
+                calculation(
+                    Opcode::SHL,
+                    &[Value::Bytes(U256::from(0xa0)), Value::Bytes(U256::one())],
+                ),
+                Value::Bytes(U256::one()),
+            ],
+        );
+        let condition: Value = calculation(
+            Opcode::EQ,
+            &[var.clone(), calculation(Opcode::AND, &[var, mask])],
+        );
+        let mut function: FunctionWithVars = FunctionWithVars {
+            label: 0,
+            kind: FunctionKind::Internal,
+            signature: None,
+            parameters: Vec::new(),
+            var_types: HashMap::new(),
+            input_vars: vec![Variable { alias: 0 }],
+            n_outputs: 0,
+            returns: false,
+            content: vec![Scope::Instructions(InstructionsWithVars {
+                lines: vec![Line::If {
+                    condition: condition.clone(),
+                }],
+            })],
+        };
+        infer_types_of_function(&mut function);
+
+        assert!(function.var_types.is_empty());
+        let Scope::Instructions(instructions) = &function.content[0] else {
+            panic!("The condition should stay in its scope.");
+        };
+        assert_eq!(instructions.lines, vec![Line::If { condition }]);
+    }
+}
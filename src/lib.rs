@@ -0,0 +1,11 @@
+pub mod bytecode_reader;
+pub mod create_blocks;
+pub mod create_graph;
+pub mod decompiler;
+pub mod detect_cycles;
+pub mod detect_functions;
+pub mod error;
+pub mod execution_flow;
+pub mod research_and_development;
+pub mod signatures;
+pub mod tools;
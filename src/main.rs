@@ -1,68 +1,142 @@
-pub mod bytecode_reader;
-pub mod create_blocks;
-pub mod create_graph;
-pub mod detect_cycles;
-pub mod detect_functions;
-pub mod execution_flow;
-pub mod research_and_development;
-pub mod tools;
-use crate::research_and_development::draw_graph_v1::draw_graph_to_str;
-use crate::tools::utils::file_exists;
-use bytecode_reader::bytecode::Bytecode;
-use bytecode_reader::metadata;
-use create_graph::graph::Graph;
-use detect_cycles::acyclic_graph::AcyclicGraph;
-use execution_flow::skeleton::skeleton::Skeleton;
-use execution_flow::with_opcodes::flow_with_opcodes::{
-    convert_skeleton_to_execution_flow, ExecutionFlowWithOpcodes,
-};
-use execution_flow::with_variables::flow_with_vars::{
-    convert_opcode_flow_to_var_flow, ExecutionFlowWithVars,
-};
-use tools::utils::write_file;
+use pellucid::bytecode_reader::hardfork::Hardfork;
+use pellucid::bytecode_reader::metadata;
+use pellucid::create_graph::graph::Graph;
+use pellucid::decompiler::{CreationDecompiler, Decompiler, PipelineInspector};
+use pellucid::detect_cycles::acyclic_graph::AcyclicGraph;
+use pellucid::error::PellucidError;
+use pellucid::execution_flow::skeleton::skeleton::Skeleton;
+use pellucid::execution_flow::with_variables::storage_layout::StorageLayout;
+use pellucid::research_and_development::draw_graph_v1::draw_graph_to_str;
+use pellucid::signatures::signature_database::SignatureDatabase;
+use pellucid::tools::utils::{file_exists, write_file};
+
+struct ProgressPrinter;
+
+impl PipelineInspector for ProgressPrinter {
+    fn on_graph(&mut self, graph: &Graph) {
+        print!("\tDone");
+        for reached_limit in &graph.reached_limits {
+            print!("\n\tWarning: {}", reached_limit.to_string());
+        }
+        print!("\nDetecting loops...");
+    }
+
+    fn on_acyclic_graph(&mut self, _a_graph: &AcyclicGraph) {
+        print!("\tDone\nDetecting internal function...");
+    }
+
+    fn on_skeleton(&mut self, _skeleton: &Skeleton) {
+        print!("\tDone\nBuilding execution flow...");
+    }
+}
+
+fn report_error(error: PellucidError) {
+    match error {
+        PellucidError::InvalidHex(_) => {
+            println!("Cannot read bytecode, please ensure it's hexadecimal.")
+        }
+        _ => println!("Error: {}", error),
+    }
+}
 
 fn main() {
     let info_msg: String = String::from(
-        "Expecting 2 args: ./pellucid [mode] [bytecode]
+        "Expecting 2 args: ./pellucid [mode] [bytecode] ([signatures])
 
 modes: - decompile        (result will be stored in 'decompiled.txt')
+       - decompile-creation (same, for creation bytecode: constructor and runtime)
+       - storage-layout   (result will be stored in 'storage_layout.txt' and
+                           'storage_layout.json')
        - disassemble      (result will be stored in 'opcodes.txt')
        - metadata         (result will be displayed in terminal)
        - graph            (result will be stored in 'graph.txt')
 
-bytecode: the runtime bytecode of the contract (hex)",
+bytecode: the runtime bytecode of the contract (hex), or its creation bytecode
+          with decompile-creation
+
+signatures: (optional, decompile and storage-layout modes only) file of function,
+            event and error signatures used to name selectors, in addition to the
+            common ones (one `signature` or `0x<hash> signature` per line, or JSON)",
     );
 
-    if std::env::args().len() != 3 {
+    if std::env::args().len() != 3 && std::env::args().len() != 4 {
         println!("{}", info_msg);
         return;
     }
 
     let mode: String = std::env::args().nth(1).unwrap();
     let raw_bytecode: String = std::env::args().nth(2).unwrap();
+    let mut signatures: SignatureDatabase = SignatureDatabase::with_common_signatures();
+    if let Some(signature_file) = std::env::args().nth(3) {
+        if let Err(error) = signatures.load_file(&signature_file) {
+            report_error(error);
+            return;
+        }
+    }
     match mode.as_str() {
         "decompile" => {
             if file_exists("./decompiled.txt") {
                 println!("./decompiled.txt already exists, please remove it.");
                 return;
             }
-            if let Some(bytecode) = Bytecode::from(&raw_bytecode) {
-                print!("\nBuilding graph...");
-                let mut graph: Graph = Graph::from(&bytecode);
-                print!("\tDone\nDetecting loops...");
-                let mut a_graph: AcyclicGraph = AcyclicGraph::from(&mut graph);
-                print!("\tDone\nDetecting internal function...");
-                let skeleton: Skeleton = Skeleton::build(&mut a_graph);
-                print!("\tDone\nBuilding execution flow...");
-                let execution_flow_with_opcodes: ExecutionFlowWithOpcodes =
-                    convert_skeleton_to_execution_flow(&skeleton);
-                let execution_flow_with_vars: ExecutionFlowWithVars =
-                    convert_opcode_flow_to_var_flow(&execution_flow_with_opcodes);
-                write_file("decompiled.txt", &execution_flow_with_vars.to_string());
-                print!("\tDone\n");
-                println!("Success, (pseudo) source code stored in ./decompiled.txt");
-            } else {
-                println!("Cannot read bytecode, please ensure it's hexadecimal.");
+            match Decompiler::from_hex(&raw_bytecode) {
+                Ok(decompiler) => {
+                    print!("\nBuilding graph...");
+                    match decompiler.decompile_with(&mut ProgressPrinter) {
+                        Ok(mut decompilation) => {
+                            decompilation.apply_signatures(&signatures);
+                            write_file("decompiled.txt", &decompilation.to_string());
+                            print!("\tDone\n");
+                            println!("Success, (pseudo) source code stored in ./decompiled.txt");
+                        }
+                        Err(error) => println!("\nDecompilation failed: {}", error),
+                    }
+                }
+                Err(error) => report_error(error),
+            }
+        }
+        "decompile-creation" => {
+            if file_exists("./decompiled.txt") {
+                println!("./decompiled.txt already exists, please remove it.");
+                return;
+            }
+            match CreationDecompiler::from_hex(&raw_bytecode) {
+                Ok(creation_decompiler) => {
+                    print!("\nBuilding graph...");
+                    match creation_decompiler.decompile_with(&mut ProgressPrinter) {
+                        Ok(mut decompilation) => {
+                            decompilation.apply_signatures(&signatures);
+                            write_file("decompiled.txt", &decompilation.to_string());
+                            print!("\tDone\n");
+                            println!("Success, (pseudo) source code stored in ./decompiled.txt");
+                        }
+                        Err(error) => println!("\nDecompilation failed: {}", error),
+                    }
+                }
+                Err(error) => report_error(error),
+            }
+        }
+        "storage-layout" => {
+            if file_exists("./storage_layout.txt") || file_exists("./storage_layout.json") {
+                println!("./storage_layout.txt or ./storage_layout.json already exists, please remove it.");
+                return;
+            }
+            match Decompiler::from_hex(&raw_bytecode) {
+                Ok(decompiler) => {
+                    print!("\nBuilding graph...");
+                    match decompiler.decompile_with(&mut ProgressPrinter) {
+                        Ok(mut decompilation) => {
+                            decompilation.apply_signatures(&signatures);
+                            let storage_layout: StorageLayout = decompilation.get_storage_layout();
+                            write_file("storage_layout.txt", &storage_layout.to_string());
+                            write_file("storage_layout.json", &storage_layout.to_json());
+                            print!("\tDone\n");
+                            println!("Success, storage layout stored in ./storage_layout.txt and ./storage_layout.json");
+                        }
+                        Err(error) => println!("\nDecompilation failed: {}", error),
+                    }
+                }
+                Err(error) => report_error(error),
             }
         }
         "disassemble" => {
@@ -70,33 +144,48 @@ bytecode: the runtime bytecode of the contract (hex)",
                 println!("./opcodes.txt already exists, please remove it.");
                 return;
             }
-            if let Some(bytecode) = Bytecode::from(&raw_bytecode) {
-                write_file("./opcodes.txt", &bytecode.to_string());
-                println!("Success, opcodes stored in ./opcodes.txt");
-            } else {
-                println!("Cannot read bytecode, please ensure it's hexadecimal.");
+            match Decompiler::from_hex(&raw_bytecode) {
+                Ok(decompiler) => {
+                    let disassembly: String = match decompiler.get_eof_container() {
+                        Some(eof_container) => eof_container.to_string(),
+                        None => decompiler.get_bytecode().to_string(),
+                    };
+                    write_file("./opcodes.txt", &disassembly);
+                    println!("Success, opcodes stored in ./opcodes.txt");
+                }
+                Err(error) => report_error(error),
             }
         }
-        "metadata" => {
-            let metadata = metadata::get_metadata(&raw_bytecode);
-            let metadata_length = metadata.len();
-            println!("{} metadata found.", metadata_length);
-            for m in metadata {
-                println!("  {}", m);
+        "metadata" => match metadata::get_metadata(&raw_bytecode) {
+            Ok(metadata) => {
+                let metadata_length = metadata.len();
+                println!("{} metadata found.", metadata_length);
+                for m in &metadata {
+                    println!("  {}", m);
+                }
+                if let Some(hardfork) = Hardfork::from_metadata(&metadata) {
+                    println!("Default hardfork for this compiler: {:?}", hardfork);
+                }
             }
-        }
+            Err(error) => report_error(error),
+        },
         "graph" => {
             if file_exists("./graph.txt") {
                 println!("./graph.txt already exists, please remove it.");
                 return;
             }
-            if let Some(bytecode) = Bytecode::from(&raw_bytecode) {
-                println!("\nDrawing graph... (May be long when there are a lot of loops)");
-                let graph_str: String = draw_graph_to_str(&bytecode);
-                write_file("graph.txt", &graph_str);
-                println!("Success, graph stored in ./graph.txt");
-            } else {
-                println!("Cannot read bytecode, please ensure it's hexadecimal.");
+            match Decompiler::from_hex(&raw_bytecode) {
+                Ok(decompiler) => {
+                    println!("\nDrawing graph... (May be long when there are a lot of loops)");
+                    match draw_graph_to_str(decompiler.get_bytecode()) {
+                        Ok(graph_str) => {
+                            write_file("graph.txt", &graph_str);
+                            println!("Success, graph stored in ./graph.txt");
+                        }
+                        Err(error) => report_error(error),
+                    }
+                }
+                Err(error) => report_error(error),
             }
         }
         _ => {
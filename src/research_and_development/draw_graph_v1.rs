@@ -1,6 +1,7 @@
 use crate::{
     bytecode_reader::bytecode::Bytecode,
     create_graph::graph::Graph,
+    error::PellucidError,
     tools::utils::{get_max_key, get_sorted_keys, iter_int, map_values_to_index, max_mapped_value},
 };
 use std::{
@@ -638,11 +639,11 @@ impl<'a> RectangleSet<'a> {
     }
 }
 
-pub fn draw_graph_to_str(bytecode: &Bytecode) -> String {
-    let graph: Graph = Graph::from(bytecode);
+pub fn draw_graph_to_str(bytecode: &Bytecode) -> Result<String, PellucidError> {
+    let graph: Graph = Graph::from(bytecode)?;
     let rectangle_set: RectangleSet = RectangleSet::new(&graph);
     let final_grid: Grid = rectangle_set.to_grid(bytecode);
-    return final_grid.to_string();
+    return Ok(final_grid.to_string());
 }
 
 #[test]
@@ -650,6 +651,10 @@ fn test_draw_graph() {
     let path: String = String::from("./contracts/simple/contract_0/bytecode.txt");
     println!("Loading {}", path);
     let bytecode_string: String = std::fs::read_to_string(path).expect("Unable to read file.");
-    let bytecode: Bytecode = Bytecode::from(&bytecode_string).unwrap();
-    print!("{}", draw_graph_to_str(&bytecode));
+    let bytecode: Bytecode = Bytecode::from(
+        &bytecode_string,
+        crate::bytecode_reader::hardfork::Hardfork::default(),
+    )
+    .unwrap();
+    print!("{}", draw_graph_to_str(&bytecode).unwrap());
 }
@@ -80,25 +80,24 @@ pub fn draw_block_offspring<'a>(
 mod tests {
     use crate::{
         bytecode_reader::bytecode::Bytecode,
+        bytecode_reader::hardfork::Hardfork,
         create_graph::{block::Block, graph::Graph},
         tools::utils::{hex_to_usize, read_file},
     };
 
     #[test]
     fn test_draw_graph() {
-        let raw_bytecode: String =
-            read_file("./contracts/simple/contract_0/bytecode.txt");
-        let bytecode: Bytecode = Bytecode::from(&raw_bytecode).unwrap();
-        let graph: Graph = Graph::from(&bytecode);
+        let raw_bytecode: String = read_file("./contracts/simple/contract_0/bytecode.txt");
+        let bytecode: Bytecode = Bytecode::from(&raw_bytecode, Hardfork::default()).unwrap();
+        let graph: Graph = Graph::from(&bytecode).unwrap();
         //super::draw_graph(&graph, "temp.png");
         let _ = graph;
     }
     #[test]
     fn test_draw_block_area() {
-        let raw_bytecode: String =
-            read_file("./contracts/simple/contract_0/bytecode.txt");
-        let bytecode: Bytecode = Bytecode::from(&raw_bytecode).unwrap();
-        let graph: Graph = Graph::from(&bytecode);
+        let raw_bytecode: String = read_file("./contracts/simple/contract_0/bytecode.txt");
+        let bytecode: Bytecode = Bytecode::from(&raw_bytecode, Hardfork::default()).unwrap();
+        let graph: Graph = Graph::from(&bytecode).unwrap();
         let block: Block = graph.get_block(hex_to_usize("12d"));
         //super::draw_block_area(&HashSet::from([block.clone()]), 3, Some(block), "temp.png");
         let _ = block;
@@ -97,6 +97,7 @@ mod tests {
 
     use super::*;
     use crate::bytecode_reader::bytecode::Bytecode;
+    use crate::bytecode_reader::hardfork::Hardfork;
 
     use std::fs;
 
@@ -105,8 +106,9 @@ mod tests {
         let bytecode_string: String =
             fs::read_to_string("./contracts/simple/contract_0/bytecode.txt")
                 .expect("Unable to read file.");
-        let bytecode_test: Bytecode = Bytecode::from(&bytecode_string).unwrap();
-        let graph = Graph::from(&bytecode_test);
+        let bytecode_test: Bytecode =
+            Bytecode::from(&bytecode_string, Hardfork::default()).unwrap();
+        let graph = Graph::from(&bytecode_test).unwrap();
         let gml = nodes_to_gml(&graph, HashMap::new());
         let _ = gml;
         //crate::tools::utils::write_file("node_graph.gml", &gml);
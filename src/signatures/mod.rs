@@ -0,0 +1 @@
+pub mod signature_database;
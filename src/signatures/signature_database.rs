@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use primitive_types::U256;
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::error::PellucidError;
+use crate::tools::utils::remove_0x;
+
+const COMMON_SIGNATURES: &str = include_str!("common_signatures.txt");
+
+// Function and custom error selectors share the same 4 bytes space, events are named by their
+// whole 32 bytes topic.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SignatureDatabase {
+    selectors: HashMap<u32, String>,
+    events: HashMap<U256, String>,
+}
+
+impl SignatureDatabase {
+    pub fn new() -> Self {
+        return SignatureDatabase::default();
+    }
+
+    // ERC20, ERC721, ERC1155, ERC165 and Ownable functions, events and errors.
+    pub fn with_common_signatures() -> Self {
+        let mut signatures: SignatureDatabase = SignatureDatabase::new();
+        signatures
+            .load_text(COMMON_SIGNATURES)
+            .expect("bundled signatures are well formed");
+        return signatures;
+    }
+
+    // JSON files start with `{` or `[`, anything else is read as text.
+    pub fn load_file(&mut self, path: &str) -> Result<(), PellucidError> {
+        let content: String = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) => {
+                return Err(PellucidError::SignatureFileMalformed(format!(
+                    "cannot read {}: {}",
+                    path, error
+                )))
+            }
+        };
+        if content.trim_start().starts_with(['{', '[']) {
+            return self.load_json(&content);
+        }
+        return self.load_text(&content);
+    }
+
+    // One entry per line: `<selector or topic> <signature>` (separated by a space, `,` or `:`)
+    // or only the signature. Empty lines and lines starting with `#` are ignored.
+    pub fn load_text(&mut self, content: &str) -> Result<(), PellucidError> {
+        for (line_index, line) in content.lines().enumerate() {
+            let line: &str = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let separator: Option<usize> =
+                line.find(|c: char| c.is_whitespace() || c == ',' || c == ':');
+            let result: Result<(), String> = match separator {
+                Some(separator) if parse_hash(&line[..separator]).is_some() => {
+                    self.add_hashed_signature(&line[..separator], line[separator + 1..].trim())
+                }
+                _ => self.add_signature(line),
+            };
+            if let Err(reason) = result {
+                return Err(PellucidError::SignatureFileMalformed(format!(
+                    "line {}: {}",
+                    line_index + 1,
+                    reason
+                )));
+            }
+        }
+        return Ok(());
+    }
+
+    // Either `{"0xa9059cbb": "transfer(address,uint256)", ...}`, where values may also be lists of
+    // colliding signatures (the first one is kept), or a list of signatures.
+    pub fn load_json(&mut self, content: &str) -> Result<(), PellucidError> {
+        let json: serde_json::Value = match serde_json::from_str(content) {
+            Ok(json) => json,
+            Err(error) => return Err(PellucidError::SignatureFileMalformed(error.to_string())),
+        };
+        let result: Result<(), String> = match &json {
+            serde_json::Value::Object(entries) => entries.iter().try_for_each(|(hash, value)| {
+                let signature: Option<&str> = match value {
+                    serde_json::Value::Array(signatures) => {
+                        signatures.first().and_then(|signature| signature.as_str())
+                    }
+                    _ => value.as_str(),
+                };
+                match signature {
+                    Some(signature) => self.add_hashed_signature(hash, signature),
+                    None => Err(format!("no signature for {}", hash)),
+                }
+            }),
+            serde_json::Value::Array(signatures) => {
+                signatures
+                    .iter()
+                    .try_for_each(|value| match value.as_str() {
+                        Some(signature) => self.add_signature(signature),
+                        None => Err(format!("{} is not a signature", value)),
+                    })
+            }
+            _ => Err("expecting an object or a list".to_owned()),
+        };
+        return result.map_err(PellucidError::SignatureFileMalformed);
+    }
+
+    // Without its hash, a signature may be a function, an error or an event.
+    pub fn add_signature(&mut self, signature: &str) -> Result<(), String> {
+        check_signature(signature)?;
+        let hash: [u8; 32] = keccak256(signature.as_bytes());
+        self.selectors.insert(
+            u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]),
+            signature.to_owned(),
+        );
+        self.events
+            .insert(U256::from_big_endian(&hash), signature.to_owned());
+        return Ok(());
+    }
+
+    // The hash is trusted, it is not checked against the signature.
+    pub fn add_hashed_signature(&mut self, hash: &str, signature: &str) -> Result<(), String> {
+        check_signature(signature)?;
+        match parse_hash(hash) {
+            Some(Hash::Selector(selector)) => {
+                self.selectors.insert(selector, signature.to_owned());
+            }
+            Some(Hash::Topic(topic)) => {
+                self.events.insert(topic, signature.to_owned());
+            }
+            None => return Err(format!("{} is neither a selector nor a topic", hash)),
+        }
+        return Ok(());
+    }
+
+    pub fn get_selector_signature(&self, selector: u32) -> Option<&String> {
+        return self.selectors.get(&selector);
+    }
+
+    pub fn get_event_signature(&self, topic: U256) -> Option<&String> {
+        return self.events.get(&topic);
+    }
+}
+
+enum Hash {
+    Selector(u32),
+    Topic(U256),
+}
+
+fn parse_hash(hash: &str) -> Option<Hash> {
+    let hash: &str = remove_0x(hash);
+    if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hash.len() {
+        8 => return u32::from_str_radix(hash, 16).ok().map(Hash::Selector),
+        64 => return U256::from_str_radix(hash, 16).ok().map(Hash::Topic),
+        _ => return None,
+    }
+}
+
+fn check_signature(signature: &str) -> Result<(), String> {
+    let is_valid: bool = match signature.find('(') {
+        Some(parenthesis) => parenthesis > 0 && signature.ends_with(')'),
+        None => false,
+    };
+    if !is_valid || signature.contains(char::is_whitespace) {
+        return Err(format!("{} is not a signature", signature));
+    }
+    return Ok(());
+}
+
+// `transfer(address,uint256)` -> (`transfer`, [`address`, `uint256`]), tuples are kept whole.
+pub fn split_signature(signature: &str) -> (&str, Vec<&str>) {
+    let (name, types) = match signature.find('(') {
+        Some(parenthesis) => (&signature[..parenthesis], &signature[parenthesis + 1..]),
+        None => return (signature, Vec::new()),
+    };
+    let types: &str = types.strip_suffix(')').unwrap_or(types);
+    let mut split_types: Vec<&str> = Vec::new();
+    let mut depth: usize = 0;
+    let mut type_start: usize = 0;
+    for (index, c) in types.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                split_types.push(&types[type_start..index]);
+                type_start = index + 1;
+            }
+            _ => (),
+        }
+    }
+    if !types.is_empty() {
+        split_types.push(&types[type_start..]);
+    }
+    return (name, split_types);
+}
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher: Keccak = Keccak::v256();
+    let mut hash: [u8; 32] = [0; 32];
+    hasher.update(data);
+    hasher.finalize(&mut hash);
+    return hash;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_signatures() {
+        let signatures: SignatureDatabase = SignatureDatabase::with_common_signatures();
+        assert_eq!(
+            signatures.get_selector_signature(0xa9059cbb).unwrap(),
+            "transfer(address,uint256)"
+        );
+        assert_eq!(
+            signatures.get_selector_signature(0x4e487b71).unwrap(),
+            "Panic(uint256)"
+        );
+        let transfer_topic: U256 = U256::from_str_radix(
+            "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",
+            16,
+        )
+        .unwrap();
+        assert_eq!(
+            signatures.get_event_signature(transfer_topic).unwrap(),
+            "Transfer(address,address,uint256)"
+        );
+    }
+
+    #[test]
+    fn test_split_signature() {
+        assert_eq!(split_signature("f()"), ("f", vec![]));
+        assert_eq!(
+            split_signature("g(uint256,(address,bytes)[],bool)"),
+            ("g", vec!["uint256", "(address,bytes)[]", "bool"])
+        );
+    }
+
+    #[test]
+    fn test_load_signatures() {
+        let mut signatures: SignatureDatabase = SignatureDatabase::new();
+        signatures
+            .load_text("# comment\n0x12345678 foo(uint256)\nabcdef01:bar()\nf()\n")
+            .unwrap();
+        signatures
+            .load_json(r#"{"0x11111111": ["baz(bool)", "collision()"], "22222222": "qux()"}"#)
+            .unwrap();
+        assert_eq!(
+            signatures.get_selector_signature(0x12345678).unwrap(),
+            "foo(uint256)"
+        );
+        assert_eq!(
+            signatures.get_selector_signature(0xabcdef01).unwrap(),
+            "bar()"
+        );
+        assert_eq!(
+            signatures.get_selector_signature(0x26121ff0).unwrap(),
+            "f()"
+        );
+        assert_eq!(
+            signatures.get_selector_signature(0x11111111).unwrap(),
+            "baz(bool)"
+        );
+        assert_eq!(
+            signatures.get_selector_signature(0x22222222).unwrap(),
+            "qux()"
+        );
+
+        assert!(matches!(
+            signatures.load_text("0x12345678 not a signature"),
+            Err(PellucidError::SignatureFileMalformed(_))
+        ));
+        assert!(matches!(
+            signatures.load_json("[1, 2]"),
+            Err(PellucidError::SignatureFileMalformed(_))
+        ));
+    }
+}
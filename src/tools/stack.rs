@@ -54,6 +54,10 @@ impl<E: Debug + Eq + Clone + Debug + Hash + PartialEq> Stack<E> {
         return self.data.iter();
     }
 
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut E> {
+        return self.data.iter_mut();
+    }
+
     pub fn multi_pop(&mut self, n: usize) -> Vec<E> {
         let mut popped_values: Vec<E> = Vec::new();
         for _ in 0..n {